@@ -9,14 +9,105 @@ pub enum AgentState {
 
 pub trait Agent {
     fn update_board(&mut self, board: &Board);
+    /// best move found so far, only to be called after go and while in Go
+    ///
+    /// searching agents block for up to FIRST_DEPTH_TIMEOUT after go until their search has completed its first depth,
+    /// so that a caller polling right away doesn't get (and play) the fallback move. After that, or once the timeout
+    /// has passed without a result, it returns immediately.
     fn get_current_best_move(&mut self) -> Move;
 
     fn get_state(&self) -> AgentState;
+    /// start working on a move for the current board
+    ///
+    /// if the game is already over there is no move to find: the agent stays in Waiting instead, so callers must check
+    /// get_state before asking for a move
     fn go(&mut self);
     fn stop(&mut self);
     fn ponder(&mut self);
 
+    /// precompute whatever the agent needs before its first move, e.g. load a table or expand an opening tree
+    ///
+    /// called once after the agent is created and before the first go, while the client is still connecting and
+    /// negotiating with the server. It runs before any command gets answered, so it must not block for long, let alone
+    /// indefinitely. Does nothing by default.
+    fn warmup(&mut self) {}
+
     fn is_reference(&self) -> bool {
         false
     }
 }
+
+// lets harnesses treat boxed agents of different types uniformly
+impl<A: Agent + ?Sized> Agent for Box<A> {
+    fn update_board(&mut self, board: &Board) {
+        (**self).update_board(board)
+    }
+
+    fn get_current_best_move(&mut self) -> Move {
+        (**self).get_current_best_move()
+    }
+
+    fn get_state(&self) -> AgentState {
+        (**self).get_state()
+    }
+
+    fn go(&mut self) {
+        (**self).go()
+    }
+
+    fn stop(&mut self) {
+        (**self).stop()
+    }
+
+    fn ponder(&mut self) {
+        (**self).ponder()
+    }
+
+    fn warmup(&mut self) {
+        (**self).warmup()
+    }
+
+    fn is_reference(&self) -> bool {
+        (**self).is_reference()
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::{Agent, AgentState};
+    use crate::agent::{FirstMoveAgent, RandomAgent};
+    use crate::kalah::valuation::store_diff_valuation;
+    use crate::{minimax, minimax_reference, pvs, tournament, Board};
+
+    #[test]
+    fn test_terminal_root() {
+        // White has no seeds left, the game is over
+        let board = Board::from_kgp("<3, 10, 4, 0, 0, 0, 1, 2, 1>");
+
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(tournament::MinimaxAgent::new(board.clone())),
+            Box::new(minimax::MinimaxAgent::new(board.clone(), store_diff_valuation)),
+            Box::new(pvs::PVSAgent::new(board.clone(), store_diff_valuation)),
+            Box::new(minimax_reference::MinimaxAgent::new(
+                board.clone(),
+                4,
+                store_diff_valuation,
+            )),
+            Box::new(RandomAgent::new(3, 3)),
+            Box::new(FirstMoveAgent::new(3, 3)),
+        ];
+
+        for mut agent in agents {
+            agent.update_board(&board);
+
+            agent.go();
+            assert_eq!(agent.get_state(), AgentState::Waiting);
+
+            // a stop from the server can still arrive
+            agent.stop();
+            assert_eq!(agent.get_state(), AgentState::Waiting);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::agent::{Agent, FirstMoveAgent, RandomAgent, ShallowAgent};
+use crate::kalah::valuation::{valuation_by_name, ValuationFn};
+use crate::{minimax, pvs, tournament, Board, House};
+
+/// names of the agents agent_by_name knows
+pub const AGENT_NAMES: &[&str] = &["tournament", "minimax", "pvs", "shallow", "random", "first"];
+
+/// most seeds agent_by_names accepts on a board: all of them have to fit into one store, where a player can end up
+/// with every seed
+pub const MAX_TOTAL_SEEDS: u32 = House::MAX as u32;
+
+/// check that board holds at most max_total_seeds seeds, before any game is played on it
+///
+/// Board::try_new and Board::try_from_kgp already refuse boards whose seeds don't fit into a store, this catches the
+/// ones built some other way and lets callers pick a tighter limit
+pub fn check_total_seeds(board: &Board, max_total_seeds: u32) -> Result<(), String> {
+    let total_seeds = board.total_seeds();
+
+    if total_seeds > max_total_seeds {
+        return Err(format!(
+            "Board with {} houses has {total_seeds} seeds, more than the limit of {max_total_seeds}",
+            board.h()
+        ));
+    }
+
+    Ok(())
+}
+
+/// create the agent called name for board, searching with valuation_fn
+///
+/// the tournament agent always uses its own valuation and the random and first move agents don't search at all, so
+/// valuation_fn only matters for minimax, pvs and shallow
+pub fn agent_by_name(name: &str, board: Board, valuation_fn: ValuationFn) -> Option<Box<dyn Agent + Send>> {
+    let mut agent: Box<dyn Agent + Send> = match name {
+        "tournament" => Box::new(tournament::MinimaxAgent::new(board.clone())),
+        "minimax" => Box::new(minimax::MinimaxAgent::new(board.clone(), valuation_fn)),
+        "pvs" => Box::new(pvs::PVSAgent::new(board.clone(), valuation_fn)),
+        "shallow" => Box::new(ShallowAgent::new(board.clone(), 3, valuation_fn)),
+        "random" => Box::new(RandomAgent::new(board.h(), 1)),
+        "first" => Box::new(FirstMoveAgent::new(board.h(), 1)),
+        _ => return None,
+    };
+
+    agent.update_board(&board);
+
+    Some(agent)
+}
+
+/// like agent_by_name, but with the valuation looked up by name as well, and boards over MAX_TOTAL_SEEDS rejected
+pub fn agent_by_names(agent_name: &str, valuation_name: &str, board: Board) -> Result<Box<dyn Agent + Send>, String> {
+    check_total_seeds(&board, MAX_TOTAL_SEEDS)?;
+
+    let valuation_fn =
+        valuation_by_name(valuation_name).ok_or_else(|| format!("Unknown valuation {valuation_name}"))?;
+
+    agent_by_name(agent_name, board, valuation_fn).ok_or_else(|| format!("Unknown agent {agent_name}"))
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::{agent_by_name, agent_by_names, check_total_seeds, AGENT_NAMES, MAX_TOTAL_SEEDS};
+    use crate::agent::AgentState;
+    use crate::kalah::valuation::{store_diff_valuation, VALUATIONS};
+    use crate::{Board, House};
+
+    #[test]
+    fn test_agent_by_name() {
+        for &name in AGENT_NAMES {
+            let agent = agent_by_name(name, Board::new(6, 4), store_diff_valuation).unwrap();
+            assert_eq!(agent.get_state(), AgentState::Waiting);
+        }
+
+        for &(valuation_name, _) in VALUATIONS {
+            assert!(agent_by_names("pvs", valuation_name, Board::new(6, 4)).is_ok());
+        }
+
+        assert!(agent_by_name("alphazero", Board::new(6, 4), store_diff_valuation).is_none());
+        assert!(agent_by_names("pvs", "mobility2", Board::new(6, 4)).is_err());
+    }
+
+    #[test]
+    fn test_check_total_seeds() {
+        let board = Board::new(3, 2);
+        assert_eq!(check_total_seeds(&board, 12), Ok(()));
+        assert!(check_total_seeds(&board, 11).is_err());
+
+        // the largest board whose seeds all fit into a store, and the smallest one whose don't
+        let board = Board::new(128, 255);
+        assert_eq!(board.total_seeds(), 65280);
+        assert!(agent_by_names("first", "store_diff", board).is_ok());
+
+        let board = Board::from_kgp(&format!("<1, 0, 0, {}, 1>", House::MAX - 1));
+        assert_eq!(board.total_seeds(), MAX_TOTAL_SEEDS);
+        assert_eq!(check_total_seeds(&board, MAX_TOTAL_SEEDS), Ok(()));
+
+        assert!(Board::try_new(128, 256).is_err());
+        assert!(Board::try_from_kgp(&format!("<1, 0, 0, {}, 2>", House::MAX - 1)).is_err());
+    }
+}
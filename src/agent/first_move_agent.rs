@@ -10,7 +10,6 @@ pub struct FirstMoveAgent {
 }
 
 impl FirstMoveAgent {
-    #[allow(dead_code)]
     pub fn new(h: u8, s: u16) -> Self {
         FirstMoveAgent {
             state: AgentState::Waiting,
@@ -27,6 +26,8 @@ impl Agent for FirstMoveAgent {
     fn get_current_best_move(&mut self) -> Move {
         assert_eq!(self.state, AgentState::Go);
 
+        self.state = AgentState::Waiting;
+
         *self.board.legal_moves(Player::White).first().unwrap()
     }
 
@@ -35,7 +36,9 @@ impl Agent for FirstMoveAgent {
     }
 
     fn go(&mut self) {
-        self.state = AgentState::Go;
+        if self.board.has_legal_move() {
+            self.state = AgentState::Go;
+        }
     }
 
     fn stop(&mut self) {
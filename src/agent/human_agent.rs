@@ -0,0 +1,135 @@
+use std::io::{BufRead, Write};
+
+use log::info;
+
+use crate::agent::{Agent, AgentState};
+use crate::{Board, Move, Player};
+
+/// agent that asks a human at the terminal for its moves
+///
+/// get_current_best_move blocks until a legal move has been entered; invalid input gets reprompted
+pub struct HumanAgent {
+    state: AgentState,
+
+    board: Board,
+
+    input: Box<dyn BufRead + Send>,
+}
+
+impl HumanAgent {
+    pub fn new(h: u8, s: u16) -> Self {
+        HumanAgent::with_input(h, s, Box::new(std::io::BufReader::new(std::io::stdin())))
+    }
+
+    /// read moves from input instead of stdin
+    pub fn with_input(h: u8, s: u16, input: Box<dyn BufRead + Send>) -> Self {
+        HumanAgent {
+            state: AgentState::Waiting,
+            board: Board::new(h, s),
+            input,
+        }
+    }
+
+    fn print_board(&self) {
+        // the human is always "our" side, at the bottom with house numbers below
+        let width = self.board.display_width();
+        let labels: String = (1..=self.board.h()).map(|house| format!("{house:>width$} ")).collect();
+
+        println!("\n{}\n{:width$}   {labels}", self.board, "");
+
+        // hints for the moves that earn another turn or capture something
+        for move_ in self.board.legal_moves(Player::White) {
+            let preview = self.board.move_preview(move_);
+
+            if preview.bonus {
+                println!("  house {move_} moves again");
+            }
+
+            if let Some((_, seeds)) = preview.capture {
+                println!("  house {move_} captures {seeds} seeds");
+            }
+        }
+
+        println!();
+    }
+
+    fn read_move(&mut self) -> Move {
+        loop {
+            print!("Your move (house 1-{}): ", self.board.h());
+            std::io::stdout().flush().unwrap();
+
+            let mut line = String::new();
+
+            if self.input.read_line(&mut line).unwrap() == 0 {
+                info!("Input closed, exiting");
+                std::process::exit(0);
+            }
+
+            match line.parse::<Move>() {
+                Ok(move_) if move_.house() < self.board.h() && self.board.is_legal_move(move_) => return move_,
+                Ok(move_) if move_.house() < self.board.h() => println!("House {move_} is empty"),
+                Ok(move_) => println!("There is no house {move_}"),
+                Err(err) => println!("{err}"),
+            }
+        }
+    }
+}
+
+impl Agent for HumanAgent {
+    fn update_board(&mut self, board: &Board) {
+        self.board = board.clone();
+    }
+
+    fn get_current_best_move(&mut self) -> Move {
+        assert_eq!(self.state, AgentState::Go);
+
+        self.print_board();
+        let move_ = self.read_move();
+
+        self.state = AgentState::Waiting;
+
+        move_
+    }
+
+    fn get_state(&self) -> AgentState {
+        self.state
+    }
+
+    fn go(&mut self) {
+        if self.board.has_legal_move() {
+            self.state = AgentState::Go;
+        }
+    }
+
+    fn stop(&mut self) {
+        self.state = AgentState::Waiting;
+    }
+
+    fn ponder(&mut self) {
+        self.state = AgentState::Ponder;
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::HumanAgent;
+    use crate::agent::{Agent, AgentState};
+    use crate::{Board, Move, Player};
+
+    #[test]
+    fn test_reprompt_invalid_input() {
+        // garbage, out of range, empty house, then a legal move
+        let input = Cursor::new("abc\n7\n1\n2\n");
+
+        let mut agent = HumanAgent::with_input(3, 3, Box::new(input));
+        agent.update_board(&Board::from_kgp("<3, 0, 0, 0, 3, 3, 3, 3, 3>"));
+
+        agent.go();
+        assert_eq!(agent.get_current_best_move(), Move::new(1, Player::White));
+        assert_eq!(agent.get_state(), AgentState::Waiting);
+    }
+}
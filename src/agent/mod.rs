@@ -1,7 +1,21 @@
 mod _agent;
+mod factory;
 mod first_move_agent;
+mod human_agent;
+mod opening_agent;
 mod random_agent;
+mod recording_agent;
+mod shallow_agent;
+mod time_policy;
+mod watchdog;
 
 pub use _agent::{Agent, AgentState};
+pub use factory::{agent_by_name, agent_by_names, check_total_seeds, AGENT_NAMES, MAX_TOTAL_SEEDS};
 pub use first_move_agent::FirstMoveAgent;
+pub use human_agent::HumanAgent;
+pub use opening_agent::OpeningAgent;
 pub use random_agent::RandomAgent;
+pub use recording_agent::{RecordingAgent, ReplayAgent};
+pub use shallow_agent::ShallowAgent;
+pub use time_policy::{TimeAllocationPolicy, CLOCK_RESERVE};
+pub use watchdog::{fallback_move, lock_search_when, worker_panic, Watchdog, FIRST_DEPTH_TIMEOUT, WATCHDOG_TIMEOUT};
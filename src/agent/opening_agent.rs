@@ -0,0 +1,142 @@
+use log::{info, warn};
+
+use crate::agent::{Agent, AgentState};
+use crate::{Board, Move, Player};
+
+/// wraps agent, but plays opening_move right away instead of searching on the start position
+///
+/// meant for comparing openings: without an opening move (or with one that isn't legal on the board) it's just agent
+pub struct OpeningAgent<A: Agent> {
+    agent: A,
+
+    // house, counted from 0
+    opening_move: Option<u8>,
+
+    board: Board,
+
+    // opening move currently being played instead of a search
+    playing: Option<Move>,
+}
+
+impl<A: Agent> OpeningAgent<A> {
+    pub fn new(agent: A, opening_move: Option<u8>) -> Self {
+        OpeningAgent {
+            agent,
+            opening_move,
+            board: Board::new(1, 1),
+            playing: None,
+        }
+    }
+
+    fn opening_move(&self) -> Option<Move> {
+        let house = self.opening_move?;
+
+        if !self.board.is_start_position() {
+            return None;
+        }
+
+        let move_ = Move::new(house, Player::White);
+
+        if house >= self.board.h() || !self.board.is_legal_move(move_) {
+            warn!(
+                "Opening move {move_} isn't legal on a board with {} houses, searching",
+                self.board.h()
+            );
+            return None;
+        }
+
+        Some(move_)
+    }
+}
+
+impl<A: Agent> Agent for OpeningAgent<A> {
+    fn update_board(&mut self, board: &Board) {
+        self.board = board.clone();
+        self.agent.update_board(board);
+    }
+
+    fn get_current_best_move(&mut self) -> Move {
+        match self.playing.take() {
+            Some(move_) => move_,
+            None => self.agent.get_current_best_move(),
+        }
+    }
+
+    fn get_state(&self) -> AgentState {
+        match self.playing {
+            Some(_) => AgentState::Go,
+            None => self.agent.get_state(),
+        }
+    }
+
+    fn go(&mut self) {
+        self.playing = self.opening_move();
+
+        match self.playing {
+            Some(move_) => info!("Playing opening move {move_}"),
+            None => self.agent.go(),
+        }
+    }
+
+    fn stop(&mut self) {
+        if self.playing.take().is_none() {
+            self.agent.stop();
+        }
+    }
+
+    fn ponder(&mut self) {
+        self.agent.ponder();
+    }
+
+    fn warmup(&mut self) {
+        self.agent.warmup();
+    }
+
+    fn is_reference(&self) -> bool {
+        self.agent.is_reference()
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::OpeningAgent;
+    use crate::agent::{Agent, AgentState, FirstMoveAgent};
+    use crate::{Board, Move, Player};
+
+    fn first_move(agent: &mut impl Agent, board: &Board) -> (Move, AgentState) {
+        agent.update_board(board);
+        agent.go();
+        let move_ = agent.get_current_best_move();
+        let state = agent.get_state();
+        agent.stop();
+
+        (move_, state)
+    }
+
+    #[test]
+    fn test_opening_move() {
+        let start = Board::new(6, 6);
+
+        let mut agent = OpeningAgent::new(FirstMoveAgent::new(6, 6), Some(0));
+        assert_eq!(
+            first_move(&mut agent, &start),
+            (Move::new(0, Player::White), AgentState::Waiting)
+        );
+
+        // later positions are left to the wrapped agent
+        let mut board = start.clone();
+        board.apply_move(Move::new(0, Player::White));
+        board.flip_board();
+
+        let mut agent = OpeningAgent::new(FirstMoveAgent::new(6, 6), Some(3));
+        assert_eq!(first_move(&mut agent, &board).0, Move::new(0, Player::White));
+
+        assert_eq!(first_move(&mut agent, &start).0, Move::new(3, Player::White));
+
+        // out of range: search instead
+        let mut agent = OpeningAgent::new(FirstMoveAgent::new(6, 6), Some(6));
+        assert_eq!(first_move(&mut agent, &start).0, Move::new(0, Player::White));
+    }
+}
@@ -2,20 +2,33 @@ use rand::seq::SliceRandom;
 use rand::thread_rng;
 
 use crate::agent::{Agent, AgentState};
+use crate::util::random::Rng;
 use crate::{Board, Move, Player};
 
 pub struct RandomAgent {
     state: AgentState,
 
     board: Board,
+
+    // None: use thread_rng
+    rng: Option<Rng>,
 }
 
 impl RandomAgent {
-    #[allow(dead_code)]
+    /// agent picking its moves with thread_rng, i.e. differently in every run
     pub fn new(h: u8, s: u16) -> Self {
         RandomAgent {
             state: AgentState::Waiting,
             board: Board::new(h, s),
+            rng: None,
+        }
+    }
+
+    /// agent picking its moves with rng, so the same seed always gives the same moves for the same positions
+    pub fn with_rng(h: u8, s: u16, rng: Rng) -> Self {
+        RandomAgent {
+            rng: Some(rng),
+            ..RandomAgent::new(h, s)
         }
     }
 }
@@ -30,7 +43,12 @@ impl Agent for RandomAgent {
 
         self.state = AgentState::Waiting;
 
-        *self.board.legal_moves(Player::White).choose(&mut thread_rng()).unwrap()
+        let legal_moves = self.board.legal_moves(Player::White);
+
+        match self.rng.as_mut() {
+            Some(rng) => legal_moves[(rng.gen_u64() % legal_moves.len() as u64) as usize],
+            None => *legal_moves.choose(&mut thread_rng()).unwrap(),
+        }
     }
 
     fn get_state(&self) -> AgentState {
@@ -38,7 +56,9 @@ impl Agent for RandomAgent {
     }
 
     fn go(&mut self) {
-        self.state = AgentState::Go;
+        if self.board.has_legal_move() {
+            self.state = AgentState::Go;
+        }
     }
 
     fn stop(&mut self) {
@@ -49,3 +69,41 @@ impl Agent for RandomAgent {
         self.state = AgentState::Ponder;
     }
 }
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::RandomAgent;
+    use crate::agent::Agent;
+    use crate::util::random::Rng;
+    use crate::{Board, Move};
+
+    fn play_out(agent: &mut RandomAgent) -> Vec<Move> {
+        let mut board = Board::new(6, 4);
+        let mut moves = Vec::new();
+
+        // the agent plays both sides
+        while board.has_legal_move() {
+            agent.update_board(&board);
+            agent.go();
+
+            let move_ = agent.get_current_best_move();
+            moves.push(move_);
+
+            if !board.apply_move(move_) {
+                board.flip_board();
+            }
+        }
+
+        moves
+    }
+
+    #[test]
+    fn test_with_rng_reproducible() {
+        let moves = play_out(&mut RandomAgent::with_rng(6, 4, Rng::new(7)));
+
+        assert_eq!(play_out(&mut RandomAgent::with_rng(6, 4, Rng::new(7))), moves);
+        assert_ne!(play_out(&mut RandomAgent::with_rng(6, 4, Rng::new(8))), moves);
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use log::warn;
+
+use crate::agent::{Agent, AgentState};
+use crate::{Board, Move, Player};
+
+/*====================================================================================================================*/
+
+// one decision per line: the board in KGP notation and the chosen house (1-based), separated by a tab
+
+fn format_record(board: &Board, move_: Move) -> String {
+    format!("{}\t{move_}", board.to_kgp())
+}
+
+fn parse_record(line: &str) -> Result<(String, Move), String> {
+    let (board, move_) = line
+        .split_once('\t')
+        .ok_or_else(|| format!("Missing tab in record \"{line}\""))?;
+
+    Ok((board.to_owned(), move_.parse()?))
+}
+
+/*====================================================================================================================*/
+
+/// wraps agent and writes every move it decides on, together with the board, to a log
+///
+/// a decision is the last move agent returned before it was stopped or finished on its own
+pub struct RecordingAgent<A: Agent> {
+    agent: A,
+
+    board: Board,
+
+    log: Box<dyn Write + Send>,
+
+    // best move of the current go, until it gets recorded
+    pending_move: Option<Move>,
+}
+
+impl<A: Agent> RecordingAgent<A> {
+    /// record to the file at path, which gets truncated
+    pub fn new(agent: A, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = File::create(path)?;
+
+        Ok(RecordingAgent::with_writer(agent, Box::new(BufWriter::new(file))))
+    }
+
+    pub fn with_writer(agent: A, log: Box<dyn Write + Send>) -> Self {
+        RecordingAgent {
+            agent,
+            board: Board::new(1, 1),
+            log,
+            pending_move: None,
+        }
+    }
+
+    fn record(&mut self) {
+        if let Some(move_) = self.pending_move.take() {
+            // flush right away, the log is most interesting after a crash
+            let written = writeln!(self.log, "{}", format_record(&self.board, move_)).and_then(|_| self.log.flush());
+
+            if let Err(err) = written {
+                warn!("Could not record move {move_}: {err}");
+            }
+        }
+    }
+}
+
+impl<A: Agent> Agent for RecordingAgent<A> {
+    fn update_board(&mut self, board: &Board) {
+        self.board = board.clone();
+        self.agent.update_board(board);
+    }
+
+    fn get_current_best_move(&mut self) -> Move {
+        let move_ = self.agent.get_current_best_move();
+        self.pending_move = Some(move_);
+
+        if self.agent.get_state() == AgentState::Waiting {
+            self.record();
+        }
+
+        move_
+    }
+
+    fn get_state(&self) -> AgentState {
+        self.agent.get_state()
+    }
+
+    fn go(&mut self) {
+        self.pending_move = None;
+        self.agent.go();
+    }
+
+    fn stop(&mut self) {
+        self.agent.stop();
+        self.record();
+    }
+
+    fn ponder(&mut self) {
+        self.agent.ponder();
+    }
+
+    fn warmup(&mut self) {
+        self.agent.warmup();
+    }
+
+    fn is_reference(&self) -> bool {
+        self.agent.is_reference()
+    }
+}
+
+/*====================================================================================================================*/
+
+/// plays back the moves of a log written by RecordingAgent, in order
+///
+/// if the game deviates from the log (or the log runs out) it warns and plays the first legal move, so a replay never
+/// gets stuck
+pub struct ReplayAgent {
+    state: AgentState,
+
+    board: Board,
+
+    records: VecDeque<(String, Move)>,
+
+    move_: Option<Move>,
+}
+
+impl ReplayAgent {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let file = File::open(path).map_err(|err| err.to_string())?;
+
+        let records = BufReader::new(file)
+            .lines()
+            .map(|line| parse_record(&line.map_err(|err| err.to_string())?))
+            .collect::<Result<_, _>>()?;
+
+        Ok(ReplayAgent {
+            state: AgentState::Waiting,
+            board: Board::new(1, 1),
+            records,
+            move_: None,
+        })
+    }
+
+    fn next_move(&mut self) -> Move {
+        let first_legal_move = *self.board.legal_moves(Player::White).first().unwrap();
+
+        let Some((board, move_)) = self.records.pop_front() else {
+            warn!("Replay log exhausted, playing {first_legal_move}");
+            return first_legal_move;
+        };
+
+        if board != self.board.to_kgp() {
+            warn!("Replayed game deviates from the log, which has {move_} on {board}");
+        }
+
+        if move_.house() < self.board.h() && self.board.is_legal_move(move_) {
+            move_
+        } else {
+            warn!("Recorded move {move_} is illegal here, playing {first_legal_move}");
+            first_legal_move
+        }
+    }
+}
+
+impl Agent for ReplayAgent {
+    fn update_board(&mut self, board: &Board) {
+        self.board = board.clone();
+    }
+
+    fn get_current_best_move(&mut self) -> Move {
+        assert_eq!(self.state, AgentState::Go);
+
+        self.state = AgentState::Waiting;
+
+        self.move_.unwrap()
+    }
+
+    fn get_state(&self) -> AgentState {
+        self.state
+    }
+
+    fn go(&mut self) {
+        if self.board.has_legal_move() {
+            self.move_ = Some(self.next_move());
+            self.state = AgentState::Go;
+        }
+    }
+
+    fn stop(&mut self) {
+        self.state = AgentState::Waiting;
+    }
+
+    fn ponder(&mut self) {
+        self.state = AgentState::Ponder;
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use super::{RecordingAgent, ReplayAgent};
+    use crate::agent::{Agent, AgentState, RandomAgent};
+    use crate::{Board, Player};
+
+    // Write into a buffer the test can still read after handing it to the agent
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // let agent play both sides of a game, returns all moves it made
+    fn self_play(agent: &mut impl Agent, h: u8, s: u16) -> Vec<String> {
+        let mut board = Board::new(h, s);
+        let mut moves = Vec::new();
+
+        while board.has_legal_move() {
+            agent.update_board(&board);
+            agent.go();
+            let move_ = agent.get_current_best_move();
+            agent.stop();
+
+            moves.push(move_.to_string());
+
+            if !board.apply_move(move_) {
+                board.flip_board();
+            }
+        }
+
+        moves
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let buf = SharedBuf::default();
+
+        let mut recording_agent = RecordingAgent::with_writer(RandomAgent::new(5, 4), Box::new(buf.clone()));
+        let recorded_moves = self_play(&mut recording_agent, 5, 4);
+
+        let log = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(log.lines().count(), recorded_moves.len());
+
+        let path = std::env::temp_dir().join(format!("kalah_test_record_replay_{}.log", std::process::id()));
+        std::fs::write(&path, &log).unwrap();
+
+        let mut replay_agent = ReplayAgent::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(self_play(&mut replay_agent, 5, 4), recorded_moves);
+
+        // log exhausted: still plays
+        replay_agent.update_board(&Board::new(5, 4));
+        replay_agent.go();
+        assert!(Board::new(5, 4)
+            .legal_moves(Player::White)
+            .contains(&replay_agent.get_current_best_move()));
+        assert_eq!(replay_agent.get_state(), AgentState::Waiting);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::agent::{Agent, AgentState};
+use crate::kalah::valuation::{Valuation, ValuationFn};
+use crate::{Board, Move, Player};
+
+/// agent that searches to a small fixed depth right in go, without a search thread
+///
+/// much stronger than FirstMoveAgent or RandomAgent and still done in an instant, which makes it a baseline for
+/// tournaments and a last resort when the clock is nearly out. Like the other searches, bonus moves don't use up depth.
+pub struct ShallowAgent {
+    state: AgentState,
+
+    board: Board,
+
+    depth: u32,
+
+    valuation_fn: ValuationFn,
+
+    // found by go, handed out by get_current_best_move
+    best_move: Option<Move>,
+}
+
+impl ShallowAgent {
+    /// search depth plies deep (at least 1) with valuation_fn
+    pub fn new(board: Board, depth: u32, valuation_fn: ValuationFn) -> Self {
+        ShallowAgent {
+            state: AgentState::Waiting,
+            board,
+            depth: depth.max(1),
+            valuation_fn,
+            best_move: None,
+        }
+    }
+
+    /// plain alpha-beta, from the perspective of the player to move like the other searches
+    fn alpha_beta(&self, board: &Board, remaining_depth: u32, alpha: Valuation, beta: Valuation) -> Valuation {
+        if remaining_depth == 0 || board.is_decided().is_some() {
+            return (self.valuation_fn)(board);
+        }
+
+        let mut best_value = Valuation::TerminalBlackWin { plies: 0 };
+        let mut alpha = alpha;
+
+        for move_ in board.legal_moves(Player::White) {
+            let mut board_after_move = board.clone();
+
+            // the child counts plies from its own position, see minimax
+            let (child_alpha, child_beta) = (alpha.decrease_plies(), beta.decrease_plies());
+
+            let value = if board_after_move.apply_move(move_) {
+                self.alpha_beta(&board_after_move, remaining_depth, child_alpha, child_beta)
+            } else {
+                board_after_move.flip_board();
+                -self.alpha_beta(&board_after_move, remaining_depth - 1, -child_beta, -child_alpha)
+            }
+            .increase_plies();
+
+            best_value = best_value.max(value);
+
+            if value > beta {
+                break;
+            }
+
+            alpha = alpha.max(best_value);
+        }
+
+        best_value
+    }
+
+    /// best move on board, searched even if the game is already decided so there always is one
+    fn search(&self, board: &Board) -> Move {
+        let beta = Valuation::TerminalWhiteWin { plies: 0 };
+        let mut alpha = Valuation::TerminalBlackWin { plies: 0 };
+        let mut best_move = None;
+
+        for move_ in board.legal_moves(Player::White) {
+            let mut board_after_move = board.clone();
+
+            let value = if board_after_move.apply_move(move_) {
+                self.alpha_beta(&board_after_move, self.depth, alpha.decrease_plies(), beta)
+            } else {
+                board_after_move.flip_board();
+                -self.alpha_beta(&board_after_move, self.depth - 1, -beta, -alpha.decrease_plies())
+            }
+            .increase_plies();
+
+            // first move that is strictly better, so equal moves keep the lowest house
+            if best_move.is_none() || value > alpha {
+                best_move = Some(move_);
+                alpha = value;
+            }
+        }
+
+        best_move.expect("No legal move to search")
+    }
+}
+
+impl Agent for ShallowAgent {
+    fn update_board(&mut self, board: &Board) {
+        self.board = board.clone();
+    }
+
+    fn get_current_best_move(&mut self) -> Move {
+        assert_eq!(self.state, AgentState::Go);
+
+        self.state = AgentState::Waiting;
+
+        self.best_move.unwrap()
+    }
+
+    fn get_state(&self) -> AgentState {
+        self.state
+    }
+
+    fn go(&mut self) {
+        if self.board.has_legal_move() {
+            self.best_move = Some(self.search(&self.board));
+            self.state = AgentState::Go;
+        }
+    }
+
+    fn stop(&mut self) {
+        self.state = AgentState::Waiting;
+    }
+
+    fn ponder(&mut self) {
+        self.state = AgentState::Ponder;
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::ShallowAgent;
+    use crate::agent::{Agent, AgentState};
+    use crate::kalah::valuation::store_diff_valuation;
+    use crate::{Board, Move, Player};
+
+    fn best_move(board: &Board, depth: u32) -> Move {
+        let mut agent = ShallowAgent::new(board.clone(), depth, store_diff_valuation);
+        agent.go();
+        assert_eq!(agent.get_state(), AgentState::Go);
+
+        let move_ = agent.get_current_best_move();
+        assert_eq!(agent.get_state(), AgentState::Waiting);
+
+        move_
+    }
+
+    #[test]
+    fn test_shallow_agent() {
+        // White wins in 2 plies: house 3 lands in the store, then house 2 captures the 3 seeds opposite of house 3
+        let board = Board::from_kgp("<3, 2, 0, 0, 1, 1, 3, 1, 1>");
+        for depth in 1..=3 {
+            assert_eq!(best_move(&board, depth), Move::new(2, Player::White), "depth {depth}");
+        }
+
+        // decided, and the last legal move
+        let decided = Board::from_kgp("<3, 5, 0, 1, 1, 1, 1, 0, 0>");
+        assert!(decided.legal_moves(Player::White).contains(&best_move(&decided, 3)));
+
+        let last_move = Board::from_kgp("<3, 4, 4, 0, 0, 1, 1, 1, 1>");
+        assert_eq!(best_move(&last_move, 0), Move::new(2, Player::White));
+
+        // nothing to search once the game is over
+        let mut agent = ShallowAgent::new(Board::from_kgp("<3, 5, 4, 0, 0, 0, 0, 0, 0>"), 3, store_diff_valuation);
+        agent.go();
+        assert_eq!(agent.get_state(), AgentState::Waiting);
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// part of the clock that no policy hands out, for the latency of sending the move and the server noticing it
+pub const CLOCK_RESERVE: Duration = Duration::from_millis(200);
+
+/// how much of its clock the agent may spend on a move
+///
+/// the server announces the clock (KGP's time:clock) before a state, the policy turns it into the time until the
+/// agent's move gets sent. Without a clock only Fixed limits the time, the others leave it to the server's stop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeAllocationPolicy {
+    /// the same time for every move, but never more than the clock minus CLOCK_RESERVE
+    Fixed(Duration),
+
+    /// this fraction (0 to 1) of the clock
+    FractionOfRemaining(f32),
+
+    /// an even share of the clock for the n moves assumed to still be left
+    MovesToGo { n: u32 },
+}
+
+impl TimeAllocationPolicy {
+    /// 5% of the clock, i.e. planning for 20 more moves on every move, which leaves most of the clock for later
+    pub fn new() -> Self {
+        TimeAllocationPolicy::FractionOfRemaining(0.05)
+    }
+
+    /// time to spend on the next move with clock left, None if the policy leaves it to the server
+    pub fn think_time(&self, clock: Option<Duration>) -> Option<Duration> {
+        let usable = clock.map(|clock| clock.saturating_sub(CLOCK_RESERVE));
+
+        let think_time = match (*self, usable) {
+            (TimeAllocationPolicy::Fixed(think_time), None) => return Some(think_time),
+            (_, None) => return None,
+            (TimeAllocationPolicy::Fixed(think_time), Some(_)) => think_time,
+            (TimeAllocationPolicy::FractionOfRemaining(fraction), Some(usable)) => {
+                usable.mul_f64(fraction.clamp(0.0, 1.0) as f64)
+            }
+            (TimeAllocationPolicy::MovesToGo { n }, Some(usable)) => usable / n.max(1),
+        };
+
+        usable.map(|usable| think_time.min(usable))
+    }
+}
+
+impl Default for TimeAllocationPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for TimeAllocationPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeAllocationPolicy::Fixed(think_time) => write!(f, "fixed:{}", think_time.as_secs_f64()),
+            TimeAllocationPolicy::FractionOfRemaining(fraction) => write!(f, "fraction:{fraction}"),
+            TimeAllocationPolicy::MovesToGo { n } => write!(f, "moves:{n}"),
+        }
+    }
+}
+
+impl FromStr for TimeAllocationPolicy {
+    type Err = String;
+
+    /// parse fixed:SECONDS, fraction:F (between 0 and 1) or moves:N (at least 1)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid time policy \"{s}\", expected fixed:SECONDS, fraction:F or moves:N");
+
+        let (kind, value) = s.trim().split_once(':').ok_or_else(invalid)?;
+
+        match kind {
+            "fixed" => {
+                let secs: f64 = value.parse().map_err(|_| invalid())?;
+                let think_time = Duration::try_from_secs_f64(secs).map_err(|_| invalid())?;
+                Ok(TimeAllocationPolicy::Fixed(think_time))
+            }
+            "fraction" => match value.parse::<f32>() {
+                Ok(fraction) if (0.0..=1.0).contains(&fraction) => {
+                    Ok(TimeAllocationPolicy::FractionOfRemaining(fraction))
+                }
+                _ => Err(invalid()),
+            },
+            "moves" => match value.parse::<u32>() {
+                Ok(n) if n > 0 => Ok(TimeAllocationPolicy::MovesToGo { n }),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{TimeAllocationPolicy, CLOCK_RESERVE};
+
+    fn secs(secs: f64) -> Duration {
+        Duration::from_secs_f64(secs)
+    }
+
+    #[test]
+    fn test_fixed() {
+        let policy = TimeAllocationPolicy::Fixed(secs(2.0));
+
+        // the same with any clock that has the time, and without one
+        assert_eq!(policy.think_time(None), Some(secs(2.0)));
+        assert_eq!(policy.think_time(Some(secs(60.0))), Some(secs(2.0)));
+        assert_eq!(policy.think_time(Some(secs(2.0) + CLOCK_RESERVE)), Some(secs(2.0)));
+
+        // but never past the reserve
+        assert_eq!(policy.think_time(Some(secs(1.0))), Some(secs(1.0) - CLOCK_RESERVE));
+    }
+
+    #[test]
+    fn test_low_clock() {
+        let policies = [
+            TimeAllocationPolicy::Fixed(secs(2.0)),
+            TimeAllocationPolicy::FractionOfRemaining(0.5),
+            TimeAllocationPolicy::MovesToGo { n: 1 },
+            TimeAllocationPolicy::new(),
+        ];
+
+        for policy in policies {
+            // a clock about to run out leaves barely anything, and nothing at all once the reserve is touched
+            let think_time = policy.think_time(Some(CLOCK_RESERVE + secs(0.01))).unwrap();
+            assert!(think_time <= secs(0.01), "{policy}: {think_time:?}");
+
+            assert_eq!(
+                policy.think_time(Some(CLOCK_RESERVE / 2)),
+                Some(Duration::ZERO),
+                "{policy}"
+            );
+            assert_eq!(
+                policy.think_time(Some(Duration::ZERO)),
+                Some(Duration::ZERO),
+                "{policy}"
+            );
+        }
+
+        // without a clock the server decides
+        assert_eq!(TimeAllocationPolicy::new().think_time(None), None);
+        assert_eq!(TimeAllocationPolicy::MovesToGo { n: 10 }.think_time(None), None);
+
+        let clock = secs(10.0) + CLOCK_RESERVE;
+        let think_time = TimeAllocationPolicy::new().think_time(Some(clock)).unwrap();
+        assert_eq!(think_time.as_millis(), 500);
+        assert_eq!(
+            TimeAllocationPolicy::MovesToGo { n: 4 }.think_time(Some(clock)),
+            Some(secs(2.5))
+        );
+    }
+
+    #[test]
+    fn test_parse_time_policy() {
+        for policy in [
+            TimeAllocationPolicy::Fixed(secs(2.5)),
+            TimeAllocationPolicy::FractionOfRemaining(0.1),
+            TimeAllocationPolicy::MovesToGo { n: 30 },
+        ] {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+
+        for invalid in ["", "fixed", "fixed:-1", "fraction:1.5", "moves:0", "moves:x", "clock:3"] {
+            assert!(invalid.parse::<TimeAllocationPolicy>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::kalah::Evaluator;
+use crate::{Board, Move, Player};
+
+/// how long a search may go without visiting a single node before it's considered stuck
+///
+/// searches visit millions of nodes per second, so this only triggers for a worker that is blocked or panicked
+pub const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// how long get_current_best_move waits for the first completed search depth before settling for the fallback move
+pub const FIRST_DEPTH_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// notices when a search thread stops making progress
+///
+/// the worker bumps a shared progress counter (e.g. its node count) without taking any locks, the agent polls
+/// is_stuck whenever it asks for the current best move
+pub struct Watchdog {
+    progress: Arc<AtomicU64>,
+
+    timeout: Duration,
+
+    last_progress: u64,
+    last_change: Instant,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Watchdog {
+            progress: Arc::new(AtomicU64::new(0)),
+            timeout,
+            last_progress: 0,
+            last_change: Instant::now(),
+        }
+    }
+
+    /// counter the watched worker has to keep updating
+    pub fn progress(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.progress)
+    }
+
+    /// true if the progress counter hasn't changed for longer than the timeout
+    pub fn is_stuck(&mut self) -> bool {
+        let progress = self.progress.load(Ordering::Relaxed);
+
+        if progress != self.last_progress {
+            self.last_progress = progress;
+            self.last_change = Instant::now();
+        }
+
+        self.last_change.elapsed() > self.timeout
+    }
+}
+
+/// lock search_state once ready is true for it, polling until deadline
+///
+/// never blocks on the lock itself, so a stuck worker holding it can't hang the caller past the deadline. Returns the
+/// guard as soon as ready holds or, at the deadline, if the lock is free then; None if it wasn't. Err if the worker
+/// panicked while holding the lock.
+pub fn lock_search_when<S>(
+    search_state: &Mutex<S>,
+    deadline: Instant,
+    ready: impl Fn(&S) -> bool,
+) -> Result<Option<MutexGuard<'_, S>>, String> {
+    loop {
+        match search_state.try_lock() {
+            Ok(guard) if ready(&guard) || Instant::now() >= deadline => return Ok(Some(guard)),
+            Err(TryLockError::WouldBlock) if Instant::now() >= deadline => return Ok(None),
+            Ok(_) | Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Poisoned(err)) => return Err(err.to_string()),
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// panic message of the search thread behind worker, if it has panicked
+///
+/// a worker that finished normally is taken out as well, so it only gets reported once either way
+pub fn worker_panic(worker: &mut Option<JoinHandle<()>>) -> Option<String> {
+    if !worker.as_ref()?.is_finished() {
+        return None;
+    }
+
+    let payload = worker.take().unwrap().join().err()?;
+
+    // panic! with a message gives either of these, anything else is unusual enough to not bother with
+    let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (None, Some(message)) => message.clone(),
+        (None, None) => "unknown panic payload".to_string(),
+    };
+
+    Some(message)
+}
+
+/// best move according to a one ply search with evaluator, for when the search can't deliver one
+pub fn fallback_move(board: &Board, evaluator: impl Evaluator) -> Move {
+    let moves = board.legal_moves(Player::White);
+
+    // board stays in White's perspective regardless of whose turn it is
+    let boards: Vec<Board> = moves
+        .iter()
+        .map(|&move_| {
+            let mut board = board.clone();
+            board.apply_move(move_);
+            board
+        })
+        .collect();
+
+    let values = evaluator.evaluate_batch(&boards);
+
+    moves
+        .into_iter()
+        .zip(boards.iter().zip(values))
+        .map(|(move_, (board, value))| (move_, evaluator.side_to_move_value(board, value)))
+        .max_by_key(|&(_, value)| value)
+        .expect("No legal move to fall back to")
+        .0
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use super::{fallback_move, lock_search_when, worker_panic, Watchdog};
+    use crate::kalah::valuation::store_diff_valuation;
+    use crate::{Board, Move, Player};
+
+    #[test]
+    fn test_watchdog() {
+        let mut watchdog = Watchdog::new(Duration::from_millis(50));
+        let progress = watchdog.progress();
+
+        for _ in 0..5 {
+            std::thread::sleep(Duration::from_millis(20));
+            progress.fetch_add(1, Ordering::Relaxed);
+
+            assert!(!watchdog.is_stuck());
+        }
+
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(watchdog.is_stuck());
+    }
+
+    #[test]
+    fn test_fallback_move() {
+        // house 0 captures the 5 seeds opposite of house 1, house 2 only gets a single seed into the store
+        let board = Board::from_kgp("<3, 0, 0, 1, 0, 3, 1, 5, 1>");
+
+        assert_eq!(fallback_move(&board, store_diff_valuation), Move::new(0, Player::White));
+    }
+
+    #[test]
+    fn test_lock_search_when() {
+        let search_state = Arc::new(Mutex::new(0));
+
+        std::thread::spawn({
+            let search_state = Arc::clone(&search_state);
+            move || {
+                std::thread::sleep(Duration::from_millis(50));
+                *search_state.lock().unwrap() = 1;
+            }
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let guard = lock_search_when(&search_state, deadline, |&depth| depth > 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(*guard, 1);
+        assert!(Instant::now() < deadline);
+        drop(guard);
+
+        // never ready: returns at the deadline anyways
+        let deadline = Instant::now() + Duration::from_millis(50);
+        assert_eq!(
+            *lock_search_when(&search_state, deadline, |&depth| depth > 1)
+                .unwrap()
+                .unwrap(),
+            1
+        );
+        assert!(Instant::now() >= deadline);
+
+        // locked the whole time: nothing to return
+        let held = search_state.lock().unwrap();
+        assert!(
+            lock_search_when(&search_state, Instant::now() + Duration::from_millis(50), |_| true)
+                .unwrap()
+                .is_none()
+        );
+        drop(held);
+
+        // a worker panicking while holding the lock
+        let _ = std::thread::spawn({
+            let search_state = Arc::clone(&search_state);
+            move || {
+                let _guard = search_state.lock().unwrap();
+                panic!("injected panic");
+            }
+        })
+        .join();
+
+        assert!(lock_search_when(&search_state, Instant::now(), |_| true).is_err());
+    }
+
+    #[test]
+    fn test_worker_panic() {
+        let wait = |worker: &Option<std::thread::JoinHandle<()>>| {
+            while !worker.as_ref().unwrap().is_finished() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        };
+
+        let mut worker = Some(std::thread::spawn(|| {}));
+        wait(&worker);
+        assert_eq!(worker_panic(&mut worker), None);
+        assert!(worker.is_none());
+
+        let mut worker = Some(std::thread::spawn(|| panic!("injected panic {}", 1)));
+        wait(&worker);
+        assert_eq!(worker_panic(&mut worker), Some("injected panic 1".to_string()));
+        assert_eq!(worker_panic(&mut worker), None);
+
+        let mut worker = Some(std::thread::spawn(|| std::thread::sleep(Duration::from_secs(1))));
+        assert_eq!(worker_panic(&mut worker), None);
+        assert!(worker.is_some());
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use super::tournament::{run_seeded_match, AgentBuilder, MatchResult, TournamentConfig};
+use crate::agent::{Agent, ShallowAgent};
+use crate::kalah::valuation::{valuation_by_name, ValuationFn};
+use crate::Board;
+
+/// what bench_eval plays: board size, search depth of both agents, number of games and the seed of the openings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchEvalConfig {
+    pub h: u8,
+    pub s: u16,
+
+    pub depth: u32,
+
+    /// has to be even, every opening is played once with each color assignment
+    pub num_games: usize,
+
+    pub seed: u64,
+}
+
+impl BenchEvalConfig {
+    /// 200 games on a board with 6 houses of 4 seeds at depth 3, which takes a few seconds
+    pub fn new() -> Self {
+        BenchEvalConfig {
+            h: 6,
+            s: 4,
+            depth: 3,
+            num_games: 200,
+            seed: 0,
+        }
+    }
+}
+
+impl Default for BenchEvalConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// play two ShallowAgents against each other that only differ in their valuation, the one called valuation1 is agent 1
+/// in the result
+///
+/// the quick check for a heuristic tweak: the agents don't get any thinking time beyond their fixed depth search and
+/// the openings only depend on the seed, so the same config always plays the same games
+pub fn bench_eval(config: &BenchEvalConfig, valuation1: &str, valuation2: &str) -> Result<MatchResult, String> {
+    let valuation_fn = |name: &str| valuation_by_name(name).ok_or_else(|| format!("Unknown valuation {name}"));
+    let (valuation_fn1, valuation_fn2) = (valuation_fn(valuation1)?, valuation_fn(valuation2)?);
+
+    Board::try_new(config.h, config.s)?;
+
+    if config.num_games == 0 || config.num_games % 2 == 1 {
+        return Err(format!(
+            "Number of games {} has to be even and positive",
+            config.num_games
+        ));
+    }
+
+    let &BenchEvalConfig { h, s, depth, .. } = config;
+
+    let tournament_config = TournamentConfig {
+        thinking_time: Duration::ZERO,
+        ..TournamentConfig::new(h, s)
+    };
+
+    let shallow_agent = |valuation_fn: ValuationFn| {
+        move || Box::new(ShallowAgent::new(Board::new(h, s), depth, valuation_fn)) as Box<dyn Agent + Send>
+    };
+    let (agent1, agent2) = (shallow_agent(valuation_fn1), shallow_agent(valuation_fn2));
+    let (agent1_builder, agent2_builder): (AgentBuilder, AgentBuilder) = (&agent1, &agent2);
+
+    Ok(run_seeded_match(
+        &tournament_config,
+        agent1_builder,
+        agent2_builder,
+        config.num_games,
+        config.seed,
+    ))
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::{bench_eval, BenchEvalConfig};
+
+    #[test]
+    fn test_bench_eval() {
+        let config = BenchEvalConfig {
+            h: 4,
+            s: 3,
+            depth: 2,
+            num_games: 8,
+            seed: 1940,
+        };
+
+        let result = bench_eval(&config, "store_diff", "seed_diff").unwrap();
+        assert_eq!(result.num_games(), 8);
+
+        // same seed, same games
+        assert_eq!(bench_eval(&config, "store_diff", "seed_diff"), Ok(result));
+
+        assert!(bench_eval(&config, "store_diff", "mobility2").is_err());
+        assert!(bench_eval(&BenchEvalConfig { num_games: 7, ..config }, "store_diff", "seed_diff").is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use log::info;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::agent::{Agent, AgentState, HumanAgent};
+use crate::{Board, GameResult, Move, Player};
+
+/*====================================================================================================================*/
+
+/// let player's agent make a single move on board (which is always from White's perspective)
+///
+/// returns the player whose turn it is after the move
+pub fn single_ply<const DO_LOGGING: bool>(
+    board: &mut Board,
+    playing_agent: &mut impl Agent,
+    player: Player,
+    thinking_time: Duration,
+) -> Player {
+    if DO_LOGGING {
+        info!("\n{board}\n");
+    }
+
+    match player {
+        Player::White => playing_agent.update_board(board),
+        Player::Black => {
+            board.flip_board();
+            playing_agent.update_board(board);
+            board.flip_board();
+        }
+    };
+
+    let start_time = std::time::Instant::now();
+
+    playing_agent.go();
+
+    let mut player_move = playing_agent.get_current_best_move();
+
+    while playing_agent.get_state() == AgentState::Go
+        && (playing_agent.is_reference() || start_time.elapsed() < thinking_time)
+    {
+        player_move = playing_agent.get_current_best_move();
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    playing_agent.stop();
+
+    // agents always move for the side they see as White, even if they are Black
+    let house = player_move.house();
+
+    if player_move.player() != Player::White || !board.is_legal_move(Move::new(house, player)) {
+        panic!("Invalid move {player_move:?} by Player {player} in position \n{board}\n\n");
+    }
+
+    if DO_LOGGING {
+        info!("{player}: playing move {player_move}");
+    }
+
+    let moves_again = board.apply_move_for(house, player).bonus;
+
+    if moves_again {
+        player
+    } else {
+        !player
+    }
+}
+
+/// play board to the end, starting with first_player, and return the final board
+pub fn game_loop<const DO_LOGGING: bool>(
+    board: Board,
+    first_player: Player,
+    white_agent: impl Agent,
+    black_agent: impl Agent,
+    thinking_time: Duration,
+) -> Board {
+    use Player::{Black, White};
+
+    let mut current_player = first_player;
+
+    let mut board = board;
+    let mut white_agent = white_agent;
+    let mut black_agent = black_agent;
+
+    // Kalah positions can't repeat (see PathHistory), every ply gets the game closer to its end. A repeated position
+    // means the rules are broken and the game might never end, so it's an error like an illegal move rather than a
+    // draw. Bonus moves always change the board, so they can't repeat a position either.
+    let mut seen_positions = HashSet::new();
+
+    while board.has_legal_move() {
+        if !seen_positions.insert((board.zobrist_hash(), current_player)) {
+            panic!("Position repeated with {current_player} to move, the game would never end\n{board}\n");
+        }
+
+        current_player = match current_player {
+            White => single_ply::<DO_LOGGING>(&mut board, &mut white_agent, White, thinking_time),
+            Black => single_ply::<DO_LOGGING>(&mut board, &mut black_agent, Black, thinking_time),
+        };
+    }
+
+    board
+}
+
+/// starting position with num_moves random moves already played, together with the player to move next
+///
+/// openings in which the game is decided during the random moves (it's over, or a store already holds more than half
+/// of the seeds) are discarded and generated again
+pub fn random_opening(h: u8, s: u16, num_moves: usize, rng: &mut impl Rng) -> (Board, Player) {
+    'retry: loop {
+        let mut board = Board::new(h, s);
+        let mut current_player = Player::White;
+
+        for _ in 0..num_moves {
+            let move_ = *board.legal_moves(current_player).choose(rng).unwrap();
+
+            if !board.apply_move(move_) {
+                current_player = !current_player;
+            }
+
+            if board.is_decided().is_some() {
+                continue 'retry;
+            }
+        }
+
+        return (board, current_player);
+    }
+}
+
+/// play a game from the starting position like game_loop and hash its transcript (every move and the board after it)
+///
+/// with deterministic agents this identifies the game, so any change in move generation, valuation or search that
+/// changes how they play changes the fingerprint. The hash (FNV-1a) is stable across platforms and Rust versions.
+#[allow(dead_code)]
+pub fn game_fingerprint(h: u8, s: u16, white_agent: impl Agent, black_agent: impl Agent) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut fingerprint = FNV_OFFSET;
+    let mut hash = |bytes: &[u8]| {
+        for &byte in bytes {
+            fingerprint = (fingerprint ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    };
+
+    let mut board = Board::new(h, s);
+    let mut white_agent = white_agent;
+    let mut black_agent = black_agent;
+    let mut current_player = Player::White;
+
+    while board.has_legal_move() {
+        let next_player = match current_player {
+            Player::White => single_ply::<false>(&mut board, &mut white_agent, Player::White, Duration::ZERO),
+            Player::Black => single_ply::<false>(&mut board, &mut black_agent, Player::Black, Duration::ZERO),
+        };
+
+        // single_ply doesn't hand out the move, but the player and the board after it pin it down
+        hash(&[current_player as u8]);
+        hash(board.to_kgp().as_bytes());
+
+        current_player = next_player;
+    }
+
+    fingerprint
+}
+
+/// play a single logged game from the starting position between white_agent and black_agent
+pub fn play_game(h: u8, s: u16, white_agent: impl Agent, black_agent: impl Agent) {
+    let board = Board::new(h, s);
+    let thinking_time = Duration::from_secs(3);
+
+    let board = game_loop::<true>(board, Player::White, white_agent, black_agent, thinking_time);
+
+    info!("Final board:\n\n{board}\n");
+
+    info!("{}.", board.winner().unwrap());
+}
+
+/// play an interactive game on the console, the human (as player human) against agent
+///
+/// the human's moves are read from stdin, White always moves first
+pub fn play_human(h: u8, s: u16, human: Player, agent: impl Agent, thinking_time: Duration) {
+    let board = Board::new(h, s);
+    let human_agent = HumanAgent::new(h, s);
+
+    let board = match human {
+        Player::White => game_loop::<false>(board, Player::White, human_agent, agent, thinking_time),
+        Player::Black => game_loop::<false>(board, Player::White, agent, human_agent, thinking_time),
+    };
+
+    println!("\nFinal board:\n\n{board}\n");
+
+    match board.winner().unwrap() {
+        GameResult::Win(player) if player == human => println!("You won!"),
+        GameResult::Win(_) => println!("You lost."),
+        GameResult::Draw => println!("Draw."),
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::SeedableRng;
+
+    use super::{game_fingerprint, game_loop, random_opening, single_ply};
+    use crate::agent::FirstMoveAgent;
+    use crate::kalah::valuation::store_diff_valuation;
+    use crate::minimax_reference::MinimaxAgent;
+    use crate::{Board, Player};
+
+    // update this (and explain why in the commit) if a change is meant to make the engine play differently
+    const FINGERPRINT: u64 = 0xe75a_0e92_7e96_e482;
+
+    #[test]
+    fn test_game_fingerprint() {
+        let play = || {
+            let minimax = MinimaxAgent::new(Board::new(6, 4), 4, store_diff_valuation);
+            game_fingerprint(6, 4, FirstMoveAgent::new(6, 4), minimax)
+        };
+
+        let fingerprint = play();
+
+        assert_eq!(play(), fingerprint, "Game isn't deterministic");
+        assert_eq!(
+            fingerprint, FINGERPRINT,
+            "Engine plays differently: fingerprint {fingerprint:#x}"
+        );
+    }
+
+    #[test]
+    fn test_game_loop_ends() {
+        use crate::agent::RandomAgent;
+
+        // the first move agents always move the seeds farthest from their store, the slowest way to make progress,
+        // and tiny boards are all bonus moves and captures. game_loop would panic on a repeated position.
+        for h in 1..=4 {
+            for s in 1..=4 {
+                let board = game_loop::<false>(
+                    Board::new(h, s),
+                    Player::White,
+                    FirstMoveAgent::new(h, s),
+                    FirstMoveAgent::new(h, s),
+                    Duration::ZERO,
+                );
+                assert!(board.winner().is_some());
+
+                for _ in 0..10 {
+                    let board = game_loop::<false>(
+                        Board::new(h, s),
+                        Player::White,
+                        RandomAgent::new(h, s),
+                        FirstMoveAgent::new(h, s),
+                        Duration::ZERO,
+                    );
+                    assert!(board.winner().is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bonus_move_keeps_turn() {
+        use Player::{Black, White};
+
+        let mut board = Board::new(3, 3);
+        let mut white_agent = FirstMoveAgent::new(3, 3);
+        let mut black_agent = FirstMoveAgent::new(3, 3);
+
+        // White's house 1 ends in the store and moves again with house 2, which ends on Black's side. Black's house 1
+        // then ends on White's side as well.
+        let mut players = vec![White];
+        for _ in 0..3 {
+            let player = *players.last().unwrap();
+            let agent = match player {
+                White => &mut white_agent,
+                Black => &mut black_agent,
+            };
+            players.push(single_ply::<false>(&mut board, agent, player, Duration::ZERO));
+        }
+        assert_eq!(players, [White, White, Black, White]);
+        assert_eq!(board.to_kgp(), "<3, 2, 1, 1, 0, 5, 0, 5, 4>");
+
+        // only house 1 ends in the store on the first move, the others all reach Black's side
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1928);
+        for _ in 0..20 {
+            let (board, player) = random_opening(3, 3, 1, &mut rng);
+            assert_eq!(player == White, board.their_houses() == [3, 3, 3], "{}", board.to_kgp());
+        }
+    }
+}
@@ -0,0 +1,14 @@
+mod bench_eval;
+mod game;
+mod selfplay;
+mod selftest;
+mod tournament;
+
+pub use bench_eval::{bench_eval, BenchEvalConfig};
+pub use game::{game_loop, play_game, play_human, random_opening, single_ply};
+pub use selfplay::generate_selfplay_data;
+pub use selftest::{perft, selftest};
+pub use tournament::{
+    round_robin, run_match, run_match_by_opening, run_seeded_match, AgentBuilder, MatchResult, OpeningResult,
+    TournamentConfig,
+};
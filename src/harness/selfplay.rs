@@ -0,0 +1,154 @@
+use std::collections::HashSet;
+
+use log::info;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+use super::game::{random_opening, single_ply};
+use super::tournament::{AgentBuilder, TournamentConfig};
+use crate::{Board, GameResult, Player};
+
+/// play num_games games of the agent from agent_builder against itself and label every position of them with the
+/// result of its game, e.g. as training data for an evaluator
+///
+/// every game starts from a random opening (see random_opening) drawn from an rng seeded with seed, the positions of
+/// the opening itself aren't part of the data. Positions are seen from the player to move like the searches see them,
+/// i.e. flipped if it's Black, and the results are from the original orientation like Board::winner's, so a position
+/// is a win for the player to move iff the result is GameResult::Win of the player flipped() says is to move.
+///
+/// agents that always play the same move in the same position (e.g. ShallowAgent, with any thinking time) play the
+/// same games every time, so the same seed gives the same data. With dedup, only the first occurrence of every
+/// position is kept, together with the result of the game it first occurred in.
+pub fn generate_selfplay_data(
+    config: &TournamentConfig,
+    num_games: usize,
+    agent_builder: AgentBuilder,
+    seed: u64,
+    dedup: bool,
+) -> Vec<(Board, GameResult)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let openings: Vec<(Board, Player)> = (0..num_games)
+        .map(|_| random_opening(config.h, config.s, 2 * config.h as usize, &mut rng))
+        .collect();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.max_workers)
+        .build()
+        .expect("Failed to build selfplay thread pool");
+
+    info!("Playing {num_games} selfplay games with {} workers", config.max_workers);
+
+    // par_iter keeps the order of the openings, so the games don't depend on which worker finished first
+    let games: Vec<Vec<(Board, GameResult)>> = pool.install(|| {
+        openings
+            .into_par_iter()
+            .map(|(opening, first_player)| selfplay_game(config, opening, first_player, agent_builder))
+            .collect()
+    });
+
+    let mut seen = HashSet::new();
+
+    games
+        .into_iter()
+        .flatten()
+        .filter(|(board, _result)| !dedup || seen.insert((board.to_kgp(), board.flipped())))
+        .collect()
+}
+
+// play a single game from opening with both sides played by an agent from agent_builder, returns every position with
+// the final result
+fn selfplay_game(
+    config: &TournamentConfig,
+    opening: Board,
+    first_player: Player,
+    agent_builder: AgentBuilder,
+) -> Vec<(Board, GameResult)> {
+    let mut board = opening;
+    let mut current_player = first_player;
+
+    let mut white_agent = agent_builder();
+    let mut black_agent = agent_builder();
+
+    let mut positions = Vec::new();
+
+    while board.has_legal_move() {
+        let mut position = board.clone();
+        if current_player == Player::Black {
+            position.flip_board();
+        }
+        positions.push(position);
+
+        current_player = match current_player {
+            Player::White => single_ply::<false>(&mut board, &mut white_agent, Player::White, config.thinking_time),
+            Player::Black => single_ply::<false>(&mut board, &mut black_agent, Player::Black, config.thinking_time),
+        };
+    }
+
+    let result = board.winner().unwrap();
+
+    positions.into_iter().map(|position| (position, result)).collect()
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::generate_selfplay_data;
+    use crate::agent::{Agent, ShallowAgent};
+    use crate::harness::{game_loop, AgentBuilder, TournamentConfig};
+    use crate::kalah::valuation::store_diff_valuation;
+    use crate::{Board, GameResult, Player};
+
+    #[test]
+    fn test_generate_selfplay_data() {
+        let config = TournamentConfig {
+            thinking_time: Duration::ZERO,
+            ..TournamentConfig::new(4, 3)
+        };
+
+        let shallow_agent =
+            || Box::new(ShallowAgent::new(Board::new(4, 3), 2, store_diff_valuation)) as Box<dyn Agent + Send>;
+        let agent_builder: AgentBuilder = &shallow_agent;
+
+        let generate = |dedup: bool| {
+            generate_selfplay_data(&config, 6, agent_builder, 1953, dedup)
+                .into_iter()
+                .map(|(board, result)| (board.to_kgp(), board.flipped(), result))
+                .collect::<Vec<_>>()
+        };
+
+        let data = generate(false);
+        assert!(data.len() >= 6);
+
+        // same seed, same games
+        assert_eq!(generate(false), data);
+
+        // the agent is deterministic, so playing on from any position ends with the result it's labeled with
+        for (kgp, flipped, result) in &data {
+            let player_to_move = if *flipped { Player::Black } else { Player::White };
+            let board = Board::from_kgp(kgp);
+            assert!(!board.legal_moves(player_to_move).is_empty(), "{kgp}");
+
+            let final_board =
+                game_loop::<false>(board, player_to_move, shallow_agent(), shallow_agent(), Duration::ZERO);
+            assert_eq!(final_board.winner(), Some(*result), "{kgp}");
+        }
+
+        // dedup drops repeated positions and nothing else
+        let deduped = generate(true);
+        let mut expected = Vec::new();
+        for position in &data {
+            if !expected
+                .iter()
+                .any(|seen: &(String, bool, GameResult)| (&seen.0, seen.1) == (&position.0, position.1))
+            {
+                expected.push(position.clone());
+            }
+        }
+        assert_eq!(deduped, expected);
+    }
+}
@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::agent::{Agent, AgentState};
+use crate::kalah::valuation::VALUATIONS;
+use crate::kgp::{Command, KgpError};
+use crate::{tournament, Board, Move, Player};
+
+/// number of positions reached after exactly depth plies, counting games that end earlier as a single position
+///
+/// bonus moves are plies of their own, so the count also checks that they keep the turn
+pub fn perft(board: &Board, depth: u32) -> u64 {
+    if depth == 0 || !board.has_legal_move() {
+        return 1;
+    }
+
+    board
+        .legal_moves(Player::White)
+        .into_iter()
+        .map(|move_| {
+            let mut board_after_move = board.clone();
+
+            if !board_after_move.apply_move(move_) {
+                board_after_move.flip_board();
+            }
+
+            perft(&board_after_move, depth - 1)
+        })
+        .sum()
+}
+
+// perft of the start position with 3 houses of 3 seeds, as counted when the rules were last changed on purpose
+const PERFT_3_3: [u64; 6] = [1, 3, 8, 19, 48, 120];
+
+// White (to move) wins in 2 plies: house 3 lands in the store, then house 2 captures the 3 seeds opposite of house 3
+const WIN_IN_2: &str = "<3, 2, 0, 0, 1, 1, 3, 1, 1>";
+
+const KGP_LINES: &[&str] = &[
+    "1 kgp 1 0 0",
+    "5@3 stop",
+    "7 error no such game",
+    "12@7 handicap 3 south",
+];
+
+fn check_perft() -> Result<(), String> {
+    let board = Board::new(3, 3);
+
+    for (depth, &expected) in PERFT_3_3.iter().enumerate() {
+        let count = perft(&board, depth as u32);
+
+        if count != expected {
+            return Err(format!("perft({depth}) is {count} instead of {expected}"));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_win_in_2() -> Result<(), String> {
+    let board = Board::from_kgp(WIN_IN_2);
+    let expected = Move::new(2, Player::White);
+
+    let mut agent = tournament::MinimaxAgent::new(board.clone());
+    agent.update_board(&board);
+    agent.go();
+
+    // the search stops on its own once it has proven the win
+    let start = Instant::now();
+    let mut best_move = agent.get_current_best_move();
+    while agent.get_state() == AgentState::Go && start.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(10));
+        best_move = agent.get_current_best_move();
+    }
+    agent.stop();
+
+    match best_move == expected {
+        true => Ok(()),
+        false => Err(format!("played {best_move} instead of {expected} on {WIN_IN_2}")),
+    }
+}
+
+fn check_valuation_symmetry() -> Result<(), String> {
+    let mut rng = StdRng::seed_from_u64(1927);
+
+    for _ in 0..20 {
+        let mut board = Board::new(6, 4);
+
+        while board.has_legal_move() {
+            let mut flipped = board.clone();
+            flipped.flip_board();
+
+            for &(name, valuation_fn) in VALUATIONS {
+                if valuation_fn(&board) != -valuation_fn(&flipped) {
+                    return Err(format!("{name} is not symmetric on {}", board.to_kgp()));
+                }
+            }
+
+            let move_ = *board.legal_moves(Player::White).choose(&mut rng).unwrap();
+
+            if !board.apply_move(move_) {
+                board.flip_board();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_kgp_round_trip() -> Result<(), String> {
+    for &line in KGP_LINES {
+        let command: Command = line.parse().map_err(|err: KgpError| err.to_string())?;
+
+        if command.to_string() != line {
+            return Err(format!("\"{line}\" came back as \"{command}\""));
+        }
+    }
+
+    // the server sends boards without spaces, which this client never sends back
+    match "4@2 state <3,0,0,3,3,3,3,3,3>"
+        .parse()
+        .map_err(|err: KgpError| err.to_string())?
+    {
+        Command::State { board, .. } if board.to_kgp() == Board::new(3, 3).to_kgp() => Ok(()),
+        command => Err(format!("start position came back as \"{command}\"")),
+    }
+}
+
+/// quick checks that the build plays by the rules, finds a forced win, values both sides alike and speaks KGP
+///
+/// returns the name and outcome of every check, meant for running before connecting to a server
+pub fn selftest() -> Vec<(&'static str, Result<(), String>)> {
+    vec![
+        ("perft", check_perft()),
+        ("win in 2", check_win_in_2()),
+        ("valuation symmetry", check_valuation_symmetry()),
+        ("kgp round trip", check_kgp_round_trip()),
+    ]
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::{perft, selftest};
+    use crate::Board;
+
+    #[test]
+    fn test_selftest() {
+        for (name, result) in selftest() {
+            assert_eq!(result, Ok(()), "{name}");
+        }
+
+        // a finished game is a single position at every depth
+        let board = Board::from_kgp("<3, 5, 4, 0, 0, 0, 0, 0, 0>");
+        assert_eq!(perft(&board, 3), 1);
+    }
+}
@@ -0,0 +1,410 @@
+use std::cmp::Ordering;
+use std::sync::atomic::{self, AtomicUsize};
+use std::time::Duration;
+
+use log::info;
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use super::game::{game_loop, random_opening};
+use crate::agent::Agent;
+use crate::{Board, Player};
+
+/*====================================================================================================================*/
+
+/// creates a fresh agent for every game, has to be callable from all worker threads at once
+pub type AgentBuilder<'a> = &'a (dyn Fn() -> Box<dyn Agent + Send> + Sync);
+
+pub struct TournamentConfig {
+    pub h: u8,
+    pub s: u16,
+
+    pub thinking_time: Duration,
+
+    /// number of games played at the same time; every game runs its agents' searches in their own threads
+    pub max_workers: usize,
+}
+
+impl TournamentConfig {
+    pub fn new(h: u8, s: u16) -> Self {
+        // agents search in separate threads, so only use half the cores to not starve them
+        let max_workers = std::thread::available_parallelism().map_or(1, |n| (n.get() / 2).max(1));
+
+        TournamentConfig {
+            h,
+            s,
+            thinking_time: Duration::from_secs(2),
+            max_workers,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchResult {
+    pub agent1_white_wins: u64,
+    pub agent1_black_wins: u64,
+
+    pub agent2_white_wins: u64,
+    pub agent2_black_wins: u64,
+
+    pub draws: u64,
+}
+
+impl MatchResult {
+    pub fn num_games(&self) -> u64 {
+        self.agent1_white_wins + self.agent1_black_wins + self.agent2_white_wins + self.agent2_black_wins + self.draws
+    }
+
+    /// share of the points agent 1 got, counting a draw as half a win
+    pub fn agent1_score(&self) -> f64 {
+        let agent1_wins = self.agent1_white_wins + self.agent1_black_wins;
+
+        (agent1_wins as f64 + 0.5 * self.draws as f64) / self.num_games() as f64
+    }
+
+    /// Elo rating of agent 1 minus the one of agent 2 that would predict agent1_score
+    ///
+    /// only a rough estimate for a few hundred games, None if there were no games or one agent got all points (which
+    /// no finite difference predicts)
+    pub fn elo_difference(&self) -> Option<f64> {
+        let score = self.agent1_score();
+
+        (score > 0.0 && score < 1.0).then(|| -400.0 * (1.0 / score - 1.0).log10())
+    }
+}
+
+impl std::ops::Add for MatchResult {
+    type Output = MatchResult;
+
+    fn add(self, other: MatchResult) -> MatchResult {
+        MatchResult {
+            agent1_white_wins: self.agent1_white_wins + other.agent1_white_wins,
+            agent1_black_wins: self.agent1_black_wins + other.agent1_black_wins,
+            agent2_white_wins: self.agent2_white_wins + other.agent2_white_wins,
+            agent2_black_wins: self.agent2_black_wins + other.agent2_black_wins,
+            draws: self.draws + other.draws,
+        }
+    }
+}
+
+/// an opening of run_match_by_opening and the results of the two games played from it
+#[derive(Debug, Clone)]
+pub struct OpeningResult {
+    pub opening: Board,
+    pub first_player: Player,
+
+    pub result: MatchResult,
+}
+
+/*====================================================================================================================*/
+
+// games are (opening, player to move, white, black)
+type Game = (Board, Player, usize, usize);
+
+// white wins, draws and black wins for every (white agent, black agent) pair, indexed by white * n + black
+struct Outcomes {
+    n: usize,
+
+    white_wins: Vec<u64>,
+    draws: Vec<u64>,
+    black_wins: Vec<u64>,
+}
+
+impl Outcomes {
+    // games and the comparison of White's and Black's final store in each of them, see play_games
+    fn new(n: usize, games: &[Game], results: &[Ordering]) -> Self {
+        let mut outcomes = Outcomes {
+            n,
+            white_wins: vec![0; n * n],
+            draws: vec![0; n * n],
+            black_wins: vec![0; n * n],
+        };
+
+        for (&(_, _, white, black), &result) in games.iter().zip(results) {
+            let counter = match result {
+                Ordering::Less => &mut outcomes.black_wins,
+                Ordering::Equal => &mut outcomes.draws,
+                Ordering::Greater => &mut outcomes.white_wins,
+            };
+
+            counter[white * n + black] += 1;
+        }
+
+        outcomes
+    }
+
+    fn match_result(&self, agent1: usize, agent2: usize) -> MatchResult {
+        let load = |counters: &Vec<u64>, white: usize, black: usize| counters[white * self.n + black];
+
+        MatchResult {
+            agent1_white_wins: load(&self.white_wins, agent1, agent2),
+            agent1_black_wins: load(&self.black_wins, agent2, agent1),
+            agent2_white_wins: load(&self.white_wins, agent2, agent1),
+            agent2_black_wins: load(&self.black_wins, agent1, agent2),
+            draws: load(&self.draws, agent1, agent2) + load(&self.draws, agent2, agent1),
+        }
+    }
+}
+
+// play all games on a rayon pool with config.max_workers threads, returns how White's final store compares to Black's
+// in every game
+fn play_games(config: &TournamentConfig, builders: &[AgentBuilder], games: &[Game]) -> Vec<Ordering> {
+    let num_games = games.len();
+    let num_done = AtomicUsize::new(0);
+    let width = num_games.to_string().len();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.max_workers)
+        .build()
+        .expect("Failed to build tournament thread pool");
+
+    info!("Running {num_games} games with {} workers", config.max_workers);
+
+    pool.install(|| {
+        games
+            .par_iter()
+            .map(|(opening, first_player, white, black)| {
+                let final_board = game_loop::<false>(
+                    opening.clone(),
+                    *first_player,
+                    builders[*white](),
+                    builders[*black](),
+                    config.thinking_time,
+                );
+
+                let num_done = num_done.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+                info!("{num_done:>width$}/{num_games}");
+
+                final_board.our_store().cmp(&final_board.their_store())
+            })
+            .collect()
+    })
+}
+
+/// play num_runs games between agent1 and agent2 from random openings, every opening once with each color
+///
+/// see run_match_by_opening for the results of the individual openings
+pub fn run_match(
+    config: &TournamentConfig,
+    agent1_builder: AgentBuilder,
+    agent2_builder: AgentBuilder,
+    num_runs: usize,
+) -> MatchResult {
+    let result = run_match_by_opening(config, agent1_builder, agent2_builder, num_runs)
+        .into_iter()
+        .fold(MatchResult::default(), |result, opening| result + opening.result);
+
+    info!(
+        "Agent 1 wins: {}/{}",
+        result.agent1_white_wins, result.agent1_black_wins
+    );
+    info!("Draws:        {}", result.draws);
+    info!(
+        "Agent 2 wins: {}/{}",
+        result.agent2_white_wins, result.agent2_black_wins
+    );
+
+    result
+}
+
+/// play the games of run_match and group their results by opening
+///
+/// every opening gets played once with each color assignment, so an opening favoring one color doesn't favor either
+/// agent. Openings that are already decided (see random_opening) never get played.
+pub fn run_match_by_opening(
+    config: &TournamentConfig,
+    agent1_builder: AgentBuilder,
+    agent2_builder: AgentBuilder,
+    num_runs: usize,
+) -> Vec<OpeningResult> {
+    match_by_opening(config, agent1_builder, agent2_builder, num_runs, &mut thread_rng())
+}
+
+/// run_match with the openings drawn from an rng seeded with seed
+///
+/// agents that always play the same move in the same position (e.g. ShallowAgent) play the same games every time, so
+/// two runs with the same seed have the same result
+pub fn run_seeded_match(
+    config: &TournamentConfig,
+    agent1_builder: AgentBuilder,
+    agent2_builder: AgentBuilder,
+    num_runs: usize,
+    seed: u64,
+) -> MatchResult {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    match_by_opening(config, agent1_builder, agent2_builder, num_runs, &mut rng)
+        .into_iter()
+        .fold(MatchResult::default(), |result, opening| result + opening.result)
+}
+
+fn match_by_opening(
+    config: &TournamentConfig,
+    agent1_builder: AgentBuilder,
+    agent2_builder: AgentBuilder,
+    num_runs: usize,
+    rng: &mut impl Rng,
+) -> Vec<OpeningResult> {
+    assert_eq!(num_runs % 2, 0, "num_runs must be divisible by 2");
+
+    let games: Vec<Game> = (0..num_runs / 2)
+        .flat_map(|_| {
+            let (opening, first_player) = random_opening(config.h, config.s, 2 * config.h as usize, rng);
+
+            [(opening.clone(), first_player, 0, 1), (opening, first_player, 1, 0)]
+        })
+        .collect();
+
+    let results = play_games(config, &[agent1_builder, agent2_builder], &games);
+
+    games
+        .chunks(2)
+        .zip(results.chunks(2))
+        .map(|(games, results)| OpeningResult {
+            opening: games[0].0.clone(),
+            first_player: games[0].1,
+            result: Outcomes::new(2, games, results).match_result(0, 1),
+        })
+        .collect()
+}
+
+/// play every pair of agents against each other in runs_per_pair games (see run_match)
+///
+/// returns the results of all pairs (i, j) with i < j, where agent i is agent1 in the MatchResult
+pub fn round_robin(
+    config: &TournamentConfig,
+    builders: &[AgentBuilder],
+    runs_per_pair: usize,
+) -> Vec<((usize, usize), MatchResult)> {
+    assert_eq!(runs_per_pair % 2, 0, "runs_per_pair must be divisible by 2");
+
+    let mut rng = thread_rng();
+
+    let pairs: Vec<(usize, usize)> = (0..builders.len())
+        .flat_map(|i| (i + 1..builders.len()).map(move |j| (i, j)))
+        .collect();
+
+    let mut games = Vec::with_capacity(pairs.len() * runs_per_pair);
+
+    for &(i, j) in &pairs {
+        for _ in 0..runs_per_pair / 2 {
+            let (opening, first_player) = random_opening(config.h, config.s, 2 * config.h as usize, &mut rng);
+
+            games.push((opening.clone(), first_player, i, j));
+            games.push((opening, first_player, j, i));
+        }
+    }
+
+    let results = play_games(config, builders, &games);
+    let outcomes = Outcomes::new(builders.len(), &games, &results);
+
+    pairs
+        .into_iter()
+        .map(|(i, j)| ((i, j), outcomes.match_result(i, j)))
+        .collect()
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{round_robin, run_match, run_match_by_opening, AgentBuilder, MatchResult, TournamentConfig};
+    use crate::agent::{Agent, FirstMoveAgent, RandomAgent};
+
+    fn test_config() -> TournamentConfig {
+        TournamentConfig {
+            h: 4,
+            s: 3,
+            thinking_time: Duration::from_millis(100),
+            max_workers: 2,
+        }
+    }
+
+    #[test]
+    fn test_run_match() {
+        let first_move: AgentBuilder = &|| Box::new(FirstMoveAgent::new(4, 3)) as Box<dyn Agent + Send>;
+        let random: AgentBuilder = &|| Box::new(RandomAgent::new(4, 3)) as Box<dyn Agent + Send>;
+
+        let result = run_match(&test_config(), first_move, random, 8);
+
+        assert_eq!(result.num_games(), 8);
+    }
+
+    #[test]
+    fn test_run_match_by_opening() {
+        let first_move: AgentBuilder = &|| Box::new(FirstMoveAgent::new(4, 3)) as Box<dyn Agent + Send>;
+        let random: AgentBuilder = &|| Box::new(RandomAgent::new(4, 3)) as Box<dyn Agent + Send>;
+
+        let openings = run_match_by_opening(&test_config(), first_move, random, 8);
+
+        assert_eq!(openings.len(), 4);
+
+        for opening in openings {
+            // one game with each color assignment
+            let result = opening.result;
+            assert_eq!(result.num_games(), 2);
+            assert!(result.agent1_white_wins + result.agent2_black_wins <= 1);
+            assert!(result.agent2_white_wins + result.agent1_black_wins <= 1);
+
+            assert!(opening.opening.is_decided().is_none());
+        }
+    }
+
+    #[test]
+    fn test_elo_difference() {
+        let result = MatchResult {
+            agent1_white_wins: 30,
+            agent1_black_wins: 20,
+            agent2_white_wins: 10,
+            agent2_black_wins: 10,
+            draws: 20,
+        };
+        assert_eq!(result.agent1_score(), 60.0 / 90.0);
+
+        // twice the points of the opponent is about 120 Elo
+        let elo = result.elo_difference().unwrap();
+        assert!((elo - 120.4).abs() < 0.1, "{elo}");
+
+        let swapped = MatchResult {
+            agent1_white_wins: 10,
+            agent1_black_wins: 10,
+            agent2_white_wins: 30,
+            agent2_black_wins: 20,
+            draws: 20,
+        };
+        assert!((swapped.elo_difference().unwrap() + elo).abs() < 1e-9);
+
+        let even = MatchResult {
+            draws: 4,
+            ..MatchResult::default()
+        };
+        assert_eq!(even.elo_difference(), Some(0.0));
+
+        let shutout = MatchResult {
+            agent1_white_wins: 4,
+            ..MatchResult::default()
+        };
+        assert_eq!(shutout.elo_difference(), None);
+        assert_eq!(MatchResult::default().elo_difference(), None);
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let first_move: AgentBuilder = &|| Box::new(FirstMoveAgent::new(4, 3)) as Box<dyn Agent + Send>;
+        let random: AgentBuilder = &|| Box::new(RandomAgent::new(4, 3)) as Box<dyn Agent + Send>;
+
+        let results = round_robin(&test_config(), &[first_move, random, first_move], 4);
+
+        assert_eq!(
+            results.iter().map(|&(pair, _)| pair).collect::<Vec<_>>(),
+            vec![(0, 1), (0, 2), (1, 2)]
+        );
+
+        for (_, result) in results {
+            assert_eq!(result.num_games(), 4);
+        }
+    }
+}
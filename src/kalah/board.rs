@@ -1,10 +1,17 @@
 use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use super::features::{non_empty_mask, range_mask, Accumulator, Features};
+use super::move_outcome::{capture_rule, HouseRef, MoveOutcome};
+use super::sweep_rule::SweepRule;
+use super::valuation::Valuation;
+use super::zobrist::{flipped_key, house_key, houses_hash, store_key};
 
 pub type House = u16;
 
 /*====================================================================================================================*/
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Player {
     White,
     Black,
@@ -31,6 +38,21 @@ impl Display for Player {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Win(Player),
+    Draw,
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameResult::Win(player) => write!(f, "{player} won"),
+            GameResult::Draw => write!(f, "Draw"),
+        }
+    }
+}
+
 /*====================================================================================================================*/
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -67,6 +89,13 @@ impl Move {
     pub fn flip_player(&self) -> Move {
         Move::new(self.house(), !self.player())
     }
+
+    /// house number for a KGP move command
+    ///
+    /// KGP numbers the houses of the player to move from 1, so this only makes sense for a move of the side to move
+    pub fn to_server_index(&self) -> u8 {
+        self.house() + 1
+    }
 }
 
 impl Display for Move {
@@ -75,6 +104,24 @@ impl Display for Move {
     }
 }
 
+/// parse a move by White from its 1-based house number, i.e. the inverse of Display
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let house: u8 = s
+            .trim()
+            .parse()
+            .map_err(|_| format!("\"{}\" is not a house number", s.trim()))?;
+
+        if !(1..=128).contains(&house) {
+            return Err(format!("House {house} out of range, houses are numbered from 1"));
+        }
+
+        Ok(Move::new(house - 1, Player::White))
+    }
+}
+
 impl Debug for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Move({}, {})", self.house(), self.player())
@@ -83,7 +130,10 @@ impl Debug for Move {
 
 /*====================================================================================================================*/
 
-// should be 24 bytes in size
+// should be 32 bytes in size
+//
+// houses and stores are Houses (u16). Seeds are never created, so no house or store can overflow as long as all seeds
+// of the board fit into one: every constructor checks that, the moves don't check again.
 pub struct Board {
     h: u8,
 
@@ -94,6 +144,10 @@ pub struct Board {
     pub their_store: u16,
 
     flipped: bool,
+
+    sweep_rule: SweepRule,
+
+    accumulator: Accumulator,
 }
 
 unsafe impl Send for Board {}
@@ -108,6 +162,7 @@ impl Board {
         their_store: House,
         flipped: bool,
     ) -> Self {
+        assert!(h > 0, "Can't create a board without houses");
         assert!(h <= 128, "Can't create more than 128 houses");
 
         assert_eq!(our_houses.len(), h as usize);
@@ -121,6 +176,16 @@ impl Board {
 
         assert_eq!(houses_vec.len(), 2 * h as usize);
 
+        debug_assert!(
+            houses_vec
+                .iter()
+                .chain([&our_store, &their_store])
+                .map(|&seeds| seeds as u32)
+                .sum::<u32>()
+                <= House::MAX as u32,
+            "Board has more seeds than fit into a store"
+        );
+
         let houses_ptr = houses_vec.as_mut_ptr();
         std::mem::forget(houses_vec);
 
@@ -134,37 +199,109 @@ impl Board {
             our_store,
             their_store,
             flipped,
+            sweep_rule: SweepRule::default(),
+            accumulator: Accumulator::default(),
         }
     }
 
+    /// create a new board with h houses per side and s seeds per house
+    ///
+    /// panics if h or s are invalid, see Board::try_new
     pub fn new(h: u8, s: House) -> Self {
-        Board::from_parts(h, vec![s; h as usize], vec![s; h as usize], 0, 0, false)
+        Board::try_new(h, s).unwrap_or_else(|err| panic!("{err}"))
     }
 
-    pub fn from_kpg(kpg: &str) -> Self {
-        let kpg: String = kpg.chars().filter(|c| !c.is_whitespace()).collect();
+    /// create a new board with h houses per side and s seeds per house, checking that:
+    /// - 1 <= h <= 128
+    /// - s >= 1, otherwise no player has a legal move from the start
+    /// - all 2 * h * s seeds fit into a single store (a House), since one player can end up with all of them
+    pub fn try_new(h: u8, s: House) -> Result<Self, String> {
+        if h == 0 || h > 128 {
+            return Err(format!("Invalid number of houses {h}, must be in 1..=128"));
+        }
 
-        let mut nums = kpg.strip_prefix('<').unwrap().strip_suffix('>').unwrap().split(',');
+        if s == 0 {
+            return Err("Invalid number of seeds 0, must be at least 1".to_owned());
+        }
 
-        let h: u8 = nums.next().unwrap().parse().unwrap();
+        let total_seeds = 2 * h as u32 * s as u32;
 
-        let our_store: u16 = nums.next().unwrap().parse().unwrap();
-        let their_store: u16 = nums.next().unwrap().parse().unwrap();
+        if total_seeds > House::MAX as u32 {
+            return Err(format!(
+                "Invalid number of seeds {s}: 2 * {h} * {s} = {total_seeds} seeds exceed the store capacity of {}",
+                House::MAX
+            ));
+        }
 
-        // let houses_vec: Vec<u16> = nums.map(|num_s| num_s.parse().unwrap()).collect();
-        let mut our_houses_vec: Vec<House> = Vec::with_capacity(h as usize);
-        for _ in 0..h {
-            our_houses_vec.push(nums.next().unwrap().parse().unwrap());
+        Ok(Board::from_parts(
+            h,
+            vec![s; h as usize],
+            vec![s; h as usize],
+            0,
+            0,
+            false,
+        ))
+    }
+
+    /// parse a board in KGP format, i.e. <h, our store, their store, our houses..., their houses...>
+    ///
+    /// panics if kgp is malformed, see Board::try_from_kgp
+    pub fn from_kgp(kgp: &str) -> Self {
+        Board::try_from_kgp(kgp).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// parse a board in KGP format, checking that it is well-formed, that 1 <= h <= 128 and that all seeds fit into a
+    /// single store
+    pub fn try_from_kgp(kgp: &str) -> Result<Self, String> {
+        let kgp: String = kgp.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let nums = kgp
+            .strip_prefix('<')
+            .and_then(|kgp| kgp.strip_suffix('>'))
+            .ok_or_else(|| format!("Board {kgp} is not enclosed in <>"))?
+            .split(',')
+            .map(|num| {
+                num.parse::<u16>()
+                    .map_err(|_| format!("Invalid number {num:?} in board {kgp}"))
+            })
+            .collect::<Result<Vec<u16>, _>>()?;
+
+        let h = nums[0];
+
+        if h == 0 || h > 128 {
+            return Err(format!(
+                "Invalid number of houses {h} in board {kgp}, must be in 1..=128"
+            ));
         }
 
-        let mut their_houses_vec: Vec<House> = Vec::with_capacity(h as usize);
-        for _ in 0..h {
-            their_houses_vec.push(nums.next().unwrap().parse().unwrap());
+        if nums.len() != 2 * h as usize + 3 {
+            return Err(format!(
+                "Board {kgp} has {} numbers, expected {} for {h} houses",
+                nums.len(),
+                2 * h as usize + 3
+            ));
         }
 
-        assert_eq!(nums.count(), 0);
+        let (our_store, their_store) = (nums[1], nums[2]);
+        let our_houses_vec: Vec<House> = nums[3..][..h as usize].to_vec();
+        let their_houses_vec: Vec<House> = nums[3 + h as usize..].to_vec();
+
+        // from_parts only checks this in debug builds, but this comes from the outside
+        let total_seeds: u32 = nums[1..].iter().map(|&seeds| seeds as u32).sum();
+        if total_seeds > House::MAX as u32 {
+            return Err(format!(
+                "Board {kgp} has {total_seeds} seeds, more than fit into a store"
+            ));
+        }
 
-        Board::from_parts(h, our_houses_vec, their_houses_vec, our_store, their_store, false)
+        Ok(Board::from_parts(
+            h as u8,
+            our_houses_vec,
+            their_houses_vec,
+            our_store,
+            their_store,
+            false,
+        ))
     }
 
     /// clone other into self, overwriting the old values, but not reallocating memory
@@ -181,7 +318,10 @@ impl Board {
         self.our_store = other.our_store;
         self.their_store = other.their_store;
 
-        self.flipped = other.flipped
+        self.flipped = other.flipped;
+        self.sweep_rule = other.sweep_rule;
+
+        self.accumulator = other.accumulator;
     }
 
     pub fn to_kgp(&self) -> String {
@@ -238,6 +378,14 @@ impl Board {
     }
 
     pub fn our_houses_mut(&mut self) -> &mut [House] {
+        // houses might get changed arbitrarily, so the tracked features can't be updated incrementally
+        self.accumulator.stale = true;
+
+        self.our_houses_raw_mut()
+    }
+
+    // like our_houses_mut, but leaves updating the accumulator to the caller
+    fn our_houses_raw_mut(&mut self) -> &mut [House] {
         // let h = self.h() as usize;
         // &mut self.houses[..h]
         unsafe { std::slice::from_raw_parts_mut(self.our_houses_ptr, self.h as usize) }
@@ -250,24 +398,193 @@ impl Board {
     }
 
     pub fn their_houses_mut(&mut self) -> &mut [House] {
+        self.accumulator.stale = true;
+
+        self.their_houses_raw_mut()
+    }
+
+    fn their_houses_raw_mut(&mut self) -> &mut [House] {
         // let h = self.h() as usize;
         // &mut self.houses[h..]
         unsafe { std::slice::from_raw_parts_mut(self.their_houses_ptr, self.h as usize) }
     }
 
+    /// number of seeds in their house opposite of our house our_house_idx
+    pub fn opposite_house(&self, our_house_idx: usize) -> House {
+        self.their_houses()[self.h as usize - our_house_idx - 1]
+    }
+
+    pub fn opposite_house_mut(&mut self, our_house_idx: usize) -> &mut House {
+        let their_house_idx = self.h as usize - our_house_idx - 1;
+        &mut self.their_houses_mut()[their_house_idx]
+    }
+
+    /// the capture of a move whose last seed landed in landing_house, on the board right after sowing: their house
+    /// opposite of it and the seeds in there, None if the move doesn't capture
+    ///
+    /// landing_house indexes like in apply_move: our houses, then our store, then their houses. Only a landing in one
+    /// of our houses can capture, see capture_rule.
+    pub fn would_capture(&self, landing_house: usize) -> Option<(usize, House)> {
+        if landing_house >= self.h() as usize {
+            return None;
+        }
+
+        let opposite_seeds = self.opposite_house(landing_house);
+
+        capture_rule(self.our_houses()[landing_house] as usize, opposite_seeds as usize)
+            .then_some((self.h() as usize - landing_house - 1, opposite_seeds))
+    }
+
+    /// keep features up to date incrementally in apply_move from now on, instead of recomputing them on every query
+    ///
+    /// evaluations call this on the root board with the features they read, e.g. seed_diff_valuation reads
+    /// Features::HOUSE_SEEDS. Untracked features still work, they just get recomputed from scratch.
+    pub fn track_features(&mut self, features: Features) {
+        self.accumulator.tracked = features;
+        self.recompute_accumulator();
+    }
+
+    fn recompute_accumulator(&mut self) {
+        let mut accumulator = self.accumulator;
+        accumulator.recompute(self.our_houses(), self.their_houses(), self.our_player());
+        self.accumulator = accumulator;
+    }
+
+    pub fn tracked_features(&self) -> Features {
+        self.accumulator.tracked
+    }
+
+    /// number of seeds in our houses and their houses (not counting the stores)
+    pub fn house_seeds(&self) -> (House, House) {
+        if self.accumulator.is_valid(Features::HOUSE_SEEDS) {
+            (self.accumulator.our_house_seeds, self.accumulator.their_house_seeds)
+        } else {
+            (self.our_houses().iter().sum(), self.their_houses().iter().sum())
+        }
+    }
+
+    /// number of our non-empty houses and their non-empty houses, i.e. the number of legal moves of either player
+    pub fn non_empty_houses(&self) -> (u8, u8) {
+        if self.accumulator.is_valid(Features::NON_EMPTY_HOUSES) {
+            (self.accumulator.our_non_empty, self.accumulator.their_non_empty)
+        } else {
+            let count = |houses: &[House]| houses.iter().filter(|&&house| house != 0).count() as u8;
+            (count(self.our_houses()), count(self.their_houses()))
+        }
+    }
+
+    /// iterate over our houses together with the houses opposite of them, yielding (idx, our seeds, their seeds)
+    pub fn house_pairs(&self) -> impl Iterator<Item = (usize, House, House)> + '_ {
+        self.our_houses()
+            .iter()
+            .zip(self.their_houses().iter().rev())
+            .enumerate()
+            .map(|(idx, (&our_house, &their_house))| (idx, our_house, their_house))
+    }
+
     pub fn flipped(&self) -> bool {
         self.flipped
     }
 
+    // player our houses belong to in the board's original orientation
+    fn our_player(&self) -> Player {
+        match self.flipped {
+            false => Player::White,
+            true => Player::Black,
+        }
+    }
+
+    /// Zobrist hash of the position, e.g. as the key of a transposition table
+    ///
+    /// it covers the houses, the stores and flipped, with the houses and stores hashed in the board's original
+    /// orientation: boards with the same contents and the same flipped hash the same, however they got there, and a
+    /// flip changes the hash. Incremental in apply_move if Features::ZOBRIST is tracked (see track_features),
+    /// otherwise the same as zobrist_hash_from_scratch.
+    pub fn zobrist_hash(&self) -> u64 {
+        if self.accumulator.is_valid(Features::ZOBRIST) {
+            self.zobrist_hash_with(self.accumulator.house_hash)
+        } else {
+            self.zobrist_hash_from_scratch()
+        }
+    }
+
+    /// Zobrist hash of the position computed from all houses, see zobrist_hash
+    pub fn zobrist_hash_from_scratch(&self) -> u64 {
+        self.zobrist_hash_with(houses_hash(self.our_player(), self.our_houses(), self.their_houses()))
+    }
+
+    // the stores aren't part of the accumulator since they can be changed from outside any time, their keys are
+    // cheap to add on every query
+    fn zobrist_hash_with(&self, house_hash: u64) -> u64 {
+        let our_player = self.our_player();
+
+        let hash = house_hash ^ store_key(our_player, self.our_store) ^ store_key(!our_player, self.their_store);
+
+        match self.flipped {
+            false => hash,
+            true => hash ^ flipped_key(),
+        }
+    }
+
+    // keep the tracked house hash up to date after one seed got sown into each of our (or their) houses in range
+    fn rehash_sown(&mut self, ours: bool, range: std::ops::Range<usize>) {
+        let (player, houses) = match ours {
+            true => (self.our_player(), self.our_houses()),
+            false => (!self.our_player(), self.their_houses()),
+        };
+
+        let hash = houses[range.clone()].iter().zip(range).fold(0, |hash, (&seeds, idx)| {
+            hash ^ house_key(player, idx, seeds - 1) ^ house_key(player, idx, seeds)
+        });
+
+        self.accumulator.house_hash ^= hash;
+    }
+
+    pub fn sweep_rule(&self) -> SweepRule {
+        self.sweep_rule
+    }
+
+    /// play by sweep_rule from now on, the board's clones (and so all positions a search reaches) inherit it
+    pub fn set_sweep_rule(&mut self, sweep_rule: SweepRule) {
+        self.sweep_rule = sweep_rule;
+    }
+
     pub fn flip_board(&mut self) {
         std::mem::swap(&mut self.our_houses_ptr, &mut self.their_houses_ptr);
 
         std::mem::swap(&mut self.our_store, &mut self.their_store);
 
+        self.accumulator.flip();
+
         self.flipped = !self.flipped
     }
 
+    /// copy of the board from the other side's perspective, with the conversion of valuations computed on the copy back
+    /// to the perspective of this board
+    ///
+    /// for analysis code comparing both sides of a position, so the board flip and the valuation flip can't get out of
+    /// step
+    pub fn analysis_flip(&self) -> (Board, fn(Valuation) -> Valuation) {
+        let mut flipped = self.clone();
+        flipped.flip_board();
+
+        (flipped, |value| -value)
+    }
+
+    /// apply move_, returns true if the player gets a bonus move (i.e. moves again)
     pub fn apply_move(&mut self, move_: Move) -> bool {
+        self.apply_move_outcome(move_).bonus
+    }
+
+    /// apply the move out of house for side, for callers that track the side to move themselves
+    ///
+    /// same as apply_move with a Move of side, without having to get the player bit of the Move right
+    pub fn apply_move_for(&mut self, house: u8, side: Player) -> MoveOutcome {
+        self.apply_move_outcome(Move::new(house, side))
+    }
+
+    /// apply move_ and report where the last seed landed and what got captured, from the perspective of the mover
+    pub(super) fn apply_move_outcome(&mut self, move_: Move) -> MoveOutcome {
         assert!(
             move_.house() < self.h(),
             "Trying to apply move {move_} that is out of range"
@@ -276,20 +593,92 @@ impl Board {
         if move_.player() == Player::Black {
             // if the move is by 'Black': flip the board, apply the move as if by White, flip the board again
             self.flip_board();
-            let ret = self.apply_move(move_.flip_player());
+            let ret = self.apply_move_outcome(move_.flip_player());
             self.flip_board();
             return ret;
         }
 
-        let h = self.h() as u16;
-
         let start_house = move_.house() as usize;
 
+        let update_accumulator = !self.accumulator.tracked.is_empty() && !self.accumulator.stale;
+        let update_hash = update_accumulator && self.accumulator.tracked.contains(Features::ZOBRIST);
+
         let seeds_in_hand = self.our_houses()[start_house];
-        self.our_houses_mut()[start_house] = 0;
+        self.our_houses_raw_mut()[start_house] = 0;
 
         assert!(seeds_in_hand != 0, "Trying to move out of empty house");
 
+        if update_hash {
+            // an empty house has key 0
+            self.accumulator.house_hash ^= house_key(self.our_player(), start_house, seeds_in_hand);
+        }
+
+        if (seeds_in_hand as usize) < self.h() as usize - start_house {
+            // by far the most common case: all seeds land in our houses after the starting house
+            self.sow_within_our_houses(start_house, seeds_in_hand, update_accumulator);
+        } else {
+            self.sow(start_house, seeds_in_hand, update_accumulator);
+        }
+
+        let h = self.h() as usize;
+        let cycle_length = 2 * h + 1;
+
+        // index of last house:
+        // 0..h : our_houses[i]
+        // h : our_store
+        // (h+1)..(2h+1) : their_house[i - h - 1] (not relevant)
+        let last_house_idx = (start_house + seeds_in_hand as usize) % cycle_length;
+
+        let capture = self.would_capture(last_house_idx);
+
+        if let Some((opposite, captured)) = capture {
+            self.our_store += captured + 1;
+            self.our_houses_raw_mut()[last_house_idx] = 0;
+            self.their_houses_raw_mut()[opposite] = 0;
+
+            if update_accumulator {
+                self.accumulator.our_house_seeds -= 1;
+                self.accumulator.their_house_seeds -= captured;
+                self.accumulator.our_non_empty -= 1;
+                self.accumulator.their_non_empty -= 1;
+                self.accumulator.our_legal_mask &= !(1 << last_house_idx);
+                self.accumulator.their_legal_mask &= !(1 << opposite);
+            }
+
+            if update_hash {
+                self.accumulator.house_hash ^=
+                    house_key(self.our_player(), last_house_idx, 1) ^ house_key(!self.our_player(), opposite, captured);
+            }
+        }
+
+        if !self.has_legal_move() {
+            // if no moves remain: finish the board, we made the last move
+            self.sweep_houses(true);
+        }
+
+        if self.accumulator.stale && !self.accumulator.tracked.is_empty() {
+            // houses were changed from outside since the last move, catch up
+            self.recompute_accumulator();
+        }
+
+        let landing = match last_house_idx {
+            idx if idx < h => HouseRef::Ours(idx as u8),
+            idx if idx == h => HouseRef::OurStore,
+            idx => HouseRef::Theirs((idx - h - 1) as u8),
+        };
+
+        MoveOutcome {
+            // if last seed in our store -> bonus move
+            bonus: landing == HouseRef::OurStore,
+            landing,
+            capture: capture.map(|(opposite, captured)| (opposite as u8, captured)),
+        }
+    }
+
+    /// sow seeds_in_hand seeds (already taken out of start_house) counter-clockwise, skipping their store
+    fn sow(&mut self, start_house: usize, seeds_in_hand: House, update_accumulator: bool) {
+        let h = self.h() as u16;
+
         // number of all houses we distribute seeds to:
         // h x our houses, 1 x our store, h x their houses
         let cycle_length = 2 * h + 1;
@@ -299,22 +688,40 @@ impl Board {
         // number of seeds remaining after complete cycles have been made
         let mut rem = (seeds_in_hand % cycle_length) as usize;
 
-        if seeds_in_hand > cycle_length {
+        if seeds_in_hand >= cycle_length {
+            debug_assert!(
+                self.our_store as u32 + num_cycles as u32 <= House::MAX as u32,
+                "Store overflows while sowing {seeds_in_hand} seeds"
+            );
+
+            // can't happen as long as the seeds on the board fit into a House (see new), but a house changed through
+            // a *_mut accessor could otherwise silently wrap around; + 1 for the seed sown after the cycles
+            debug_assert!(
+                self.our_houses()
+                    .iter()
+                    .chain(self.their_houses())
+                    .all(|&seeds| (seeds as u32 + num_cycles as u32) < House::MAX as u32),
+                "House overflows while sowing {seeds_in_hand} seeds"
+            );
+
             // distribute seeds to all houses and our store evenly
-            for our_house in self.our_houses_mut() {
+            for our_house in self.our_houses_raw_mut() {
                 *our_house += num_cycles;
             }
 
             self.our_store += num_cycles;
 
-            for their_house in self.their_houses_mut() {
+            for their_house in self.their_houses_raw_mut() {
                 *their_house += num_cycles;
             }
         }
 
+        // number of seeds sown one by one after the complete cycles, for the accumulator
+        let rem_ours_after_start = rem.min(h as usize - start_house - 1);
+
         // our houses after starting house
         for our_house in self
-            .our_houses_mut()
+            .our_houses_raw_mut()
             .iter_mut()
             .skip(start_house + 1) // skip until after starting house
             .take(rem)
@@ -323,21 +730,27 @@ impl Board {
             rem -= 1;
         }
 
+        let rem_store = rem.min(1);
+
         // our store
         if rem > 0 {
             self.our_store += 1;
             rem -= 1;
         }
 
+        let rem_theirs = rem.min(h as usize);
+
         // their houses
-        for their_house in self.their_houses_mut().iter_mut().take(rem) {
+        for their_house in self.their_houses_raw_mut().iter_mut().take(rem) {
             *their_house += 1;
             rem -= 1;
         }
 
+        let rem_ours_wrapped = rem;
+
         // our houses until starting house (inclusive)
         if rem > 0 {
-            for our_house in self.our_houses_mut().iter_mut().take(rem) {
+            for our_house in self.our_houses_raw_mut().iter_mut().take(rem) {
                 *our_house += 1;
                 rem -= 1;
             }
@@ -345,30 +758,65 @@ impl Board {
 
         assert_eq!(rem, 0);
 
-        // index of last house:
-        // 0..h : our_houses[i]
-        // h : our_store
-        // (h+1)..(2h+1) : their_house[i - h - 1] (not relevant)
-        let h = h as usize; // only used for indexing from here on, so 'convert' to usize once
-        let last_house_idx = (start_house + seeds_in_hand as usize) % cycle_length as usize;
+        if update_accumulator {
+            let store_sown = num_cycles + rem_store as House;
+            let theirs_sown = num_cycles * h + rem_theirs as House;
 
-        // last seed in our house && our house was empty && opposite house if not empty:
-        if last_house_idx < h
-            && self.our_houses()[last_house_idx] == 1
-            && self.their_houses()[h - last_house_idx - 1] > 0
-        {
-            self.our_store += self.their_houses()[h - last_house_idx - 1] + 1;
-            self.our_houses_mut()[last_house_idx] = 0;
-            self.their_houses_mut()[h - last_house_idx - 1] = 0;
+            // all seeds that didn't end up in our store or their houses are back in our houses
+            self.accumulator.our_house_seeds -= store_sown + theirs_sown;
+            self.accumulator.their_house_seeds += theirs_sown;
+
+            if num_cycles > 0 {
+                // every house got seeds, just count again
+                self.recompute_accumulator();
+            } else {
+                // without complete cycles every house got at most one seed, so it was empty iff it has one seed now
+                let newly_non_empty = |houses: &[House]| houses.iter().filter(|&&house| house == 1).count() as u8;
+
+                let our_houses = self.our_houses();
+                let our_non_empty = newly_non_empty(&our_houses[start_house + 1..][..rem_ours_after_start])
+                    + newly_non_empty(&our_houses[..rem_ours_wrapped]);
+                let their_non_empty = newly_non_empty(&self.their_houses()[..rem_theirs]);
+
+                // the starting house is empty now, less than a cycle never reaches it again
+                self.accumulator.our_non_empty = self.accumulator.our_non_empty + our_non_empty - 1;
+                self.accumulator.their_non_empty += their_non_empty;
+
+                self.accumulator.our_legal_mask = (self.accumulator.our_legal_mask & !(1 << start_house))
+                    | range_mask(start_house + 1, rem_ours_after_start)
+                    | range_mask(0, rem_ours_wrapped);
+                self.accumulator.their_legal_mask |= range_mask(0, rem_theirs);
+
+                if self.accumulator.tracked.contains(Features::ZOBRIST) {
+                    self.rehash_sown(true, start_house + 1..start_house + 1 + rem_ours_after_start);
+                    self.rehash_sown(false, 0..rem_theirs);
+                    self.rehash_sown(true, 0..rem_ours_wrapped);
+                }
+            }
         }
+    }
 
-        if !self.has_legal_move() {
-            // if no moves remain: finish the board
-            self.finish_game();
+    /// sow for hands small enough to only reach our houses after start_house, without any of the wrap-around logic
+    fn sow_within_our_houses(&mut self, start_house: usize, seeds_in_hand: House, update_accumulator: bool) {
+        let sown = &mut self.our_houses_raw_mut()[start_house + 1..][..seeds_in_hand as usize];
+
+        let mut newly_non_empty = 0;
+
+        for our_house in sown {
+            newly_non_empty += (*our_house == 0) as u8;
+            *our_house += 1;
         }
 
-        // if last seed in our store -> true (bonus move), else -> false
-        last_house_idx == h
+        if update_accumulator {
+            // seeds stay on our side, but the starting house is empty now
+            self.accumulator.our_non_empty = self.accumulator.our_non_empty + newly_non_empty - 1;
+            self.accumulator.our_legal_mask = (self.accumulator.our_legal_mask & !(1 << start_house))
+                | range_mask(start_house + 1, seeds_in_hand as usize);
+
+            if self.accumulator.tracked.contains(Features::ZOBRIST) {
+                self.rehash_sown(true, start_house + 1..start_house + 1 + seeds_in_hand as usize);
+            }
+        }
     }
 
     pub fn legal_moves(&self, player: Player) -> Vec<Move> {
@@ -385,6 +833,25 @@ impl Board {
             .collect()
     }
 
+    /// bitmask of the legal moves of player: bit i is set iff house i of player is not empty
+    ///
+    /// since h <= 128 this always fits into a u128
+    pub fn legal_mask(&self, player: Player) -> u128 {
+        if self.accumulator.is_valid(Features::LEGAL_MASK) {
+            return match player {
+                Player::White => self.accumulator.our_legal_mask,
+                Player::Black => self.accumulator.their_legal_mask,
+            };
+        }
+
+        let houses = match player {
+            Player::White => self.our_houses(),
+            Player::Black => self.their_houses(),
+        };
+
+        non_empty_mask(houses)
+    }
+
     pub fn is_legal_move(&self, move_: Move) -> bool {
         match move_.player() {
             Player::White => self.our_houses()[move_.house() as usize] != 0,
@@ -392,34 +859,160 @@ impl Board {
         }
     }
 
+    /// true if both sides have a non-empty house, i.e. the game isn't over yet
+    ///
+    /// O(1) if Features::LEGAL_MASK is tracked, the searches check this at every node
     pub fn has_legal_move(&self) -> bool {
+        if self.accumulator.is_valid(Features::LEGAL_MASK) {
+            return self.accumulator.our_legal_mask != 0 && self.accumulator.their_legal_mask != 0;
+        }
+
         self.our_houses().iter().any(|&house| house != 0) && self.their_houses().iter().any(|&house| house != 0)
     }
 
+    /// true if no seed has been moved yet, i.e. all houses hold the same number of seeds and the stores are empty
+    pub fn is_start_position(&self) -> bool {
+        let s = self.our_houses()[0];
+
+        self.our_store == 0
+            && self.their_store == 0
+            && self
+                .our_houses()
+                .iter()
+                .chain(self.their_houses())
+                .all(|&house| house == s)
+    }
+
+    /// result of the game if it is over, from the perspective of the board's original orientation (see flipped)
+    pub fn winner(&self) -> Option<GameResult> {
+        if self.has_legal_move() {
+            return None;
+        }
+
+        let (our_score, their_score) = self.final_scores();
+
+        let (our_player, their_player) = match self.flipped {
+            false => (Player::White, Player::Black),
+            true => (Player::Black, Player::White),
+        };
+
+        Some(match our_score.cmp(&their_score) {
+            std::cmp::Ordering::Greater => GameResult::Win(our_player),
+            std::cmp::Ordering::Less => GameResult::Win(their_player),
+            std::cmp::Ordering::Equal => GameResult::Draw,
+        })
+    }
+
+    /// our and their score if the game ended now, with the remaining seeds swept like finish_game does
+    pub fn final_scores(&self) -> (u32, u32) {
+        let (our_house_seeds, their_house_seeds) = self.house_seeds();
+        let (our_sweep, their_sweep) = self
+            .sweep_rule
+            .sweep(our_house_seeds as u32, their_house_seeds as u32, false);
+
+        (self.our_store as u32 + our_sweep, self.their_store as u32 + their_sweep)
+    }
+
+    /// number of seeds on the board, in the houses and stores of both players
+    ///
+    /// moves never create or remove seeds, so this stays the same over the whole game (unless SweepRule::Discard
+    /// removes the remaining ones at the end)
+    pub fn total_seeds(&self) -> u32 {
+        let (our_house_seeds, their_house_seeds) = self.house_seeds();
+
+        self.our_store as u32 + self.their_store as u32 + our_house_seeds as u32 + their_house_seeds as u32
+    }
+
+    /// result of the game if it is already decided, i.e. it is over or one store holds more than half of all seeds
+    ///
+    /// like winner, the result refers to the board's original orientation (see flipped)
+    pub fn is_decided(&self) -> Option<GameResult> {
+        if !self.has_legal_move() {
+            return self.winner();
+        }
+
+        let total_seeds = self.total_seeds();
+
+        let (our_player, their_player) = match self.flipped {
+            false => (Player::White, Player::Black),
+            true => (Player::Black, Player::White),
+        };
+
+        if 2 * self.our_store as u32 > total_seeds {
+            Some(GameResult::Win(our_player))
+        } else if 2 * self.their_store as u32 > total_seeds {
+            Some(GameResult::Win(their_player))
+        } else {
+            None
+        }
+    }
+
+    /// width of the columns in Display: enough for the largest house or store, but at least 3 so boards line up
+    pub fn display_width(&self) -> usize {
+        let max_seeds = self
+            .our_houses()
+            .iter()
+            .chain(self.their_houses())
+            .chain([&self.our_store, &self.their_store])
+            .max()
+            .unwrap();
+
+        max_seeds.to_string().len().max(3)
+    }
+
+    /// end the game now, sweeping the seeds left in the houses into the stores by the board's sweep rule
+    ///
+    /// for SweepRule::LastMover the last move counts as theirs, since it's our turn. apply_move finishes games on its
+    /// own, right after our move.
     pub fn finish_game(&mut self) {
-        self.our_store += self.our_houses().iter().sum::<u16>();
-        self.their_store += self.their_houses().iter().sum::<u16>();
+        self.sweep_houses(false);
+    }
+
+    fn sweep_houses(&mut self, we_moved_last: bool) {
+        // sum up as u32, so an overflow gets reported as such instead of as one in the middle of the sum
+        let our_house_seeds: u32 = self.our_houses().iter().map(|&seeds| seeds as u32).sum();
+        let their_house_seeds: u32 = self.their_houses().iter().map(|&seeds| seeds as u32).sum();
+
+        let (our_sweep, their_sweep) = self.sweep_rule.sweep(our_house_seeds, their_house_seeds, we_moved_last);
+
+        debug_assert!(
+            self.our_store as u32 + our_sweep <= House::MAX as u32
+                && self.their_store as u32 + their_sweep <= House::MAX as u32,
+            "Store overflows when finishing the game"
+        );
+
+        self.our_store += our_sweep as House;
+        self.their_store += their_sweep as House;
 
-        self.our_houses_mut().fill(0);
-        self.their_houses_mut().fill(0);
+        self.our_houses_raw_mut().fill(0);
+        self.their_houses_raw_mut().fill(0);
+
+        // all houses are empty, so the accumulator is exact again even if it was stale
+        let tracked = self.accumulator.tracked;
+        self.accumulator = Accumulator {
+            tracked,
+            ..Accumulator::default()
+        };
     }
 }
 
 impl Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:>3} |", self.their_store)?;
+        let width = self.display_width();
+
+        write!(f, "{:>width$} |", self.their_store)?;
 
         for their_house in self.their_houses().iter().rev() {
-            write!(f, " {:>3}", *their_house)?;
+            write!(f, " {:>width$}", *their_house)?;
         }
 
-        write!(f, "\n\n      ")?;
+        write!(f, "\n\n{:width$}   ", "")?;
 
         for our_house in self.our_houses() {
-            write!(f, "{our_house:>3} ")?;
+            write!(f, "{our_house:>width$} ")?;
         }
 
-        write!(f, "| {:>3}", self.our_store)
+        write!(f, "| {:>width$}", self.our_store)
     }
 }
 
@@ -462,6 +1055,8 @@ impl Clone for Board {
             their_store: self.their_store,
             h: self.h,
             flipped: self.flipped,
+            sweep_rule: self.sweep_rule,
+            accumulator: self.accumulator,
         }
     }
 }
@@ -486,7 +1081,10 @@ impl Drop for Board {
 
 #[cfg(test)]
 mod tests {
-    use crate::Board;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use crate::{Board, House, Move, Player};
 
     #[test]
     fn test_board_new() {
@@ -504,6 +1102,163 @@ mod tests {
         for their_house in board.their_houses() {
             assert!(*their_house == s);
         }
+
+        assert_eq!(board.total_seeds(), 2 * h as u32 * s as u32);
+    }
+
+    #[test]
+    fn test_board_try_new() {
+        assert!(Board::try_new(0, 4).is_err());
+        assert!(Board::try_new(129, 4).is_err());
+        assert!(Board::try_new(6, 0).is_err());
+
+        assert!(Board::try_new(1, 1).is_ok());
+        assert!(Board::try_new(128, 1).is_ok());
+
+        // 2 * 6 * 5461 = 65532 seeds still fit into a store, 2 * 6 * 5462 = 65544 don't
+        assert!(Board::try_new(6, 5461).is_ok());
+        assert!(Board::try_new(6, 5462).is_err());
+        assert!(Board::try_new(128, u16::MAX).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_board_new_no_houses() {
+        let _ = Board::new(0, 4);
+    }
+
+    #[test]
+    fn test_legal_mask() {
+        use rand::seq::SliceRandom;
+        use rand::thread_rng;
+
+        let mut rng = thread_rng();
+
+        for _ in 0..100 {
+            let mut board = Board::new(6, 4);
+            let mut player = Player::White;
+
+            while board.has_legal_move() {
+                for p in [Player::White, Player::Black] {
+                    let mask = board.legal_mask(p);
+                    let legal_moves = board.legal_moves(p);
+
+                    assert_eq!(mask.count_ones() as usize, legal_moves.len());
+
+                    for move_ in legal_moves {
+                        assert!(mask & (1 << move_.house()) != 0);
+                    }
+                }
+
+                let move_ = *board.legal_moves(player).choose(&mut rng).unwrap();
+
+                if !board.apply_move(move_) {
+                    player = !player;
+                }
+            }
+
+            assert_eq!(board.legal_mask(Player::White), 0);
+            assert_eq!(board.legal_mask(Player::Black), 0);
+        }
+    }
+
+    #[test]
+    fn test_incremental_features() {
+        use rand::seq::SliceRandom;
+        use rand::{thread_rng, Rng};
+
+        use crate::kalah::Features;
+
+        let mut rng = thread_rng();
+
+        let assert_features_match = |board: &Board| {
+            let mut from_scratch = board.clone();
+            from_scratch.track_features(Features::NONE);
+
+            assert_eq!(board.house_seeds(), from_scratch.house_seeds(), "{board}");
+            assert_eq!(board.non_empty_houses(), from_scratch.non_empty_houses(), "{board}");
+            assert_eq!(
+                board.legal_mask(Player::White),
+                from_scratch.legal_mask(Player::White),
+                "{board}"
+            );
+            assert_eq!(
+                board.legal_mask(Player::Black),
+                from_scratch.legal_mask(Player::Black),
+                "{board}"
+            );
+            assert_eq!(board.has_legal_move(), from_scratch.has_legal_move(), "{board}");
+            assert_eq!(board.zobrist_hash(), from_scratch.zobrist_hash(), "{board}");
+
+            // clones carry the accumulator along
+            let cloned = board.clone();
+            assert_eq!(
+                cloned.legal_mask(Player::White),
+                board.legal_mask(Player::White),
+                "{board}"
+            );
+            assert_eq!(
+                cloned.legal_mask(Player::Black),
+                board.legal_mask(Player::Black),
+                "{board}"
+            );
+        };
+
+        for _ in 0..200 {
+            // large s to also get complete cycles
+            let mut board = Board::new(rng.gen_range(1..=6), rng.gen_range(1..=20));
+            board.track_features(Features::ALL);
+
+            let mut player = Player::White;
+
+            while board.has_legal_move() {
+                let move_ = *board.legal_moves(player).choose(&mut rng).unwrap();
+
+                if !board.apply_move(move_) {
+                    player = !player;
+                }
+
+                // apply_move keeps the accumulator up to date, so there is no fallback to recomputing
+                assert!(!board.accumulator.stale);
+                assert_features_match(&board);
+
+                if rng.gen_bool(0.1) {
+                    board.flip_board();
+                    player = !player;
+                    assert_features_match(&board);
+                }
+
+                if rng.gen_bool(0.05) {
+                    // moving a seed from outside makes the accumulator stale until the next move
+                    let houses = board.our_houses_mut();
+                    if let Some(idx) = houses.iter().position(|&house| house > 1) {
+                        houses[idx] -= 1;
+                        board.our_store += 1;
+                    }
+
+                    assert!(board.accumulator.stale);
+                    assert_features_match(&board);
+                }
+            }
+
+            assert_features_match(&board);
+        }
+    }
+
+    #[test]
+    fn test_house_pairs() {
+        let board = Board::from_kgp("<3, 0, 0, 11, 12, 13, 21, 22, 23>");
+
+        assert_eq!(board.opposite_house(0), 23);
+        assert_eq!(board.opposite_house(1), 22);
+        assert_eq!(board.opposite_house(2), 21);
+
+        let pairs: Vec<_> = board.house_pairs().collect();
+        assert_eq!(pairs, vec![(0, 11, 23), (1, 12, 22), (2, 13, 21)]);
+
+        let mut board = board;
+        *board.opposite_house_mut(0) = 0;
+        assert_eq!(board.their_houses(), &[21, 22, 0]);
     }
 
     #[test]
@@ -536,10 +1291,99 @@ mod tests {
     }
 
     #[test]
-    fn test_from_to_kpg() {
-        let kpg = "<3, 2, 3, 11, 12, 13, 21, 22, 23>";
+    fn test_zobrist_hash() {
+        use std::collections::HashMap;
+
+        use crate::kalah::Features;
+
+        let board = Board::from_kgp("<3, 2, 3, 11, 12, 13, 21, 22, 23>");
+
+        // the keys come from ZOBRIST_SEED, so the hash of a board never changes
+        assert_eq!(board.zobrist_hash(), 0x1b8e_90a9_bb5f_30bd);
+        assert_eq!(board.clone().zobrist_hash(), board.zobrist_hash());
+
+        // same contents from the other side: flip_board swaps the house pointers, from_parts doesn't
+        let mut flipped = board.clone();
+        flipped.flip_board();
+        let same_as_flipped = Board::from_parts(3, vec![21, 22, 23], vec![11, 12, 13], 3, 2, true);
+
+        assert_eq!(same_as_flipped.zobrist_hash(), flipped.zobrist_hash());
+        assert_ne!(flipped.zobrist_hash(), board.zobrist_hash());
+
+        // flipping back restores the hash, only flipped tells the otherwise identical boards apart
+        flipped.flip_board();
+        assert_eq!(flipped.zobrist_hash(), board.zobrist_hash());
+
+        let mut not_flipped = same_as_flipped.clone();
+        not_flipped.flip_board();
+        let flipped_only = Board::from_parts(3, vec![11, 12, 13], vec![21, 22, 23], 2, 3, true);
+        assert_ne!(flipped_only.zobrist_hash(), not_flipped.zobrist_hash());
+
+        // every house and store counts, also with more seeds than have a key of their own
+        let variants = [
+            "<3, 2, 3, 12, 11, 13, 21, 22, 23>",
+            "<3, 2, 3, 11, 12, 13, 23, 22, 21>",
+            "<3, 3, 2, 11, 12, 13, 21, 22, 23>",
+            "<3, 2, 3, 21, 22, 23, 11, 12, 13>",
+            "<3, 2, 3, 11, 12, 200, 21, 22, 23>",
+            "<3, 2, 3, 11, 12, 201, 21, 22, 23>",
+        ];
+        let mut hashes: Vec<u64> = variants
+            .iter()
+            .map(|kgp| Board::from_kgp(kgp).zobrist_hash())
+            .chain([board.zobrist_hash()])
+            .collect();
+        hashes.sort();
+        hashes.dedup();
+        assert_eq!(hashes.len(), variants.len() + 1);
+
+        // positions reached by different lines hash the same, with the hash updated incrementally along the lines
+        fn visit(board: &Board, plies: usize, seen: &mut HashMap<(String, bool), u64>, transpositions: &mut usize) {
+            let hash = board.zobrist_hash();
+            assert_eq!(hash, board.zobrist_hash_from_scratch(), "{board:?}");
+
+            if let Some(&seen_hash) = seen.get(&(board.to_kgp(), board.flipped())) {
+                assert_eq!(hash, seen_hash, "{board:?}");
+                *transpositions += 1;
+                return;
+            }
+            seen.insert((board.to_kgp(), board.flipped()), hash);
+
+            if plies == 0 || !board.has_legal_move() {
+                return;
+            }
+
+            for move_ in board.legal_moves(Player::White) {
+                let mut board = board.clone();
+                if !board.apply_move(move_) {
+                    board.flip_board();
+                }
+                visit(&board, plies - 1, seen, transpositions);
+            }
+        }
+
+        let mut root = Board::new(4, 3);
+        root.track_features(Features::ZOBRIST);
+
+        let mut seen = HashMap::new();
+        let mut transpositions = 0;
+        visit(&root, 6, &mut seen, &mut transpositions);
 
-        let board = Board::from_kpg(kpg);
+        assert!(transpositions > 0);
+
+        // and different positions differently
+        let mut hashes: Vec<u64> = seen.into_values().collect();
+        let num_positions = hashes.len();
+        hashes.sort();
+        hashes.dedup();
+        assert_eq!(hashes.len(), num_positions);
+    }
+
+    #[test]
+    fn test_from_to_kgp() {
+        let kgp = "<3, 2, 3, 11, 12, 13, 21, 22, 23>";
+
+        let board = Board::from_kgp(kgp);
 
         assert_eq!(board.h(), 3);
 
@@ -549,6 +1393,237 @@ mod tests {
         assert_eq!(board.our_houses(), &[11, 12, 13]);
         assert_eq!(board.their_houses(), &[21, 22, 23]);
 
-        assert_eq!(board.to_kgp(), kpg);
+        assert_eq!(board.to_kgp(), kgp);
+
+        for malformed in [
+            "3, 2, 3, 11, 12, 13, 21, 22, 23",
+            "<3, 2, 3, 11, 12, 13, 21, 22>",
+            "<3, 2, 3, 11, 12, 13, 21, 22, 23, 24>",
+            "<3, 2, 3, 11, 12, x, 21, 22, 23>",
+            "<0, 2, 3>",
+            "<1, 0, 0, 40000, 40000>",
+        ] {
+            assert!(Board::try_from_kgp(malformed).is_err(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn test_move_from_str() {
+        use crate::Move;
+
+        assert_eq!("1".parse(), Ok(Move::new(0, Player::White)));
+        assert_eq!(" 12\n".parse(), Ok(Move::new(11, Player::White)));
+
+        for move_ in [Move::new(0, Player::White), Move::new(127, Player::White)] {
+            assert_eq!(move_.to_string().parse(), Ok(move_));
+        }
+
+        for invalid in ["", "0", "129", "-1", "a", "1 2"] {
+            assert!(invalid.parse::<Move>().is_err(), "{invalid:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_is_start_position() {
+        let mut board = Board::new(4, 3);
+        assert!(board.is_start_position());
+
+        board.apply_move(Move::new(3, Player::White));
+        assert!(!board.is_start_position());
+
+        assert!(!Board::from_kgp("<3, 0, 0, 3, 3, 3, 3, 3, 2>").is_start_position());
+    }
+
+    #[test]
+    fn test_winner() {
+        use crate::GameResult;
+
+        assert_eq!(Board::new(6, 4).winner(), None);
+
+        assert_eq!(
+            Board::from_kgp("<3, 10, 8, 0, 0, 0, 0, 0, 0>").winner(),
+            Some(GameResult::Win(Player::White))
+        );
+        assert_eq!(
+            Board::from_kgp("<3, 10, 10, 0, 0, 0, 0, 0, 0>").winner(),
+            Some(GameResult::Draw)
+        );
+
+        // game over since White has no seeds left, Black's remaining seeds count for Black
+        let mut board = Board::from_kgp("<3, 10, 8, 0, 0, 0, 1, 0, 2>");
+        assert_eq!(board.winner(), Some(GameResult::Win(Player::Black)));
+
+        // the result refers to the original orientation
+        board.flip_board();
+        assert_eq!(board.winner(), Some(GameResult::Win(Player::Black)));
+    }
+
+    #[test]
+    fn test_max_seeds() {
+        use rand::seq::SliceRandom;
+
+        // 10000 seeds per house don't fit into a store
+        assert!(Board::try_new(6, 10000).is_err());
+
+        // 65532 seeds: most moves go around the board several times, all the seeds end up in the stores
+        let total_seeds = 2 * 6 * 5461;
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..20 {
+            let mut board = Board::new(6, 5461);
+
+            while board.has_legal_move() {
+                let move_ = *board.legal_moves(Player::White).choose(&mut rng).unwrap();
+
+                if !board.apply_move(move_) {
+                    board.flip_board();
+                }
+
+                let (our_house_seeds, their_house_seeds) = board.house_seeds();
+                assert_eq!(
+                    board.our_store as u32
+                        + board.their_store as u32
+                        + our_house_seeds as u32
+                        + their_house_seeds as u32,
+                    total_seeds
+                );
+            }
+
+            assert_eq!(board.our_store as u32 + board.their_store as u32, total_seeds);
+        }
+    }
+
+    #[test]
+    fn test_is_decided() {
+        use crate::GameResult;
+
+        assert_eq!(Board::new(6, 4).is_decided(), None);
+
+        // 10 of 19 seeds in our store: decided even though both sides can still move
+        let mut board = Board::from_kgp("<3, 10, 3, 1, 1, 1, 1, 1, 1>");
+        assert!(board.has_legal_move());
+        assert_eq!(board.is_decided(), Some(GameResult::Win(Player::White)));
+
+        board.flip_board();
+        assert_eq!(board.is_decided(), Some(GameResult::Win(Player::White)));
+
+        // exactly half isn't enough
+        assert_eq!(Board::from_kgp("<3, 10, 4, 1, 1, 1, 1, 1, 1>").is_decided(), None);
+        assert_eq!(
+            Board::from_kgp("<3, 4, 11, 1, 1, 1, 1, 1, 1>").is_decided(),
+            Some(GameResult::Win(Player::Black))
+        );
+
+        // game over: same as winner
+        let board = Board::from_kgp("<3, 10, 8, 0, 0, 0, 1, 0, 2>");
+        assert_eq!(board.is_decided(), board.winner());
+    }
+
+    // all (board, starting house) pairs from random games whose hand stays within our houses
+    fn small_hands(num_games: usize, rng: &mut impl rand::Rng) -> Vec<(Board, usize)> {
+        use crate::kalah::Features;
+
+        let mut small_hands = Vec::new();
+
+        for game in 0..num_games {
+            let h = 4 + (game % 5) as u8;
+            let mut board = Board::new(h, 3 + (game % 4) as u16);
+            board.track_features(Features::ALL);
+
+            while board.has_legal_move() {
+                for (house, &seeds) in board.our_houses().iter().enumerate() {
+                    if seeds != 0 && (seeds as usize) < h as usize - house {
+                        small_hands.push((board.clone(), house));
+                    }
+                }
+
+                let move_ = *board.legal_moves(Player::White).choose(rng).unwrap();
+                if !board.apply_move(move_) {
+                    board.flip_board();
+                }
+            }
+        }
+
+        small_hands
+    }
+
+    #[test]
+    fn test_sow_within_our_houses() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1881);
+
+        for (board, house) in small_hands(50, &mut rng) {
+            let mut fast = board.clone();
+            let mut general = board.clone();
+
+            let seeds = board.our_houses()[house];
+            fast.our_houses_raw_mut()[house] = 0;
+            general.our_houses_raw_mut()[house] = 0;
+
+            fast.sow_within_our_houses(house, seeds, true);
+            general.sow(house, seeds, true);
+
+            assert_eq!(fast.to_kgp(), general.to_kgp(), "house {house} of\n{board}");
+            assert_eq!(fast.house_seeds(), general.house_seeds());
+            assert_eq!(fast.non_empty_houses(), general.non_empty_houses());
+        }
+    }
+
+    // cargo test --release bench_sow_within_our_houses -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_sow_within_our_houses() {
+        use std::time::Instant;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1881);
+        let small_hands = small_hands(2000, &mut rng);
+
+        let bench = |sow: fn(&mut Board, usize, House, bool)| {
+            let start = Instant::now();
+
+            for _ in 0..20 {
+                for (board, house) in &small_hands {
+                    let mut board = board.clone();
+                    let seeds = std::mem::take(&mut board.our_houses_raw_mut()[*house]);
+                    sow(&mut board, *house, seeds, true);
+                    std::hint::black_box(&board);
+                }
+            }
+
+            start.elapsed()
+        };
+
+        let general = bench(Board::sow);
+        let fast = bench(Board::sow_within_our_houses);
+
+        println!(
+            "{} moves: general {general:?}, fast path {fast:?} ({:.2}x)",
+            20 * small_hands.len(),
+            general.as_secs_f64() / fast.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_display_width() {
+        let board = Board::from_kgp("<3, 4, 20, 1, 2, 3, 4, 5, 6>");
+        assert_eq!(board.display_width(), 3);
+        assert_eq!(board.to_string(), " 20 |   6   5   4\n\n        1   2   3 |   4");
+
+        let board = Board::from_kgp("<3, 4, 20, 1, 1234, 3, 4, 5, 6>");
+        assert_eq!(board.display_width(), 4);
+        assert_eq!(
+            board.to_string(),
+            "  20 |    6    5    4\n\n          1 1234    3 |    4"
+        );
+
+        // every house ends in the same column as the one opposite of it
+        let board_str = board.to_string();
+        let lines: Vec<&str> = board_str.lines().filter(|line| !line.is_empty()).collect();
+        let ends = |line: &str| {
+            line.char_indices()
+                .filter(|&(idx, c)| c.is_ascii_digit() && !line[idx + 1..].starts_with(|c: char| c.is_ascii_digit()))
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ends(lines[0])[1..], ends(lines[1])[..3]);
     }
 }
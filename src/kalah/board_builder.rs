@@ -0,0 +1,174 @@
+use super::SweepRule;
+use crate::{Board, House};
+
+/// step by step construction of a specific board, e.g. for tests
+///
+/// ```ignore
+/// let board = BoardBuilder::new().our_houses([0, 1, 2]).their_houses([3, 0, 0]).our_store(4).build()?;
+/// ```
+///
+/// h defaults to the number of houses given, the stores to 0, flipped to false and the sweep rule to OwnSide. Unlike
+/// from_parts, build reports inconsistent parts as an error instead of panicking.
+#[derive(Debug, Clone, Default)]
+pub struct BoardBuilder {
+    h: Option<u8>,
+
+    our_houses: Vec<House>,
+    their_houses: Vec<House>,
+
+    our_store: House,
+    their_store: House,
+
+    flipped: bool,
+
+    sweep_rule: SweepRule,
+}
+
+impl BoardBuilder {
+    pub fn new() -> Self {
+        BoardBuilder::default()
+    }
+
+    pub fn h(mut self, h: u8) -> Self {
+        self.h = Some(h);
+        self
+    }
+
+    pub fn our_houses(mut self, houses: impl Into<Vec<House>>) -> Self {
+        self.our_houses = houses.into();
+        self
+    }
+
+    pub fn their_houses(mut self, houses: impl Into<Vec<House>>) -> Self {
+        self.their_houses = houses.into();
+        self
+    }
+
+    pub fn our_store(mut self, seeds: House) -> Self {
+        self.our_store = seeds;
+        self
+    }
+
+    pub fn their_store(mut self, seeds: House) -> Self {
+        self.their_store = seeds;
+        self
+    }
+
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    pub fn sweep_rule(mut self, sweep_rule: SweepRule) -> Self {
+        self.sweep_rule = sweep_rule;
+        self
+    }
+
+    pub fn build(self) -> Result<Board, String> {
+        let h = self.h.unwrap_or(self.our_houses.len().min(u8::MAX as usize) as u8);
+
+        if h == 0 || h > 128 {
+            return Err(format!("Invalid number of houses {h}, must be in 1..=128"));
+        }
+
+        if self.our_houses.len() != h as usize || self.their_houses.len() != h as usize {
+            return Err(format!(
+                "Expected {h} houses per side, got {} of ours and {} of theirs",
+                self.our_houses.len(),
+                self.their_houses.len()
+            ));
+        }
+
+        let total_seeds: u32 = self
+            .our_houses
+            .iter()
+            .chain(&self.their_houses)
+            .chain([&self.our_store, &self.their_store])
+            .map(|&seeds| seeds as u32)
+            .sum();
+
+        if total_seeds > House::MAX as u32 {
+            return Err(format!(
+                "{total_seeds} seeds exceed the store capacity of {}",
+                House::MAX
+            ));
+        }
+
+        let mut board = Board::from_parts(
+            h,
+            self.our_houses,
+            self.their_houses,
+            self.our_store,
+            self.their_store,
+            self.flipped,
+        );
+        board.set_sweep_rule(self.sweep_rule);
+
+        Ok(board)
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::BoardBuilder;
+    use crate::Board;
+
+    #[test]
+    fn test_board_builder() {
+        let board = BoardBuilder::new()
+            .our_houses([0, 1, 2])
+            .their_houses([3, 0, 0])
+            .our_store(4)
+            .their_store(5)
+            .build()
+            .unwrap();
+        assert_eq!(board.to_kgp(), "<3, 4, 5, 0, 1, 2, 3, 0, 0>");
+
+        let flipped = BoardBuilder::new()
+            .h(2)
+            .our_houses([1, 1])
+            .their_houses(vec![2, 0])
+            .flipped(true)
+            .build()
+            .unwrap();
+        assert!(flipped.flipped());
+        assert_eq!(flipped.our_houses(), [1, 1]);
+
+        let mut start = BoardBuilder::new()
+            .our_houses([4; 6])
+            .their_houses([4; 6])
+            .build()
+            .unwrap();
+        assert!(start.is_start_position());
+        start.clone_from(&Board::new(6, 4));
+        assert_eq!(start.to_kgp(), Board::new(6, 4).to_kgp());
+    }
+
+    #[test]
+    fn test_board_builder_errors() {
+        // mismatched number of houses
+        assert!(BoardBuilder::new()
+            .our_houses([1, 2, 3])
+            .their_houses([1, 2])
+            .build()
+            .is_err());
+        assert!(BoardBuilder::new()
+            .h(4)
+            .our_houses([1, 2, 3])
+            .their_houses([1, 2, 3])
+            .build()
+            .is_err());
+
+        // no houses at all
+        assert!(BoardBuilder::new().build().is_err());
+
+        // more seeds than fit into a store
+        assert!(BoardBuilder::new()
+            .our_houses([40000])
+            .their_houses([30000])
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use super::Board;
+
+/// preallocated boards a search worker reuses for its board_after_move instead of cloning one at every node
+///
+/// a node takes a board when it starts and gives it back before it returns, so the pool works like a stack holding a
+/// board for each node on the current line. All boards have to come from the same game since clone_from only works
+/// between boards with the same h. Lines longer than the pool (bonus moves don't count towards the depth) clone the
+/// missing boards once, after that they are part of the pool as well.
+#[derive(Default)]
+pub struct BoardPool {
+    boards: Vec<Board>,
+}
+
+impl BoardPool {
+    pub fn new() -> Self {
+        BoardPool { boards: Vec::new() }
+    }
+
+    /// number of boards that are currently in the pool
+    pub fn len(&self) -> usize {
+        self.boards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boards.is_empty()
+    }
+
+    /// fill the pool with clones of root until it holds at least size boards, called at the start of a search depth
+    pub fn reserve(&mut self, root: &Board, size: usize) {
+        while self.boards.len() < size {
+            self.boards.push(root.clone());
+        }
+    }
+
+    /// a copy of board, only allocating if the pool ran dry
+    pub fn take(&mut self, board: &Board) -> Board {
+        match self.boards.pop() {
+            Some(mut scratch) => {
+                scratch.clone_from(board);
+                scratch
+            }
+            None => board.clone(),
+        }
+    }
+
+    pub fn give_back(&mut self, board: Board) {
+        self.boards.push(board);
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::BoardPool;
+    use crate::{Board, Move, Player};
+
+    #[test]
+    fn test_board_pool() {
+        let root = Board::new(6, 4);
+
+        let mut pool = BoardPool::new();
+        pool.reserve(&root, 2);
+        assert_eq!(pool.len(), 2);
+
+        // reserving again only tops up
+        pool.reserve(&root, 1);
+        assert_eq!(pool.len(), 2);
+
+        let mut board = root.clone();
+        board.apply_move(Move::new(2, Player::White));
+
+        let scratch = pool.take(&board);
+        assert_eq!(scratch.to_kgp(), board.to_kgp());
+
+        // running dry clones, giving back grows the pool
+        let boards: Vec<Board> = (0..3).map(|_| pool.take(&root)).collect();
+        assert!(pool.is_empty());
+        assert!(boards.iter().all(|scratch| scratch.to_kgp() == root.to_kgp()));
+
+        for scratch in boards.into_iter().chain([scratch]) {
+            pool.give_back(scratch);
+        }
+        assert_eq!(pool.len(), 4);
+    }
+
+    // cargo test --release bench_board_pool -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_board_pool() {
+        use std::time::Instant;
+
+        let board = Board::new(8, 8);
+        let depth = 12;
+        let iterations = 1_000_000;
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            let line: Vec<Board> = (0..depth).map(|_| board.clone()).collect();
+            std::hint::black_box(&line);
+        }
+        let cloned = start.elapsed();
+
+        let mut pool = BoardPool::new();
+        pool.reserve(&board, depth);
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            let line: Vec<Board> = (0..depth).map(|_| pool.take(&board)).collect();
+            std::hint::black_box(&line);
+            line.into_iter().for_each(|scratch| pool.give_back(scratch));
+        }
+        let pooled = start.elapsed();
+
+        println!(
+            "{} boards: clone {cloned:?}, pool {pooled:?} ({:.2}x)",
+            depth * iterations,
+            cloned.as_secs_f64() / pooled.as_secs_f64()
+        );
+    }
+}
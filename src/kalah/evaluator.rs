@@ -0,0 +1,164 @@
+use crate::kalah::Valuation;
+use crate::Board;
+
+/// whose point of view an Evaluator values positions from, see Evaluator::perspective
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Perspective {
+    /// positive is good for our side of the board, which the searches always make the side to move by flipping the
+    /// board whenever the turn changes, so a heuristic never has to know which color it is playing
+    SideToMove,
+
+    /// positive is good for White of the game, i.e. for our side on boards that aren't flipped and for their side on
+    /// boards that are
+    White,
+}
+
+/// anything that can value positions like the valuation functions do, i.e. from the perspective of the side to move
+///
+/// every ValuationFn (and closure of the same signature) is an Evaluator, evaluators with state, e.g. weights or a
+/// network, implement it themselves. Evaluators that find it more natural to value for White can say so with
+/// perspective, the searches only use evaluate_side_to_move and get the same results either way.
+pub trait Evaluator {
+    fn evaluate(&self, board: &Board) -> Valuation;
+
+    /// convention of the values evaluate returns, all valuation functions value for the side to move
+    fn perspective(&self) -> Perspective {
+        Perspective::SideToMove
+    }
+
+    /// value that evaluate (or evaluate_batch) returned for board, from the perspective of the side to move
+    fn side_to_move_value(&self, board: &Board, value: Valuation) -> Valuation {
+        match self.perspective() {
+            Perspective::White if board.flipped() => -value,
+            _ => value,
+        }
+    }
+
+    /// evaluate, converted to the perspective of the side to move if the evaluator uses a different one
+    fn evaluate_side_to_move(&self, board: &Board) -> Valuation {
+        self.side_to_move_value(board, self.evaluate(board))
+    }
+
+    /// value all boards at once, which backends with a high cost per call (e.g. a neural network) can override
+    ///
+    /// the contract for overrides:
+    /// - the result has one valuation per board, in the same order, equal to what evaluate would return for it
+    /// - no side effects: evaluating a batch has to be indistinguishable from evaluating the boards one at a time
+    fn evaluate_batch(&self, boards: &[Board]) -> Vec<Valuation> {
+        boards.iter().map(|board| self.evaluate(board)).collect()
+    }
+}
+
+impl<F: Fn(&Board) -> Valuation> Evaluator for F {
+    fn evaluate(&self, board: &Board) -> Valuation {
+        self(board)
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::{Evaluator, Perspective};
+    use crate::kalah::valuation::{seed_diff_valuation, store_diff_valuation, Valuation};
+    use crate::{pvs, Board, Move, Player};
+
+    /// counts its calls, to tell batches from single evaluations
+    struct BatchingEvaluator {
+        calls: Cell<usize>,
+    }
+
+    impl Evaluator for BatchingEvaluator {
+        fn evaluate(&self, board: &Board) -> Valuation {
+            self.evaluate_batch(std::slice::from_ref(board))[0]
+        }
+
+        fn evaluate_batch(&self, boards: &[Board]) -> Vec<Valuation> {
+            self.calls.set(self.calls.get() + 1);
+
+            boards.iter().map(seed_diff_valuation).collect()
+        }
+    }
+
+    #[test]
+    fn test_evaluate_batch() {
+        let board = Board::new(6, 4);
+        let boards: Vec<Board> = board
+            .legal_moves(Player::White)
+            .into_iter()
+            .chain([Move::new(0, Player::Black)])
+            .map(|move_| {
+                let mut board = board.clone();
+                board.apply_move(move_);
+                board
+            })
+            .collect();
+
+        // default: same as evaluating one at a time, in order
+        let singles: Vec<Valuation> = boards.iter().map(store_diff_valuation).collect();
+        assert_eq!(store_diff_valuation.evaluate_batch(&boards), singles);
+        assert!(store_diff_valuation.evaluate_batch(&[]).is_empty());
+
+        let evaluator = BatchingEvaluator { calls: Cell::new(0) };
+        let batch = evaluator.evaluate_batch(&boards);
+        assert_eq!(evaluator.calls.get(), 1);
+
+        let singles: Vec<Valuation> = boards.iter().map(|board| evaluator.evaluate(board)).collect();
+        assert_eq!(batch, singles);
+    }
+
+    /// store_diff_valuation from the perspective of White, like a heuristic written in terms of colors would value
+    struct WhiteStoreDiff;
+
+    impl Evaluator for WhiteStoreDiff {
+        fn evaluate(&self, board: &Board) -> Valuation {
+            let value = store_diff_valuation(board);
+
+            if board.flipped() {
+                -value
+            } else {
+                value
+            }
+        }
+
+        fn perspective(&self) -> Perspective {
+            Perspective::White
+        }
+    }
+
+    #[test]
+    fn test_perspective() {
+        use rand::seq::SliceRandom;
+
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..10 {
+            let mut board = Board::new(4, 3);
+
+            while board.has_legal_move() {
+                assert_eq!(
+                    WhiteStoreDiff.evaluate_side_to_move(&board),
+                    store_diff_valuation(&board)
+                );
+
+                // searches flip the board every time the turn changes, so they see White and Black to move alike
+                for depth in 1..=3 {
+                    let (value, pv) = pvs::search_line(&board, depth, WhiteStoreDiff);
+                    assert_eq!(
+                        (value, pv),
+                        pvs::search_line(&board, depth, store_diff_valuation),
+                        "{board}"
+                    );
+                }
+
+                let move_ = *board.legal_moves(Player::White).choose(&mut rng).unwrap();
+
+                if !board.apply_move(move_) {
+                    board.flip_board();
+                }
+            }
+        }
+    }
+}
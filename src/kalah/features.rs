@@ -0,0 +1,112 @@
+use std::ops::BitOr;
+
+use super::zobrist::houses_hash;
+use crate::{House, Player};
+
+/// set of board features that a Board can keep up to date incrementally in apply_move, see Board::track_features
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Features(u8);
+
+impl Features {
+    pub const NONE: Features = Features(0);
+
+    /// sum of the seeds in our and their houses, see Board::house_seeds
+    pub const HOUSE_SEEDS: Features = Features(1 << 0);
+
+    /// number of our and their non-empty houses (i.e. mobility), see Board::non_empty_houses
+    pub const NON_EMPTY_HOUSES: Features = Features(1 << 1);
+
+    /// bitmasks of our and their non-empty houses, see Board::legal_mask and Board::has_legal_move
+    pub const LEGAL_MASK: Features = Features(1 << 2);
+
+    /// Zobrist hash of the houses, see Board::zobrist_hash
+    pub const ZOBRIST: Features = Features(1 << 3);
+
+    pub const ALL: Features =
+        Features(Features::HOUSE_SEEDS.0 | Features::NON_EMPTY_HOUSES.0 | Features::LEGAL_MASK.0 | Features::ZOBRIST.0);
+
+    pub fn contains(self, other: Features) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Features {
+    type Output = Features;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Features(self.0 | rhs.0)
+    }
+}
+
+/*====================================================================================================================*/
+
+/// incrementally updated feature values of a board, only meaningful for the tracked features and if not stale
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Accumulator {
+    pub tracked: Features,
+
+    /// houses were modified through a *_mut accessor, so the values can't be trusted until they get recomputed
+    pub stale: bool,
+
+    pub our_house_seeds: House,
+    pub their_house_seeds: House,
+
+    pub our_non_empty: u8,
+    pub their_non_empty: u8,
+
+    pub our_legal_mask: u128,
+    pub their_legal_mask: u128,
+
+    /// hash of the houses in the board's original orientation, so flipping doesn't change it
+    pub house_hash: u64,
+}
+
+impl Accumulator {
+    pub fn is_valid(&self, features: Features) -> bool {
+        !self.stale && self.tracked.contains(features)
+    }
+
+    /// recompute all values, our houses being the houses of our_player in the board's original orientation
+    pub fn recompute(&mut self, our_houses: &[House], their_houses: &[House], our_player: Player) {
+        self.our_house_seeds = our_houses.iter().sum();
+        self.their_house_seeds = their_houses.iter().sum();
+
+        self.our_non_empty = our_houses.iter().filter(|&&house| house != 0).count() as u8;
+        self.their_non_empty = their_houses.iter().filter(|&&house| house != 0).count() as u8;
+
+        self.our_legal_mask = non_empty_mask(our_houses);
+        self.their_legal_mask = non_empty_mask(their_houses);
+
+        self.house_hash = houses_hash(our_player, our_houses, their_houses);
+
+        self.stale = false;
+    }
+
+    pub fn flip(&mut self) {
+        std::mem::swap(&mut self.our_house_seeds, &mut self.their_house_seeds);
+        std::mem::swap(&mut self.our_non_empty, &mut self.their_non_empty);
+        std::mem::swap(&mut self.our_legal_mask, &mut self.their_legal_mask);
+    }
+}
+
+/// bit i is set iff houses[i] is not empty
+pub(super) fn non_empty_mask(houses: &[House]) -> u128 {
+    houses
+        .iter()
+        .enumerate()
+        .filter(|&(_house_num, &house)| house != 0)
+        .fold(0, |mask, (house_num, _house)| mask | (1 << house_num))
+}
+
+/// bits start..start + len set, for the houses a move sowed into
+pub(super) fn range_mask(start: usize, len: usize) -> u128 {
+    if len == 0 {
+        0
+    } else {
+        (u128::MAX >> (128 - len)) << start
+    }
+}
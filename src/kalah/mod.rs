@@ -1,5 +1,28 @@
 mod board;
+mod board_builder;
+mod board_pool;
+mod evaluator;
+mod features;
+mod move_outcome;
+mod phase_evaluator;
+mod repetition;
+mod sweep_rule;
 pub mod valuation;
+mod weights;
+mod zobrist;
 
-pub use board::{Board, House, Move, Player};
+#[cfg(test)]
+mod rules_tests;
+
+pub use board::{Board, GameResult, House, Move, Player};
+pub use board_builder::BoardBuilder;
+pub use board_pool::BoardPool;
+pub use evaluator::{Evaluator, Perspective};
+pub use features::Features;
+pub use move_outcome::{HouseRef, MoveDescription, MoveOutcome, MovePreview};
+pub use phase_evaluator::PhaseEvaluator;
+pub use repetition::{repetition_value, PathHistory};
+pub use sweep_rule::SweepRule;
 pub use valuation::{Valuation, ValuationFn};
+pub use weights::Weights;
+pub use zobrist::ZOBRIST_SEED;
@@ -0,0 +1,392 @@
+use std::cmp::Reverse;
+use std::fmt::Display;
+
+use crate::{Board, House, Move, Player};
+
+/// a house or store, seen from the player making a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HouseRef {
+    Ours(u8),
+    OurStore,
+    Theirs(u8),
+}
+
+impl Display for HouseRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // houses are 1-indexed like moves
+        match self {
+            HouseRef::Ours(house) => write!(f, "own house {}", house + 1),
+            HouseRef::OurStore => write!(f, "own store"),
+            HouseRef::Theirs(house) => write!(f, "opponent's house {}", house + 1),
+        }
+    }
+}
+
+/// what happened during a move, from the perspective of the player making it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveOutcome {
+    /// the player gets to move again
+    pub bonus: bool,
+
+    /// where the last seed was sown
+    pub landing: HouseRef,
+
+    /// opponent's house the seeds were captured from and the number of seeds in it
+    pub capture: Option<(u8, House)>,
+}
+
+/// annotation of a single ply for transcripts, see Board::describe_move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveDescription {
+    pub player: Player,
+
+    /// house the seeds were taken from
+    pub source: u8,
+    pub seeds: House,
+
+    pub outcome: MoveOutcome,
+}
+
+impl Display for MoveDescription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: house {} ({} seeds), last seed in {}",
+            self.player,
+            self.source + 1,
+            self.seeds,
+            self.outcome.landing
+        )?;
+
+        if let Some((house, seeds)) = self.outcome.capture {
+            write!(f, ", captures {seeds} seeds from opponent's house {}", house + 1)?;
+        }
+
+        if self.outcome.bonus {
+            write!(f, ", moves again")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// whether a move earns a bonus move and what it captures, see Board::move_preview
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovePreview {
+    pub bonus: bool,
+
+    /// opponent's house the seeds would be captured from and the number of seeds in it
+    pub capture: Option<(u8, House)>,
+}
+
+/// whether the last seed of a move captures: it landed in one of our houses, which now holds landing_seeds (so it was
+/// empty before iff that's 1), with opposite_seeds in their house opposite of it
+///
+/// a landing in an empty house with an empty house opposite doesn't capture anything, the seed just stays. Shared by
+/// Board::would_capture (on the board after sowing) and move_preview (which works out the seeds without sowing).
+pub(super) fn capture_rule(landing_seeds: usize, opposite_seeds: usize) -> bool {
+    landing_seeds == 1 && opposite_seeds > 0
+}
+
+impl Board {
+    /// describe what move_ would do on this board, without changing it
+    pub fn describe_move(&self, move_: Move) -> MoveDescription {
+        let seeds = match move_.player() {
+            Player::White => self.our_houses()[move_.house() as usize],
+            Player::Black => self.their_houses()[move_.house() as usize],
+        };
+
+        let outcome = self.clone().apply_move_outcome(move_);
+
+        MoveDescription {
+            player: move_.player(),
+            source: move_.house(),
+            seeds,
+            outcome,
+        }
+    }
+
+    /// bonus and capture of move_ like in apply_move's MoveOutcome, computed from the seed counts without cloning
+    pub fn move_preview(&self, move_: Move) -> MovePreview {
+        let (our_houses, their_houses) = match move_.player() {
+            Player::White => (self.our_houses(), self.their_houses()),
+            Player::Black => (self.their_houses(), self.our_houses()),
+        };
+
+        let h = self.h() as usize;
+        let cycle_length = 2 * h + 1;
+
+        // same indexing as in apply_move: our houses, our store, their houses
+        let start_house = move_.house() as usize;
+        let seeds_in_hand = our_houses[start_house] as usize;
+
+        assert!(seeds_in_hand != 0, "Trying to move out of empty house");
+
+        let num_cycles = seeds_in_hand / cycle_length;
+        let rem = seeds_in_hand % cycle_length;
+
+        // seeds that house idx gets: one per complete cycle plus one if it's among the rem houses after the start
+        let sown = |idx: usize| num_cycles + ((idx + cycle_length - start_house - 1) % cycle_length < rem) as usize;
+
+        let last_house_idx = (start_house + seeds_in_hand) % cycle_length;
+
+        let mut capture = None;
+
+        if last_house_idx < h {
+            // the starting house got emptied before sowing
+            let before = if last_house_idx == start_house {
+                0
+            } else {
+                our_houses[last_house_idx] as usize
+            };
+
+            let opposite = h - last_house_idx - 1;
+            let opposite_seeds = their_houses[opposite] as usize + sown(h + 1 + opposite);
+
+            if capture_rule(before + sown(last_house_idx), opposite_seeds) {
+                capture = Some((opposite as u8, opposite_seeds as House));
+            }
+        }
+
+        MovePreview {
+            bonus: last_house_idx == h,
+            capture,
+        }
+    }
+
+    /// legal_moves of player, with the moves that earn a bonus move first, then captures from the most seeds taken to
+    /// the fewest, then the rest, each by house
+    ///
+    /// a static ordering from move_preview, i.e. it doesn't learn anything from the search. Cheap enough for the
+    /// simpler searches to get better cutoffs than in house order without killer moves or history tables.
+    pub fn legal_moves_ordered(&self, player: Player) -> Vec<Move> {
+        let mut moves = self.legal_moves(player);
+
+        // stable sort, so ties stay in house order
+        moves.sort_by_key(|&move_| {
+            let preview = self.move_preview(move_);
+            (
+                !preview.bonus,
+                Reverse(preview.capture.map_or(0, |(_house, seeds)| seeds)),
+            )
+        });
+
+        moves
+    }
+
+    /// apply move_ like apply_move and also list every house (and our store) that seeds were sown into, in the order
+    /// the first seed reached them, with the number of seeds each got, from the perspective of the mover
+    ///
+    /// meant for animating a move. Hands that go around the board several times aren't sown one seed at a time, so
+    /// every house shows up once with all the seeds it got. The seeds always add up to the ones taken out of the
+    /// house, what the capture moves is in the MoveOutcome, and seeds swept into the stores at the end of the game
+    /// aren't part of the trace.
+    pub fn apply_move_traced(&mut self, move_: Move) -> (MoveOutcome, Vec<(HouseRef, House)>) {
+        let seeds_in_hand = match move_.player() {
+            Player::White => self.our_houses()[move_.house() as usize],
+            Player::Black => self.their_houses()[move_.house() as usize],
+        } as usize;
+
+        let h = self.h() as usize;
+        let cycle_length = 2 * h + 1;
+        let start_house = move_.house() as usize;
+
+        // same indexing as in apply_move: our houses, our store, their houses
+        let house_ref = |idx: usize| match idx {
+            idx if idx < h => HouseRef::Ours(idx as u8),
+            idx if idx == h => HouseRef::OurStore,
+            idx => HouseRef::Theirs((idx - h - 1) as u8),
+        };
+
+        let num_cycles = seeds_in_hand / cycle_length;
+        let rem = seeds_in_hand % cycle_length;
+
+        // the k-th house after the start gets one seed per complete cycle and one more if it's among the first rem
+        let trace: Vec<(HouseRef, House)> = (1..=seeds_in_hand.min(cycle_length))
+            .map(|k| {
+                let seeds = num_cycles + (k <= rem) as usize;
+                (house_ref((start_house + k) % cycle_length), seeds as House)
+            })
+            .collect();
+
+        debug_assert_eq!(
+            trace.iter().map(|&(_, seeds)| seeds as usize).sum::<usize>(),
+            seeds_in_hand,
+            "Trace of {move_} loses seeds"
+        );
+
+        (self.apply_move_outcome(move_), trace)
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::{HouseRef, MoveOutcome};
+    use crate::{Board, House, Move, Player};
+
+    #[test]
+    fn test_move_preview() {
+        use rand::seq::SliceRandom;
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..200 {
+            let h = rng.gen_range(1..=8);
+
+            // random seeds, including hands that go around the board several times
+            let mut random_houses = || -> Vec<House> {
+                (0..h)
+                    .map(|_| *[0, 1, 2, 5, 13, 40].choose(&mut rng).unwrap())
+                    .collect()
+            };
+            let (our_houses, their_houses) = (random_houses(), random_houses());
+            let board = Board::from_parts(h, our_houses, their_houses, 0, 0, false);
+
+            for player in [Player::White, Player::Black] {
+                for move_ in board.legal_moves(player) {
+                    let outcome = board.clone().apply_move_outcome(move_);
+                    let preview = board.move_preview(move_);
+
+                    assert_eq!(preview.bonus, outcome.bonus, "{move_:?} on {}", board.to_kgp());
+                    assert_eq!(preview.capture, outcome.capture, "{move_:?} on {}", board.to_kgp());
+                }
+            }
+        }
+
+        // capture from the house opposite of our house 1 and a bonus move
+        let board = Board::from_kgp("<3, 0, 0, 1, 0, 1, 1, 5, 1>");
+        assert_eq!(board.move_preview(Move::new(0, Player::White)).capture, Some((1, 5)));
+        assert!(board.move_preview(Move::new(2, Player::White)).bonus);
+    }
+
+    #[test]
+    fn test_legal_moves_ordered() {
+        // houses 6 and 7 are bonus moves, 3 and 1 capture 6 and 2 seeds, 5 does neither
+        let board = Board::from_kgp("<7, 0, 0, 1, 0, 1, 0, 4, 2, 1, 1, 0, 0, 6, 0, 2, 0>");
+
+        let houses = |moves: Vec<Move>| -> Vec<u8> { moves.into_iter().map(|move_| move_.house()).collect() };
+
+        assert_eq!(houses(board.legal_moves(Player::White)), [0, 2, 4, 5, 6]);
+        assert_eq!(houses(board.legal_moves_ordered(Player::White)), [5, 6, 2, 0, 4]);
+
+        // the same for the opponent on the flipped board
+        let mut flipped = board.clone();
+        flipped.flip_board();
+
+        let ordered = flipped.legal_moves_ordered(Player::Black);
+        assert!(ordered.iter().all(|move_| move_.player() == Player::Black));
+        assert_eq!(houses(ordered), [5, 6, 2, 0, 4]);
+    }
+
+    #[test]
+    fn test_apply_move_traced() {
+        use rand::seq::SliceRandom;
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..200 {
+            let h = rng.gen_range(1..=8);
+
+            // random seeds, including hands that go around the board several times
+            let mut random_houses = || -> Vec<House> {
+                (0..h)
+                    .map(|_| *[0, 1, 2, 5, 13, 40].choose(&mut rng).unwrap())
+                    .collect()
+            };
+            let (our_houses, their_houses) = (random_houses(), random_houses());
+            let board = Board::from_parts(h, our_houses, their_houses, 0, 0, false);
+
+            let h = h as usize;
+
+            for player in [Player::White, Player::Black] {
+                for move_ in board.legal_moves(player) {
+                    let mut traced = board.clone();
+                    let (outcome, trace) = traced.apply_move_traced(move_);
+
+                    let mut applied = board.clone();
+                    assert_eq!(outcome, applied.apply_move_outcome(move_));
+                    assert_eq!(traced.to_kgp(), applied.to_kgp());
+
+                    // sow one seed at a time, in the mover's indexing: our houses, our store, their houses
+                    let seeds_in_hand = match player {
+                        Player::White => board.our_houses()[move_.house() as usize],
+                        Player::Black => board.their_houses()[move_.house() as usize],
+                    };
+
+                    let mut expected: Vec<(HouseRef, House)> = Vec::new();
+                    let mut idx = move_.house() as usize;
+
+                    for _ in 0..seeds_in_hand {
+                        idx = (idx + 1) % (2 * h + 1);
+
+                        let house = match idx {
+                            idx if idx < h => HouseRef::Ours(idx as u8),
+                            idx if idx == h => HouseRef::OurStore,
+                            idx => HouseRef::Theirs((idx - h - 1) as u8),
+                        };
+
+                        match expected.iter_mut().find(|(sown_into, _)| *sown_into == house) {
+                            Some((_, seeds)) => *seeds += 1,
+                            None => expected.push((house, 1)),
+                        }
+                    }
+
+                    assert_eq!(trace, expected, "{move_:?} on {}", board.to_kgp());
+                }
+            }
+        }
+
+        // 2 seeds from our house 2: our house 3, then the store
+        let mut board = Board::from_kgp("<3, 0, 0, 1, 2, 0, 1, 1, 1>");
+        let (outcome, trace) = board.apply_move_traced(Move::new(1, Player::White));
+        assert!(outcome.bonus);
+        assert_eq!(trace, vec![(HouseRef::Ours(2), 1), (HouseRef::OurStore, 1)]);
+    }
+
+    #[test]
+    fn test_describe_move() {
+        let board = Board::from_kgp("<3, 0, 0, 1, 0, 1, 1, 5, 1>");
+
+        // capture from the house opposite of our house 1
+        let description = board.describe_move(Move::new(0, Player::White));
+        assert_eq!(
+            description.outcome,
+            MoveOutcome {
+                bonus: false,
+                landing: HouseRef::Ours(1),
+                capture: Some((1, 5)),
+            }
+        );
+        assert_eq!(
+            description.to_string(),
+            "White: house 1 (1 seeds), last seed in own house 2, captures 5 seeds from opponent's house 2"
+        );
+
+        // bonus move
+        let description = board.describe_move(Move::new(2, Player::White));
+        assert_eq!(description.outcome.landing, HouseRef::OurStore);
+        assert!(description.outcome.bonus);
+        assert_eq!(
+            description.to_string(),
+            "White: house 3 (1 seeds), last seed in own store, moves again"
+        );
+
+        // Black's perspective: house 2 of Black has 5 seeds, reaching its store and 1 more
+        let description = board.describe_move(Move::new(1, Player::Black));
+        assert_eq!(
+            description.outcome,
+            MoveOutcome {
+                bonus: false,
+                landing: HouseRef::Theirs(2),
+                capture: None,
+            }
+        );
+
+        // describing doesn't change the board
+        assert_eq!(board.to_kgp(), "<3, 0, 0, 1, 0, 1, 1, 5, 1>");
+    }
+}
@@ -0,0 +1,120 @@
+use crate::kalah::valuation::{decided_valuation, Valuation};
+use crate::kalah::Evaluator;
+use crate::Board;
+
+/// valuation that shifts from the seed difference to the store difference as the houses empty
+///
+/// early on, seeds in the houses are still worth about as much as seeds in the store, late in the game only the stores
+/// count. The share of all seeds that is still in the houses tells the phases apart: at or above opening the value is
+/// the one of seed_diff_valuation, at or below endgame the one of store_diff_valuation, in between it blends linearly
+/// from one to the other. Decided positions are terminal like with all valuations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseEvaluator {
+    /// share of the seeds in the houses (0.0 to 1.0) from which on the position counts as opening
+    pub opening: f64,
+
+    /// share of the seeds in the houses up to which the position counts as endgame, at most opening
+    pub endgame: f64,
+}
+
+impl PhaseEvaluator {
+    /// default thresholds: opening until a quarter of the seeds is in the stores, endgame once three quarters are
+    pub fn new() -> Self {
+        PhaseEvaluator {
+            opening: 0.75,
+            endgame: 0.25,
+        }
+    }
+
+    /// weight of the seed difference for a board with house_share of its seeds in the houses, the store difference
+    /// gets the rest
+    fn seed_weight(&self, house_share: f64) -> f64 {
+        debug_assert!(self.endgame <= self.opening, "PhaseEvaluator endgame is above opening");
+
+        // endgame first, so opening and endgame at 1.0 mean store difference everywhere (and both at 0.0 seed difference,
+        // which is the store difference once the houses are empty)
+        if house_share <= self.endgame {
+            0.0
+        } else if house_share >= self.opening {
+            1.0
+        } else {
+            (house_share - self.endgame) / (self.opening - self.endgame)
+        }
+    }
+}
+
+impl Default for PhaseEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evaluator for PhaseEvaluator {
+    fn evaluate(&self, board: &Board) -> Valuation {
+        if let Some(valuation) = decided_valuation(board) {
+            return valuation;
+        }
+
+        let (our_house_seeds, their_house_seeds) = board.house_seeds();
+        let house_seeds = our_house_seeds as u32 + their_house_seeds as u32;
+
+        let store_diff = board.our_store as i64 - board.their_store as i64;
+        let seed_diff = store_diff + our_house_seeds as i64 - their_house_seeds as i64;
+
+        let seed_weight = self.seed_weight(house_seeds as f64 / board.total_seeds() as f64);
+        let value = seed_weight * seed_diff as f64 + (1.0 - seed_weight) * store_diff as f64;
+
+        Valuation::non_terminal(value.round() as i64)
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::PhaseEvaluator;
+    use crate::kalah::valuation::{seed_diff_valuation, store_diff_valuation, Valuation};
+    use crate::kalah::Evaluator;
+    use crate::Board;
+
+    #[test]
+    fn test_phase_evaluator() {
+        let evaluator = PhaseEvaluator::new();
+
+        // 12 of 14 seeds in the houses: opening, pure seed difference
+        let opening = Board::from_kgp("<3, 1, 1, 5, 0, 2, 1, 2, 2>");
+        assert_eq!(evaluator.evaluate(&opening), seed_diff_valuation(&opening));
+        assert_ne!(evaluator.evaluate(&opening), store_diff_valuation(&opening));
+
+        // 3 of 14 seeds in the houses: endgame, pure store difference
+        let endgame = Board::from_kgp("<3, 6, 5, 0, 0, 1, 1, 1, 0>");
+        assert_eq!(evaluator.evaluate(&endgame), store_diff_valuation(&endgame));
+        assert_ne!(evaluator.evaluate(&endgame), seed_diff_valuation(&endgame));
+
+        // 7 of 14 seeds in the houses: halfway between seed difference 4 and store difference -1, rounded away from 0
+        let middle = Board::from_kgp("<3, 3, 4, 2, 2, 2, 1, 0, 0>");
+        assert_eq!(evaluator.evaluate(&middle), Valuation::NonTerminal { value: 2 });
+
+        let mut flipped = middle.clone();
+        flipped.flip_board();
+        assert_eq!(evaluator.evaluate(&flipped), -evaluator.evaluate(&middle));
+
+        // thresholds at the extremes make it one of the components everywhere
+        let only_seeds = PhaseEvaluator {
+            opening: 0.0,
+            endgame: 0.0,
+        };
+        let only_stores = PhaseEvaluator {
+            opening: 1.0,
+            endgame: 1.0,
+        };
+        for board in [&opening, &endgame, &middle] {
+            assert_eq!(only_seeds.evaluate(board), seed_diff_valuation(board));
+            assert_eq!(only_stores.evaluate(board), store_diff_valuation(board));
+        }
+
+        // decided positions are terminal like with all valuations
+        let decided = Board::from_kgp("<3, 8, 1, 2, 0, 1, 0, 1, 1>");
+        assert_eq!(evaluator.evaluate(&decided), Valuation::TerminalWhiteWin { plies: 0 });
+    }
+}
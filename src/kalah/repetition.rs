@@ -0,0 +1,117 @@
+use crate::kalah::Valuation;
+
+/// positions on the path from the search root to the current node, to notice when one repeats
+///
+/// Kalah positions can't actually repeat: every move either puts a seed into the mover's store, or only moves seeds
+/// closer to it without any seed reaching the opponent's side, so the game keeps moving in one direction (see also
+/// the tablebase). This guards searches against variants or bugs that break that, positions are identified by a key
+/// such as a hash of the board.
+#[derive(Debug, Default, Clone)]
+pub struct PathHistory {
+    keys: Vec<u64>,
+}
+
+impl PathHistory {
+    pub fn new() -> Self {
+        PathHistory::default()
+    }
+
+    /// enter the position with key, returns true if it already occurred on the current path
+    ///
+    /// the position has to be left with pop either way
+    pub fn push(&mut self, key: u64) -> bool {
+        let repeated = self.keys.contains(&key);
+        self.keys.push(key);
+        repeated
+    }
+
+    pub fn pop(&mut self) {
+        self.keys.pop().expect("Left more positions than were entered");
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+/// value of a repeated position for the side to move
+///
+/// A repetition isn't the end of the game, so unlike TerminalDraw this is a NonTerminal value: it ranks like an even
+/// position, which the side that is worse off gladly repeats into and the side that is better off avoids, and it's
+/// never mistaken for a proven result. Contempt is how much the side to move dislikes the repetition, i.e. by how much
+/// it thinks it's better than its opponent. With a positive contempt the side to move avoids repeating even from
+/// slightly worse positions, and the opponent is happy to repeat for the same reason, since the value is negated on
+/// the way up.
+pub fn repetition_value(contempt: i32) -> Valuation {
+    Valuation::NonTerminal { value: -contempt }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::{repetition_value, PathHistory};
+    use crate::kalah::Valuation;
+
+    // tiny game that can go in circles, which Kalah can't: nodes with their successors, leaves with their value for
+    // the side to move there
+    enum Node {
+        Inner(&'static [usize]),
+        Leaf(Valuation),
+    }
+
+    fn negamax(nodes: &[Node], node: usize, history: &mut PathHistory, contempt: i32) -> Valuation {
+        if history.push(node as u64) {
+            history.pop();
+            return repetition_value(contempt);
+        }
+
+        let value = match nodes[node] {
+            Node::Leaf(value) => value,
+            Node::Inner(children) => children
+                .iter()
+                .map(|&child| -negamax(nodes, child, history, contempt))
+                .max()
+                .unwrap(),
+        };
+
+        history.pop();
+        value
+    }
+
+    #[test]
+    fn test_repetition_draw() {
+        use Valuation::{NonTerminal, TerminalBlackWin, TerminalDraw};
+
+        // 0: side to move (losing) can go to 1 or resign into a loss
+        // 1: opponent can go back to 0 or end the game in a draw (bad for the opponent, which is winning)
+        let nodes = [
+            Node::Inner(&[1, 2]),
+            Node::Inner(&[0, 3]),
+            Node::Leaf(-TerminalBlackWin { plies: 0 }),
+            Node::Leaf(TerminalDraw { plies: 0 }),
+        ];
+
+        let mut history = PathHistory::new();
+
+        // going around in circles is the best the losing side can get, and it's no proven draw
+        assert_eq!(negamax(&nodes, 0, &mut history, 0), NonTerminal { value: 0 });
+        assert!(history.is_empty());
+
+        // with contempt (the side to move at the repetition being the losing one), repeating isn't worth it: the
+        // opponent can get more out of going back to 0
+        assert_eq!(negamax(&nodes, 0, &mut history, 5), NonTerminal { value: -5 });
+
+        // without a loop the game draws for real
+        let nodes = [
+            Node::Inner(&[1]),
+            Node::Inner(&[2]),
+            Node::Leaf(TerminalDraw { plies: 0 }),
+        ];
+        assert_eq!(negamax(&nodes, 0, &mut history, 0), TerminalDraw { plies: 0 });
+    }
+}
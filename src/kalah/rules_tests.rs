@@ -0,0 +1,246 @@
+// tests pinning down the exact rules implemented by Board::apply_move: bonus moves, captures, the final sweep and
+// sowing around the whole board
+
+use crate::{Board, Move, Player};
+
+/// apply move (by White) to the board given in KGP format, check the resulting board and bonus flag
+fn check_move(before: &str, house: u8, after: &str, bonus: bool) {
+    let mut board = Board::from_kgp(before);
+
+    let moves_again = board.apply_move(Move::new(house, Player::White));
+
+    assert_eq!(board.to_kgp(), after, "Unexpected board after move {house} on {before}");
+    assert_eq!(
+        moves_again, bonus,
+        "Unexpected bonus flag after move {house} on {before}"
+    );
+}
+
+/// check every move of fixtures, in the format of move_fixtures.txt
+fn check_fixtures(fixtures: &str) {
+    for (line_num, line) in fixtures.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // the boards contain spaces themselves, but end with the only > of each
+        let parse = || -> Option<(&str, &str, &str)> {
+            let (before, rest) = line.split_once('>')?;
+            let (house, after) = rest.split_once('<')?;
+            Some((before, house, after))
+        };
+        let (before, house, after) = parse().unwrap_or_else(|| panic!("Malformed fixture on line {}", line_num + 1));
+
+        let mut board = Board::from_kgp(&format!("{before}>"));
+        let move_: Move = house.parse().unwrap();
+
+        board.apply_move(move_);
+
+        assert_eq!(
+            board.to_kgp(),
+            Board::from_kgp(&format!("<{after}")).to_kgp(),
+            "Unexpected board after move {} on {before}> (line {})",
+            house.trim(),
+            line_num + 1
+        );
+    }
+}
+
+#[test]
+fn test_move_fixtures() {
+    check_fixtures(include_str!("move_fixtures.txt"));
+}
+
+#[test]
+fn test_bonus_move() {
+    // last seed lands in our store
+    check_move("<3, 0, 0, 1, 2, 3, 1, 1, 1>", 1, "<3, 1, 0, 1, 0, 4, 1, 1, 1>", true);
+}
+
+#[test]
+fn test_no_bonus_move() {
+    // last seed lands in their houses
+    check_move("<3, 0, 0, 1, 2, 3, 1, 1, 1>", 2, "<3, 1, 0, 1, 2, 0, 2, 2, 1>", false);
+}
+
+#[test]
+fn test_capture() {
+    // last seed lands in our empty house 1, opposite of which is their house 1 with 4 seeds
+    check_move("<3, 0, 0, 1, 0, 3, 5, 4, 2>", 0, "<3, 5, 0, 0, 0, 3, 5, 0, 2>", false);
+}
+
+#[test]
+fn test_no_capture_empty_opposite() {
+    // last seed lands in our empty house 1, but the opposite house is empty as well: nothing happens
+    check_move("<3, 0, 0, 1, 0, 3, 5, 0, 2>", 0, "<3, 0, 0, 0, 1, 3, 5, 0, 2>", false);
+}
+
+#[test]
+fn test_capture_starves_opponent() {
+    // the capture takes their last seeds, so the game ends and we sweep our remaining seeds into our store
+    check_move("<3, 0, 0, 1, 0, 3, 0, 4, 0>", 0, "<3, 8, 0, 0, 0, 0, 0, 0, 0>", false);
+}
+
+#[test]
+fn test_move_starves_ourselves() {
+    // our last seed goes into our store, leaving us without seeds: they sweep their seeds into their store
+    check_move("<3, 2, 1, 0, 0, 1, 2, 2, 2>", 2, "<3, 3, 7, 0, 0, 0, 0, 0, 0>", true);
+}
+
+#[test]
+fn test_full_cycle() {
+    // 7 seeds with h = 2 (cycle length 5): every house and our store get one seed, then house 1 and our store
+    check_move("<2, 0, 0, 7, 0, 1, 1>", 0, "<2, 2, 0, 1, 2, 2, 2>", true);
+}
+
+#[test]
+fn test_exact_full_cycle() {
+    // exactly 2h + 1 seeds: the last seed lands in the (now empty) starting house, capturing the opposite house
+    check_move("<2, 0, 0, 5, 0, 1, 1>", 0, "<2, 4, 0, 0, 1, 2, 0>", false);
+}
+
+#[test]
+fn test_multiple_full_cycles() {
+    // 12 seeds with h = 2: two full cycles, then house 1 and our store
+    check_move("<2, 0, 0, 12, 0, 1, 1>", 0, "<2, 3, 0, 2, 3, 3, 3>", true);
+}
+
+#[test]
+fn test_huge_hand() {
+    use crate::kalah::Features;
+    use crate::House;
+
+    // a single house holding (almost) all seeds a board can have, sown around the board thousands of times
+    for seeds in [60000, House::MAX] {
+        let mut board = Board::from_kgp(&format!("<6, 0, 0, {seeds}, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0>"));
+        board.track_features(Features::ALL);
+
+        board.apply_move(Move::new(0, Player::White));
+
+        // cycle length 13: every house and our store get num_cycles seeds, the houses after the starting house get the
+        // remaining ones (fewer than h here, so none of them reach the store)
+        let num_cycles = seeds / 13;
+        let rem = (seeds % 13) as usize;
+        assert!(rem < 6);
+
+        assert_eq!(board.our_houses()[0], num_cycles);
+        for (idx, &house) in board.our_houses().iter().enumerate().skip(1) {
+            assert_eq!(house, num_cycles + (idx <= rem) as House, "{board}");
+        }
+        assert_eq!(board.our_store, num_cycles);
+        assert!(board.their_houses().iter().all(|&house| house == num_cycles));
+        assert_eq!(board.their_store, 0);
+
+        let total: u32 = board
+            .our_houses()
+            .iter()
+            .chain(board.their_houses())
+            .chain([&board.our_store, &board.their_store])
+            .map(|&seeds| seeds as u32)
+            .sum();
+        assert_eq!(total, seeds as u32);
+
+        let (our_house_seeds, their_house_seeds) = board.house_seeds();
+        assert_eq!(
+            our_house_seeds as u32 + their_house_seeds as u32 + num_cycles as u32,
+            seeds as u32
+        );
+    }
+}
+
+#[test]
+fn test_black_move() {
+    // a move by Black is the mirror image of the same move by White
+    let mut board = Board::from_kgp("<3, 0, 0, 1, 1, 1, 1, 2, 3>");
+
+    let moves_again = board.apply_move(Move::new(1, Player::Black));
+
+    assert_eq!(board.to_kgp(), "<3, 0, 1, 1, 1, 1, 1, 0, 4>");
+    assert!(moves_again);
+}
+
+#[test]
+fn test_apply_move_for() {
+    // same moves as apply_move with a Move of the given side
+    for side in [Player::White, Player::Black] {
+        let mut board = Board::from_kgp("<3, 0, 0, 1, 1, 1, 1, 2, 3>");
+        let mut expected = board.clone();
+
+        let outcome = board.apply_move_for(1, side);
+        let moves_again = expected.apply_move(Move::new(1, side));
+
+        assert_eq!(board.to_kgp(), expected.to_kgp());
+        assert_eq!(outcome.bonus, moves_again);
+    }
+}
+
+#[test]
+fn test_would_capture() {
+    // the board right after sowing: the last seed is alone in our house 1, opposite of their house 3 with 2 seeds
+    let board = Board::from_kgp("<3, 0, 0, 1, 0, 3, 5, 4, 2>");
+    assert_eq!(board.would_capture(0), Some((2, 2)));
+
+    // our house 2 was empty, but so is their house 2 opposite of it
+    let board = Board::from_kgp("<3, 0, 0, 0, 1, 3, 5, 0, 2>");
+    assert_eq!(board.would_capture(1), None);
+
+    // a house that wasn't empty before the last seed, our store and their houses never capture
+    let board = Board::from_kgp("<3, 0, 0, 2, 1, 1, 5, 4, 2>");
+    assert_eq!(board.would_capture(0), None);
+    assert_eq!(board.would_capture(1), Some((1, 4)));
+    for landing_house in 3..7 {
+        assert_eq!(board.would_capture(landing_house), None);
+    }
+}
+
+#[test]
+fn test_sweep_rules() {
+    use crate::kalah::valuation::store_diff_valuation;
+    use crate::kalah::{SweepRule, Valuation};
+    use crate::GameResult;
+
+    // our last seed goes into our store, leaving us without seeds: their 6 remaining seeds get swept by the rule
+    for (sweep_rule, after) in [
+        (SweepRule::OwnSide, "<3, 3, 7, 0, 0, 0, 0, 0, 0>"),
+        (SweepRule::LastMover, "<3, 9, 1, 0, 0, 0, 0, 0, 0>"),
+        (SweepRule::Discard, "<3, 3, 1, 0, 0, 0, 0, 0, 0>"),
+    ] {
+        let mut board = Board::from_kgp("<3, 2, 1, 0, 0, 1, 2, 2, 2>");
+        board.set_sweep_rule(sweep_rule);
+
+        board.apply_move(Move::new(2, Player::White));
+        assert_eq!(board.to_kgp(), after, "{sweep_rule:?}");
+
+        // clones and the boards of the search play by the same rule
+        assert_eq!(board.clone().sweep_rule(), sweep_rule);
+    }
+
+    // their side is starved, but the game hasn't been finished yet: with our turn, they moved last
+    for (sweep_rule, scores, winner) in [
+        (SweepRule::OwnSide, (7, 5), Player::White),
+        (SweepRule::LastMover, (4, 8), Player::Black),
+        (SweepRule::Discard, (4, 5), Player::Black),
+    ] {
+        let mut board = Board::from_kgp("<3, 4, 5, 1, 0, 2, 0, 0, 0>");
+        board.set_sweep_rule(sweep_rule);
+
+        assert_eq!(board.final_scores(), scores, "{sweep_rule:?}");
+        assert_eq!(board.winner(), Some(GameResult::Win(winner)), "{sweep_rule:?}");
+        assert_eq!(board.is_decided(), board.winner(), "{sweep_rule:?}");
+
+        let expected = match winner {
+            Player::White => Valuation::TerminalWhiteWin { plies: 0 },
+            Player::Black => Valuation::TerminalBlackWin { plies: 0 },
+        };
+        assert_eq!(store_diff_valuation(&board), expected, "{sweep_rule:?}");
+
+        board.finish_game();
+        assert_eq!(
+            (board.our_store as u32, board.their_store as u32),
+            scores,
+            "{sweep_rule:?}"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+/// who gets the seeds left in the houses when the game ends, see Board::finish_game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SweepRule {
+    /// every player gets the seeds on their own side (standard Kalah)
+    #[default]
+    OwnSide,
+
+    /// the player who made the last move gets all remaining seeds
+    LastMover,
+
+    /// the remaining seeds count for nobody, only the stores decide the game
+    Discard,
+}
+
+impl SweepRule {
+    /// seeds that go to our and their store when our_house_seeds and their_house_seeds are left in the houses
+    pub fn sweep(self, our_house_seeds: u32, their_house_seeds: u32, we_moved_last: bool) -> (u32, u32) {
+        let remaining = our_house_seeds + their_house_seeds;
+
+        match self {
+            SweepRule::OwnSide => (our_house_seeds, their_house_seeds),
+            SweepRule::LastMover if we_moved_last => (remaining, 0),
+            SweepRule::LastMover => (0, remaining),
+            SweepRule::Discard => (0, 0),
+        }
+    }
+}
@@ -1,10 +1,17 @@
 use std::fmt::Display;
 
-use crate::{Board, House};
+use crate::kalah::{Evaluator, PhaseEvaluator};
+use crate::{Board, GameResult, Player};
 
-/// # Safety
+/// bound of the heuristic values: evaluators keep NonTerminal values within [-MAX_EVAL, MAX_EVAL]
 ///
-/// - value shall never be f32::NAN, to making it comparable using f32::partial_cmp
+/// far below i32::MAX, so negating values and widening search windows around them can't overflow, and no heuristic
+/// estimate can get close enough to the limits to be mistaken for a result. Real positions stay far below it.
+pub const MAX_EVAL: i32 = 1 << 24;
+
+/// value of a position from White's perspective, either a heuristic estimate or the known result of the game
+///
+/// this is the only valuation type, all searches compare positions with its Ord (see there for the ordering rules)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Valuation {
     NonTerminal { value: i32 },
@@ -14,9 +21,41 @@ pub enum Valuation {
 }
 
 impl Valuation {
-    /* pub fn is_terminal(&self) -> bool {
+    /// heuristic value clamped to [-MAX_EVAL, MAX_EVAL], what evaluators should build their NonTerminal values with
+    pub fn non_terminal(value: i64) -> Valuation {
+        Valuation::NonTerminal {
+            value: value.clamp(-MAX_EVAL as i64, MAX_EVAL as i64) as i32,
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
         !(matches!(self, Valuation::NonTerminal { .. }))
-    } */
+    }
+
+    /// valuation as a float from White's perspective, e.g. for averaging
+    ///
+    /// non-terminal values map to themselves, certain wins and losses saturate to f32::MAX and f32::MIN and draws
+    /// map to 0.0 (like in cmp). The number of plies gets lost, so all wins (and all losses) compare equal as f32.
+    pub fn as_f32(&self) -> f32 {
+        match self {
+            Valuation::NonTerminal { value } => *value as f32,
+            Valuation::TerminalWhiteWin { .. } => f32::MAX,
+            Valuation::TerminalBlackWin { .. } => f32::MIN,
+            Valuation::TerminalDraw { .. } => 0.0,
+        }
+    }
+
+    /// whether plies is the exact distance to the forced result, for a value found by a search to depth
+    ///
+    /// depth doesn't count bonus moves, so a search to depth sees every line of up to depth plies, but not all longer
+    /// ones. A win (or loss) in plies is only known to be the shortest one once all lines that are shorter have been
+    /// searched, i.e. for plies <= depth + 1. Always false for values that aren't wins or losses.
+    pub fn distance_is_exact(&self, depth: u32) -> bool {
+        match self {
+            Valuation::TerminalWhiteWin { plies } | Valuation::TerminalBlackWin { plies } => *plies <= depth + 1,
+            Valuation::NonTerminal { .. } | Valuation::TerminalDraw { .. } => false,
+        }
+    }
 
     pub fn increase_plies(self) -> Valuation {
         use Valuation::{NonTerminal, TerminalBlackWin, TerminalDraw, TerminalWhiteWin};
@@ -28,6 +67,24 @@ impl Valuation {
             TerminalDraw { plies: steps } => TerminalDraw { plies: steps + 1 },
         }
     }
+
+    /// inverse of increase_plies, terminal valuations that are already at 0 plies stay there
+    pub fn decrease_plies(self) -> Valuation {
+        use Valuation::{NonTerminal, TerminalBlackWin, TerminalDraw, TerminalWhiteWin};
+
+        match self {
+            NonTerminal { .. } => self,
+            TerminalWhiteWin { plies: steps } => TerminalWhiteWin {
+                plies: steps.saturating_sub(1),
+            },
+            TerminalBlackWin { plies: steps } => TerminalBlackWin {
+                plies: steps.saturating_sub(1),
+            },
+            TerminalDraw { plies: steps } => TerminalDraw {
+                plies: steps.saturating_sub(1),
+            },
+        }
+    }
 }
 
 impl Display for Valuation {
@@ -63,7 +120,14 @@ impl PartialOrd for Valuation {
     }
 }
 
-// compare valuations from the perspective of White
+/// compare valuations from the perspective of White
+///
+/// - wins beat everything else, shorter wins beat longer ones
+/// - losses lose to everything else, longer losses beat shorter ones
+/// - NonTerminal and TerminalDraw compare by value, with draws counting as 0. A NonTerminal with value 0 beats a draw:
+///   an even position still has all results open and the opponent may still go wrong, while a draw is settled.
+///   This is also needed for Ord to be consistent with Eq, i.e. NonTerminal { value: 0 } must not be equal to draws.
+/// - longer draws beat shorter ones, for the same reason
 impl Ord for Valuation {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         use std::cmp::Ordering::{Greater, Less};
@@ -75,10 +139,22 @@ impl Ord for Valuation {
             (TerminalWhiteWin { .. }, _) => Greater,
             (_, TerminalWhiteWin { .. }) => Less,
 
-            // NonTerminal and TerminalDraw get compared by value (draws count as 0 value)
+            // NonTerminal and TerminalDraw get compared by value (draws count as 0 value, but lose ties)
             (NonTerminal { value: v1 }, NonTerminal { value: v2 }) => v1.cmp(v2),
-            (NonTerminal { value }, TerminalDraw { .. }) => value.cmp(&0),
-            (TerminalDraw { .. }, NonTerminal { value }) => 0.cmp(value),
+            (NonTerminal { value }, TerminalDraw { .. }) => {
+                if *value >= 0 {
+                    Greater
+                } else {
+                    Less
+                }
+            }
+            (TerminalDraw { .. }, NonTerminal { value }) => {
+                if *value >= 0 {
+                    Less
+                } else {
+                    Greater
+                }
+            }
             // select longer draw: more chances for opponent to mess up
             (TerminalDraw { plies: p1 }, TerminalDraw { plies: p2 }) => p1.cmp(p2),
 
@@ -94,88 +170,69 @@ impl Ord for Valuation {
 
 pub type ValuationFn = fn(&Board) -> Valuation;
 
-#[allow(dead_code)]
-pub fn store_diff_valuation(board: &Board) -> Valuation {
-    use Valuation::{NonTerminal, TerminalBlackWin, TerminalDraw, TerminalWhiteWin};
-
-    let our_store = board.our_store as i32;
-    let their_store = board.their_store as i32;
-
-    let store_diff = our_store - their_store;
+/// all valuation functions by name, new heuristics only have to be added here to be selectable from the command line
+pub const VALUATIONS: &[(&str, ValuationFn)] = &[
+    ("store_diff", store_diff_valuation),
+    ("store_diff2", store_diff_valuation2),
+    ("seed_diff", seed_diff_valuation),
+    ("phase", phase_valuation),
+    ("capture_threat", capture_threat_valuation),
+];
+
+pub fn valuation_by_name(name: &str) -> Option<ValuationFn> {
+    VALUATIONS
+        .iter()
+        .find(|&&(valuation_name, _)| valuation_name == name)
+        .map(|&(_, valuation_fn)| valuation_fn)
+}
 
-    if !board.has_legal_move() {
-        // no move left or more than half the seeds in one players store -> this is a terminal node
-        // meaning the player with more seeds in their store wins the game
-        // thus if White has more seeds in the store (i.e. score_diff > 0) this node is a guaranteed win
-        // and vice versa. If both have the same number, it's a draw with value 0.0
+/// terminal valuation of a decided position (see Board::is_decided), from the perspective of the player to move
+///
+/// shared by all valuation functions (and evaluators like Weights), so they agree on which positions are terminal
+pub(crate) fn decided_valuation(board: &Board) -> Option<Valuation> {
+    use Valuation::{TerminalBlackWin, TerminalDraw, TerminalWhiteWin};
+
+    // valuations always see our side as White, no matter who that is in the original orientation
+    let our_player = if board.flipped() { Player::Black } else { Player::White };
+
+    board.is_decided().map(|result| match result {
+        GameResult::Win(player) if player == our_player => TerminalWhiteWin { plies: 0 },
+        GameResult::Win(_) => TerminalBlackWin { plies: 0 },
+        GameResult::Draw => TerminalDraw { plies: 0 },
+    })
+}
 
-        return match store_diff {
-            store_diff if store_diff > 0 => TerminalWhiteWin { plies: 0 },
-            store_diff if store_diff < 0 => TerminalBlackWin { plies: 0 },
-            store_diff if store_diff == 0 => TerminalDraw { plies: 0 },
-            _ => unreachable!(),
-        };
+pub fn store_diff_valuation(board: &Board) -> Valuation {
+    if let Some(valuation) = decided_valuation(board) {
+        return valuation;
     }
 
-    NonTerminal { value: store_diff }
+    let store_diff = board.our_store as i32 - board.their_store as i32;
+
+    Valuation::non_terminal(store_diff.into())
 }
 
-#[allow(dead_code)]
 pub fn store_diff_valuation2(board: &Board) -> Valuation {
-    use Valuation::{NonTerminal, TerminalBlackWin, TerminalDraw, TerminalWhiteWin};
-
-    let our_store = board.our_store as i32;
-    let their_store = board.their_store as i32;
-
-    let our_houses_sum = board.our_houses().iter().sum::<u16>() as i32;
-    let their_houses_sum = board.their_houses().iter().sum::<u16>() as i32;
-
-    let half_total_seeds = (our_store + our_houses_sum + their_store + their_houses_sum) / 2;
-
-    let store_diff = our_store - their_store;
-
-    if !board.has_legal_move() || our_store > half_total_seeds || their_store > half_total_seeds {
-        // no move left or more than half the seeds in one players store -> this is a terminal node
-        // meaning the player with more seeds in their store wins the game
-        // thus if White has more seeds in the store (i.e. score_diff > 0) this node is a guaranteed win
-        // and vice versa. If both have the same number, it's a draw with value 0.0
-
-        return match store_diff {
-            store_diff if store_diff > 0 => TerminalWhiteWin { plies: 0 },
-            store_diff if store_diff < 0 => TerminalBlackWin { plies: 0 },
-            store_diff if store_diff == 0 => TerminalDraw { plies: 0 },
-            _ => unreachable!(),
-        };
+    // no move left or more than half the seeds in one players store -> this is a terminal node
+    if let Some(valuation) = decided_valuation(board) {
+        return valuation;
     }
 
-    NonTerminal { value: store_diff }
+    let store_diff = board.our_store as i32 - board.their_store as i32;
+
+    Valuation::non_terminal(store_diff.into())
 }
 
-#[allow(dead_code)]
 pub fn seed_diff_valuation(board: &Board) -> Valuation {
-    use Valuation::{NonTerminal, TerminalBlackWin, TerminalDraw, TerminalWhiteWin};
+    if let Some(valuation) = decided_valuation(board) {
+        return valuation;
+    }
 
     let our_store = board.our_store as i32;
     let their_store = board.their_store as i32;
 
-    let our_houses_sum = board.our_houses().iter().sum::<House>() as i32;
-    let their_houses_sum = board.their_houses().iter().sum::<House>() as i32;
-
-    if !board.has_legal_move() {
-        // no move left or more than half the seeds in one players store -> this is a terminal node
-        // meaning the player with more seeds in their store wins the game
-        // thus if White has more seeds in the store (i.e. score_diff > 0) this node is a guaranteed win
-        // and vice versa. If both have the same number, it's a draw with value 0.0
-        let store_diff = our_store - their_store;
-
-        return match store_diff {
-            store_diff if store_diff > 0 => TerminalWhiteWin { plies: 0 },
-            store_diff if store_diff < 0 => TerminalBlackWin { plies: 0 },
-            store_diff if store_diff == 0 => TerminalDraw { plies: 0 },
-            // val => panic!("Value has invalid value {}", val),
-            _ => unreachable!(),
-        };
-    }
+    let (our_houses_sum, their_houses_sum) = board.house_seeds();
+    let (our_houses_sum, their_houses_sum) = (our_houses_sum as i32, their_houses_sum as i32);
 
     // let total_seeds = our_store + our_houses_sum + their_store + their_houses_sum;
 
@@ -189,14 +246,46 @@ pub fn seed_diff_valuation(board: &Board) -> Valuation {
     // let score = ((seed_diff as i32) << 16) + (store_diff as i32 - (i16::MIN as i32));
     let score = seed_diff;
 
-    NonTerminal { value: score }
+    Valuation::non_terminal(score.into())
+}
+
+/// PhaseEvaluator with its default thresholds, for the searches that take a ValuationFn
+pub fn phase_valuation(board: &Board) -> Valuation {
+    PhaseEvaluator::new().evaluate(board)
+}
+
+/// most seeds a single move of player could capture right now, 0 if none of its moves captures
+fn best_capture(board: &Board, player: Player) -> i32 {
+    board
+        .legal_moves(player)
+        .into_iter()
+        .filter_map(|move_| board.move_preview(move_).capture)
+        .map(|(_house, seeds)| seeds as i32)
+        .max()
+        .unwrap_or(0)
+}
+
+/// store difference plus the biggest capture we could make minus the biggest one the opponent could make
+///
+/// one ply of lookahead without searching: rewards setting up captures and penalizes loaded houses left across from an
+/// empty house of the opponent
+pub fn capture_threat_valuation(board: &Board) -> Valuation {
+    if let Some(valuation) = decided_valuation(board) {
+        return valuation;
+    }
+
+    let store_diff = board.our_store as i64 - board.their_store as i64;
+    let threat_diff = best_capture(board, Player::White) as i64 - best_capture(board, Player::Black) as i64;
+
+    Valuation::non_terminal(store_diff + threat_diff)
 }
 
 /*====================================================================================================================*/
 
 #[cfg(test)]
 mod tests {
-    use super::Valuation;
+    use super::{capture_threat_valuation, valuation_by_name, Valuation, MAX_EVAL, VALUATIONS};
+    use crate::Board;
 
     #[test]
     fn test_cmp() {
@@ -227,5 +316,218 @@ mod tests {
         assert!(bw1 < draw1);
         assert!(draw1 < ww1);
         assert!(bw1 < ww1);
+
+        assert_eq!(ww1.increase_plies().decrease_plies(), ww1);
+        assert_eq!(
+            TerminalBlackWin { plies: 0 }.decrease_plies(),
+            TerminalBlackWin { plies: 0 }
+        );
+    }
+
+    #[test]
+    fn test_cmp_even_vs_draw() {
+        use std::cmp::Ordering::Equal;
+        use Valuation::{NonTerminal, TerminalBlackWin, TerminalDraw, TerminalWhiteWin};
+
+        let even = NonTerminal { value: 0 };
+
+        assert!(even > TerminalDraw { plies: 0 });
+        assert!(even > TerminalDraw { plies: 100 });
+        assert!(NonTerminal { value: -1 } < TerminalDraw { plies: 0 });
+        assert!(-even > -TerminalDraw { plies: 3 });
+
+        // Ord has to agree with Eq and be transitive over all kinds of valuations
+        let valuations = [
+            TerminalBlackWin { plies: 2 },
+            TerminalBlackWin { plies: 7 },
+            NonTerminal { value: -3 },
+            TerminalDraw { plies: 1 },
+            TerminalDraw { plies: 4 },
+            NonTerminal { value: 0 },
+            NonTerminal { value: 3 },
+            TerminalWhiteWin { plies: 7 },
+            TerminalWhiteWin { plies: 2 },
+        ];
+
+        for (i, v1) in valuations.iter().enumerate() {
+            for (j, v2) in valuations.iter().enumerate() {
+                assert_eq!(v1.cmp(v2), i.cmp(&j), "{v1} vs {v2}");
+                assert_eq!(v1.cmp(v2) == Equal, v1 == v2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_as_f32() {
+        use Valuation::{NonTerminal, TerminalBlackWin, TerminalDraw, TerminalWhiteWin};
+
+        let valuations = [
+            TerminalBlackWin { plies: 5 },
+            TerminalBlackWin { plies: 10 },
+            NonTerminal { value: i32::MIN },
+            NonTerminal { value: -5 },
+            TerminalDraw { plies: 3 },
+            NonTerminal { value: 5 },
+            NonTerminal { value: i32::MAX },
+            TerminalWhiteWin { plies: 10 },
+            TerminalWhiteWin { plies: 5 },
+        ];
+
+        // as_f32 never contradicts cmp
+        for window in valuations.windows(2) {
+            assert!(window[0] < window[1]);
+            assert!(window[0].as_f32() <= window[1].as_f32());
+        }
+
+        assert_eq!(TerminalWhiteWin { plies: 1 }.as_f32(), f32::MAX);
+        assert_eq!(TerminalBlackWin { plies: 1 }.as_f32(), f32::MIN);
+        assert_eq!(TerminalDraw { plies: 1 }.as_f32(), 0.0);
+        assert_eq!(NonTerminal { value: -7 }.as_f32(), -7.0);
+
+        assert!(valuations
+            .iter()
+            .all(|v| v.is_terminal() != matches!(v, NonTerminal { .. })));
+    }
+
+    #[test]
+    fn test_valuations_agree_on_terminal() {
+        use rand::seq::SliceRandom;
+
+        use crate::Player;
+
+        let mut rng = rand::thread_rng();
+
+        // more than half of the seeds in our store, but both sides can still move
+        let decided = Board::from_kgp("<3, 10, 3, 1, 1, 1, 1, 1, 1>");
+        let mut boards = vec![decided.clone()];
+
+        let mut flipped = decided;
+        flipped.flip_board();
+        boards.push(flipped);
+
+        for _ in 0..50 {
+            let mut board = Board::new(6, 6);
+
+            while board.has_legal_move() {
+                let move_ = *board.legal_moves(Player::White).choose(&mut rng).unwrap();
+
+                if !board.apply_move(move_) {
+                    board.flip_board();
+                }
+
+                boards.push(board.clone());
+            }
+        }
+
+        for board in &boards {
+            let valuations: Vec<Valuation> = VALUATIONS
+                .iter()
+                .map(|&(_, valuation_fn)| valuation_fn(board))
+                .collect();
+
+            assert_eq!(valuations[0].is_terminal(), board.is_decided().is_some(), "{board}");
+
+            for valuation in &valuations[1..] {
+                assert_eq!(valuation.is_terminal(), valuations[0].is_terminal(), "{board}");
+
+                if valuation.is_terminal() {
+                    assert_eq!(*valuation, valuations[0], "{board}");
+                }
+            }
+        }
+
+        assert_eq!(VALUATIONS[0].1(&boards[0]), Valuation::TerminalWhiteWin { plies: 0 });
+        assert_eq!(VALUATIONS[0].1(&boards[1]), Valuation::TerminalBlackWin { plies: 0 });
+    }
+
+    #[test]
+    fn test_analysis_flip() {
+        use rand::seq::SliceRandom;
+
+        use crate::Player;
+
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..20 {
+            let mut board = Board::new(6, 6);
+
+            while board.has_legal_move() {
+                let (flipped, to_ours) = board.analysis_flip();
+                assert_eq!(flipped.flipped(), !board.flipped());
+
+                // all valuations are symmetric, so the other side's view converted back has to match ours
+                for &(name, valuation_fn) in VALUATIONS {
+                    assert_eq!(to_ours(valuation_fn(&flipped)), valuation_fn(&board), "{name}: {board}");
+                }
+
+                let move_ = *board.legal_moves(Player::White).choose(&mut rng).unwrap();
+
+                if !board.apply_move(move_) {
+                    board.flip_board();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_valuation_by_name() {
+        let board = Board::from_kgp("<3, 4, 1, 0, 2, 5, 1, 0, 3>");
+
+        for &(name, valuation_fn) in VALUATIONS {
+            let found = valuation_by_name(name).unwrap();
+            assert_eq!(found(&board), valuation_fn(&board), "{name}");
+        }
+
+        assert!(valuation_by_name("mobility2").is_none());
+        assert!(valuation_by_name("").is_none());
+    }
+
+    #[test]
+    fn test_capture_threat_valuation() {
+        use Valuation::NonTerminal;
+
+        // our house 1 lands in the empty house 2 and takes the 5 seeds across, Black has no capture
+        let board = Board::from_kgp("<3, 0, 0, 1, 0, 1, 1, 5, 1>");
+        assert_eq!(capture_threat_valuation(&board), NonTerminal { value: 5 });
+
+        let mut flipped = board.clone();
+        flipped.flip_board();
+        assert_eq!(capture_threat_valuation(&flipped), NonTerminal { value: -5 });
+
+        // Black's house 1 lands in its empty house 2 and takes our 4 seeds, which outweighs our store lead of 1
+        let board = Board::from_kgp("<3, 2, 1, 0, 4, 0, 1, 0, 0>");
+        assert_eq!(capture_threat_valuation(&board), NonTerminal { value: -3 });
+    }
+
+    #[test]
+    fn test_eval_clamp() {
+        use Valuation::{NonTerminal, TerminalBlackWin, TerminalWhiteWin};
+
+        assert_eq!(Valuation::non_terminal(i64::MAX), NonTerminal { value: MAX_EVAL });
+        assert_eq!(Valuation::non_terminal(i64::MIN), NonTerminal { value: -MAX_EVAL });
+        assert_eq!(-Valuation::non_terminal(i64::MIN), Valuation::non_terminal(i64::MAX));
+
+        // clamped values stay clear of the results
+        assert!(Valuation::non_terminal(i64::MAX) < TerminalWhiteWin { plies: u32::MAX });
+        assert!(Valuation::non_terminal(i64::MIN) > TerminalBlackWin { plies: u32::MAX });
+
+        // normal values pass unchanged, so they order like before, and clamping never reverses an order
+        let values = [
+            i64::MIN,
+            -(MAX_EVAL as i64) - 1,
+            -1000,
+            -1,
+            0,
+            1,
+            1000,
+            MAX_EVAL as i64 + 1,
+            i64::MAX,
+        ];
+        for &value in &values[2..7] {
+            assert_eq!(Valuation::non_terminal(value), NonTerminal { value: value as i32 });
+        }
+        for pair in values.windows(2) {
+            assert!(Valuation::non_terminal(pair[0]) <= Valuation::non_terminal(pair[1]));
+        }
     }
 }
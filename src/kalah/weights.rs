@@ -0,0 +1,217 @@
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::kalah::valuation::{decided_valuation, Valuation};
+use crate::kalah::Evaluator;
+use crate::{Board, Player};
+
+/// weights of a linear valuation, to be tuned offline and loaded at runtime (see WeightedAgent)
+///
+/// the value is the weighted sum of the store difference, the seed differences between the houses with the same index
+/// on both sides and the difference in the number of legal moves. Both sides get the same weights, so the valuation
+/// is symmetric like all others. Values beyond MAX_EVAL get clamped, so huge weights can't overflow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Weights {
+    /// one weight per house index, counted from each side's first house
+    pub houses: Vec<i32>,
+
+    pub store: i32,
+
+    pub mobility: i32,
+}
+
+impl Weights {
+    /// default weights for boards with h houses: seeds in the store count twice as much as seeds in the houses
+    pub fn new(h: u8) -> Self {
+        Weights {
+            houses: vec![1; h as usize],
+            store: 2,
+            mobility: 0,
+        }
+    }
+
+    /// read weights from a file in the format of Display
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+
+        std::fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {err}", path.display()))?
+            .parse()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+
+        std::fs::write(path, self.to_string()).map_err(|err| format!("Could not write {}: {err}", path.display()))
+    }
+}
+
+/// one weight per line, the house weights in a single line starting with the first house, # starts a comment:
+///
+/// ```text
+/// houses 1 1 1 1 1 1
+/// store 2
+/// mobility 0
+/// ```
+impl Display for Weights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let houses: Vec<String> = self.houses.iter().map(|weight| weight.to_string()).collect();
+
+        writeln!(f, "houses {}", houses.join(" "))?;
+        writeln!(f, "store {}", self.store)?;
+        writeln!(f, "mobility {}", self.mobility)
+    }
+}
+
+impl FromStr for Weights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut houses = None;
+        let mut store = None;
+        let mut mobility = None;
+
+        for (line_num, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let key = words.next().unwrap();
+
+            let values = words
+                .map(|word| word.parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|err| format!("Line {}: invalid weight ({err})", line_num + 1))?;
+
+            let (weight, num_values) = match key {
+                "houses" => (&mut houses, values.len().max(1)),
+                "store" => (&mut store, 1),
+                "mobility" => (&mut mobility, 1),
+                _ => return Err(format!("Line {}: unknown weight {key}", line_num + 1)),
+            };
+
+            if values.len() != num_values || values.len() > 128 {
+                return Err(format!("Line {}: wrong number of values for {key}", line_num + 1));
+            }
+
+            if weight.replace(values).is_some() {
+                return Err(format!("Line {}: {key} given more than once", line_num + 1));
+            }
+        }
+
+        let missing = |key: &str| format!("No weight for {key}");
+
+        Ok(Weights {
+            houses: houses.ok_or_else(|| missing("houses"))?,
+            store: store.ok_or_else(|| missing("store"))?[0],
+            mobility: mobility.ok_or_else(|| missing("mobility"))?[0],
+        })
+    }
+}
+
+impl Evaluator for Weights {
+    fn evaluate(&self, board: &Board) -> Valuation {
+        if let Some(valuation) = decided_valuation(board) {
+            return valuation;
+        }
+
+        debug_assert_eq!(self.houses.len(), board.h() as usize, "Weights don't fit the board");
+
+        // summed up in i64, so large weights get clamped instead of overflowing
+        let store_diff = board.our_store as i64 - board.their_store as i64;
+
+        let house_diffs: i64 = self
+            .houses
+            .iter()
+            .zip(board.our_houses().iter().zip(board.their_houses()))
+            .map(|(&weight, (&ours, &theirs))| weight as i64 * (ours as i64 - theirs as i64))
+            .sum();
+
+        let mobility_diff =
+            board.legal_mask(Player::White).count_ones() as i64 - board.legal_mask(Player::Black).count_ones() as i64;
+
+        Valuation::non_terminal(self.store as i64 * store_diff + house_diffs + self.mobility as i64 * mobility_diff)
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::Weights;
+    use crate::kalah::valuation::{Valuation, MAX_EVAL};
+    use crate::kalah::Evaluator;
+    use crate::Board;
+
+    #[test]
+    fn test_parse_weights() {
+        let weights = Weights {
+            houses: vec![3, -1, 0],
+            store: 5,
+            mobility: 2,
+        };
+
+        assert_eq!(weights.to_string().parse(), Ok(weights.clone()));
+
+        let path = std::env::temp_dir().join(format!("kalah_test_weights_{}.txt", std::process::id()));
+        weights.save(&path).unwrap();
+        assert_eq!(Weights::load(&path), Ok(weights));
+        std::fs::remove_file(&path).unwrap();
+
+        // comments, blank lines and any order
+        assert_eq!(
+            "# tuned\n\nmobility 1\nstore 2 # stores\nhouses 1 2\n".parse(),
+            Ok(Weights {
+                houses: vec![1, 2],
+                store: 2,
+                mobility: 1,
+            })
+        );
+
+        for malformed in [
+            "houses 1 1\nstore 2",
+            "houses 1 1\nstore 2\nmobility 0\nstore 3",
+            "houses\nstore 2\nmobility 0",
+            "houses 1 1\nstore 2 3\nmobility 0",
+            "houses 1 x\nstore 2\nmobility 0",
+            "houses 1 1\nstore 2\nmobility 0\ncaptures 4",
+        ] {
+            assert!(malformed.parse::<Weights>().is_err(), "{malformed:?}");
+        }
+
+        assert!(Weights::load(std::env::temp_dir().join("kalah_test_no_such_weights.txt")).is_err());
+    }
+
+    #[test]
+    fn test_weights_evaluate() {
+        let weights = Weights {
+            houses: vec![3, 2, 1],
+            store: 5,
+            mobility: 7,
+        };
+
+        // store diff 1, house diffs (2 - 0, 0 - 1, 1 - 1), legal moves 2 vs 2
+        let board = Board::from_kgp("<3, 2, 1, 2, 0, 1, 0, 1, 1>");
+        assert_eq!(weights.evaluate(&board), Valuation::NonTerminal { value: 5 + 6 - 2 });
+
+        let mut flipped = board.clone();
+        flipped.flip_board();
+        assert_eq!(weights.evaluate(&flipped), -weights.evaluate(&board));
+
+        let huge = Weights {
+            houses: vec![i32::MAX; 3],
+            store: i32::MAX,
+            mobility: i32::MAX,
+        };
+        assert_eq!(huge.evaluate(&board), Valuation::NonTerminal { value: MAX_EVAL });
+        assert_eq!(huge.evaluate(&flipped), Valuation::NonTerminal { value: -MAX_EVAL });
+
+        // decided positions are terminal like with all valuations
+        let board = Board::from_kgp("<3, 7, 1, 2, 0, 1, 0, 1, 1>");
+        assert_eq!(weights.evaluate(&board), Valuation::TerminalWhiteWin { plies: 0 });
+    }
+}
@@ -0,0 +1,83 @@
+use lazy_static::lazy_static;
+
+use super::board::{House, Player};
+use crate::util::random::Rng;
+
+/// seed of the Zobrist keys, public so hashes (e.g. in logs or stored tables) can be reproduced by other processes
+///
+/// changing it changes every hash, which invalidates any persisted data keyed by them, such as opening books or stored
+/// tables
+pub const ZOBRIST_SEED: u64 = 0x6b61_6c61_685f_7a62;
+
+// seed counts with a key of their own, larger counts (only possible on boards with many seeds) get one mixed from it
+const KEYED_SEEDS: usize = 128;
+
+// key slots: White's houses, Black's houses (128 each, the most a board can have), White's store, Black's store and
+// the flipped flag (as if it was a slot holding one seed)
+const WHITE_HOUSES: usize = 0;
+const BLACK_HOUSES: usize = 128;
+const WHITE_STORE: usize = 256;
+const BLACK_STORE: usize = 257;
+const FLIPPED: usize = 258;
+const NUM_SLOTS: usize = 259;
+
+lazy_static! {
+    // one key per slot and seed count, the key of an empty slot is 0 so empty houses don't change the hash
+    static ref KEYS: Box<[[u64; KEYED_SEEDS]]> = {
+        // util's Rng rather than rand's, so the keys can't change with a dependency update
+        let mut rng = Rng::new(ZOBRIST_SEED);
+
+        (0..NUM_SLOTS)
+            .map(|_| {
+                let mut keys = [0; KEYED_SEEDS];
+                for key in &mut keys[1..] {
+                    *key = rng.gen_u64();
+                }
+                keys
+            })
+            .collect()
+    };
+}
+
+fn key(slot: usize, seeds: House) -> u64 {
+    let seeds = seeds as usize;
+
+    if seeds < KEYED_SEEDS {
+        KEYS[slot][seeds]
+    } else {
+        Rng::new(KEYS[slot][KEYED_SEEDS - 1] ^ seeds as u64).gen_u64()
+    }
+}
+
+/// key of house idx of player holding seeds
+pub(super) fn house_key(player: Player, idx: usize, seeds: House) -> u64 {
+    match player {
+        Player::White => key(WHITE_HOUSES + idx, seeds),
+        Player::Black => key(BLACK_HOUSES + idx, seeds),
+    }
+}
+
+/// key of the store of player holding seeds
+pub(super) fn store_key(player: Player, seeds: House) -> u64 {
+    match player {
+        Player::White => key(WHITE_STORE, seeds),
+        Player::Black => key(BLACK_STORE, seeds),
+    }
+}
+
+/// key of a flipped board, see Board::flipped
+pub(super) fn flipped_key() -> u64 {
+    KEYS[FLIPPED][1]
+}
+
+/// hash of the houses alone, with our houses being player's
+pub(super) fn houses_hash(player: Player, our_houses: &[House], their_houses: &[House]) -> u64 {
+    let side_hash = |player, houses: &[House]| {
+        houses
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (idx, &seeds)| hash ^ house_key(player, idx, seeds))
+    };
+
+    side_hash(player, our_houses) ^ side_hash(!player, their_houses)
+}
@@ -4,11 +4,12 @@ use std::str::FromStr;
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use super::KgpError;
 use crate::Board;
 
 #[derive(Debug)]
 pub enum Command {
-    Kpg {
+    Kgp {
         id: Option<u32>,
         ref_id: Option<u32>,
         major: u8,
@@ -53,6 +54,13 @@ pub enum Command {
         ref_id: Option<u32>,
         msg: String,
     },
+    /// command this client doesn't know, e.g. from a newer minor version of the protocol
+    Unknown {
+        id: Option<u32>,
+        ref_id: Option<u32>,
+        cmd: String,
+        args: String,
+    },
 }
 
 // from kalah-game/client/pykgp/kgp.py
@@ -79,11 +87,17 @@ $
     // static ref KGP_REGEX: Regex = Regex::new(r"^(?P<major>\d+)\s*(?P<minor>\d+)\s*(?P<patch>\d+)\s*$").unwrap();
 }
 
-// based on connect in kalah-game/client/pykgp/kgp.py
 impl FromStr for Command {
-    type Err = String;
+    type Err = KgpError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Command::parse(s).map_err(KgpError::Parse)
+    }
+}
+
+impl Command {
+    // based on connect in kalah-game/client/pykgp/kgp.py
+    fn parse(s: &str) -> Result<Self, String> {
         let command_captures = COMMAND_REGEX
             .captures(s)
             .ok_or(format!("command \"{s}\" didn't match regex"))?;
@@ -113,20 +127,20 @@ impl FromStr for Command {
                 let args_vec: Vec<&str> = args.split_ascii_whitespace().collect();
 
                 if args_vec.len() != 3 {
-                    return Err(format!("Unexpected args for kpg command: \"{args}\""));
+                    return Err(format!("Unexpected args for kgp command: \"{args}\""));
                 }
 
                 let major: u8 = args_vec[0]
                     .parse()
-                    .map_err(|_| "Could not parse major version of kpg command")?;
+                    .map_err(|_| "Could not parse major version of kgp command")?;
                 let minor: u8 = args_vec[1]
                     .parse()
-                    .map_err(|_| "Could not parse minor version of kpg command")?;
+                    .map_err(|_| "Could not parse minor version of kgp command")?;
                 let patch: u8 = args_vec[2]
                     .parse()
-                    .map_err(|_| "Could not parse patch version of kpg command")?;
+                    .map_err(|_| "Could not parse patch version of kgp command")?;
 
-                Ok(Command::Kpg {
+                Ok(Command::Kgp {
                     id,
                     ref_id,
                     major,
@@ -141,7 +155,7 @@ impl FromStr for Command {
                     return Err(format!("Unexpected args for state command: \"{args}\""));
                 }
 
-                let board = Board::from_kpg(args_vec[0]);
+                let board = Board::try_from_kgp(args_vec[0])?;
 
                 Ok(Command::State { id, ref_id, board })
             }
@@ -177,7 +191,12 @@ impl FromStr for Command {
                 ref_id,
                 msg: args.to_owned(),
             }),
-            _ => Err(format!("Unknown command {cmd}")),
+            _ => Ok(Command::Unknown {
+                id,
+                ref_id,
+                cmd: cmd.to_owned(),
+                args: args.to_owned(),
+            }),
         }
     }
 }
@@ -185,7 +204,7 @@ impl FromStr for Command {
 impl Display for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Command::Kpg {
+            Command::Kgp {
                 id,
                 ref_id,
                 major,
@@ -198,7 +217,7 @@ impl Display for Command {
                 if let Some(ref_id) = ref_id {
                     write!(f, "@{ref_id}")?;
                 }
-                write!(f, " kpg {major} {minor} {patch}")
+                write!(f, " kgp {major} {minor} {patch}")
             }
             Command::State { id, ref_id, board } => {
                 if let Some(id) = id {
@@ -277,6 +296,91 @@ impl Display for Command {
                 }
                 write!(f, " error {msg}")
             }
+            Command::Unknown { id, ref_id, cmd, args } => {
+                if let Some(id) = id {
+                    write!(f, "{id}")?;
+                }
+                if let Some(ref_id) = ref_id {
+                    write!(f, "@{ref_id}")?;
+                }
+                write!(f, " {cmd}")?;
+                if !args.is_empty() {
+                    write!(f, " {args}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+
+    #[test]
+    fn test_kgp_round_trip() {
+        let line = "1 kgp 1 0 0";
+
+        let cmd: Command = line.parse().unwrap();
+
+        match cmd {
+            Command::Kgp {
+                id,
+                ref_id,
+                major,
+                minor,
+                patch,
+            } => {
+                assert_eq!(id, Some(1));
+                assert_eq!(ref_id, None);
+                assert_eq!((major, minor, patch), (1, 0, 0));
+            }
+            _ => panic!("Expected kgp command, got {cmd:?}"),
         }
+
+        assert_eq!(cmd.to_string(), line);
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let line = "12@7 handicap 3 south";
+
+        let cmd: Command = line.parse().unwrap();
+
+        match cmd {
+            Command::Unknown {
+                id,
+                ref_id,
+                ref cmd,
+                ref args,
+            } => {
+                assert_eq!(id, Some(12));
+                assert_eq!(ref_id, Some(7));
+                assert_eq!(cmd, "handicap");
+                assert_eq!(args, "3 south");
+            }
+            _ => panic!("Expected unknown command, got {cmd:?}"),
+        }
+
+        assert_eq!(cmd.to_string(), line);
+
+        // without arguments
+        assert!(matches!("3 resign".parse(), Ok(Command::Unknown { ref args, .. }) if args.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        use crate::kgp::KgpError;
+
+        assert!(matches!("".parse::<Command>(), Err(KgpError::Parse(_))));
+        assert!(matches!("1 kgp 1 0".parse::<Command>(), Err(KgpError::Parse(_))));
+
+        // a malformed board is an error rather than a panic
+        assert!(matches!(
+            "4 state <3,0,0,3,3>".parse::<Command>(),
+            Err(KgpError::Parse(_))
+        ));
     }
 }
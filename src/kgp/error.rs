@@ -0,0 +1,44 @@
+use std::fmt::Display;
+
+/// everything that can go wrong talking to a KGP server, see Connection and kgp_connect
+#[derive(Debug)]
+pub enum KgpError {
+    /// a line from the server that isn't a valid command, e.g. a state with a malformed board
+    Parse(String),
+
+    /// reading from or writing to the connection failed
+    Io(std::io::Error),
+
+    /// the server ended the session in a way the client can't recover from, e.g. an unsupported protocol version or
+    /// rejecting every mode
+    Protocol(String),
+
+    /// a command without the id it needs, e.g. a state that can't be answered because it has none
+    UnexpectedId(String),
+}
+
+impl Display for KgpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KgpError::Parse(msg) => write!(f, "Invalid command: {msg}"),
+            KgpError::Io(err) => write!(f, "Connection failed: {err}"),
+            KgpError::Protocol(msg) => write!(f, "Protocol error: {msg}"),
+            KgpError::UnexpectedId(msg) => write!(f, "Unexpected id: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for KgpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KgpError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for KgpError {
+    fn from(err: std::io::Error) -> Self {
+        KgpError::Io(err)
+    }
+}
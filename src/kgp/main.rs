@@ -1,12 +1,14 @@
-use std::time::Duration;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
 
-use crate::agent::{Agent, AgentState};
+use log::{debug, error, info, warn};
+
+use crate::agent::{Agent, AgentState, TimeAllocationPolicy};
 // use crate::kalah::valuation;
 use crate::kgp::Connection;
-use crate::tournament::MinimaxAgent;
-use crate::Board;
+use crate::{Board, GameResult, Move, Player};
 
-use super::Command;
+use super::{Command, KgpError, Mode};
 
 /*====================================================================================================================*/
 
@@ -20,19 +22,249 @@ static mut CTRLC_STATUS: CtrlCStatus = CtrlCStatus::Run; */
 
 /*====================================================================================================================*/
 
-fn process_command(conn: &mut Connection, agent: &mut Box<dyn Agent>, cur_id: &mut u32) {
+/// modes to request from the server, in order of preference
+///
+/// if the server rejects a mode the next one is requested, if it rejects all of them the session ends with an error
+struct ModeNegotiation {
+    modes: Vec<Mode>,
+
+    // index of the requested mode and id of the mode command, until the server answered it
+    pending: Option<(usize, u32)>,
+}
+
+impl ModeNegotiation {
+    fn new(modes: Vec<Mode>) -> Self {
+        assert!(!modes.is_empty(), "Need at least one mode to request");
+
+        ModeNegotiation { modes, pending: None }
+    }
+
+    /// request the mode at idx, returns false if there are no modes left to request
+    fn request(&mut self, conn: &mut Connection, idx: usize) -> Result<bool, KgpError> {
+        let Some(&mode) = self.modes.get(idx) else {
+            return Ok(false);
+        };
+
+        let id = conn.write_command(&format!("mode {mode}"), None)?;
+        self.pending = Some((idx, id));
+
+        info!("Requested mode: {mode}");
+
+        Ok(true)
+    }
+
+    /// if ref_id refers to the pending mode request, mark it as answered and return the index of the mode
+    fn answer(&mut self, ref_id: Option<u32>) -> Option<usize> {
+        match self.pending {
+            Some((idx, id)) if ref_id == Some(id) => {
+                self.pending = None;
+                Some(idx)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// the game the agent is currently playing
+struct CurrentGame {
+    // id of the state command that started it
+    id: u32,
+
+    // ids of the move commands sent for it, with the move (as the agent chose it) each one sent
+    moves: Vec<(u32, Move)>,
+
+    // moves the server answered with an error, i.e. considers illegal
+    rejected: Vec<Move>,
+
+    // whether the board the agent got was flipped, i.e. the agent's White is the server's Black
+    flipped: bool,
+
+    // board of the state command, to check the next state of the game against
+    board: Option<Board>,
+
+    // when the move has to be sent, as the time policy decided with the clock the server announced for this state
+    deadline: Option<Instant>,
+
+    // clock the server announced (time:clock) for the next state
+    next_clock: Option<Duration>,
+}
+
+impl CurrentGame {
+    fn new(id: u32) -> Self {
+        CurrentGame {
+            id,
+            moves: Vec::new(),
+            rejected: Vec::new(),
+            flipped: false,
+            board: None,
+            deadline: None,
+            next_clock: None,
+        }
+    }
+
+    fn refers_to(&self, ref_id: u32) -> bool {
+        ref_id == self.id || self.sent_move(ref_id).is_some()
+    }
+
+    /// the move sent with the move command move_id
+    fn sent_move(&self, move_id: u32) -> Option<Move> {
+        self.moves
+            .iter()
+            .find(|&&(id, _)| id == move_id)
+            .map(|&(_, move_)| move_)
+    }
+
+    /// move to send for the agent's best_move: best_move itself, unless the server rejected it already
+    ///
+    /// then the engine and the server disagree on what's legal (e.g. a different rules variant), and asking the agent
+    /// again would only give the same move. Instead take the first move legal for the engine that the server hasn't
+    /// rejected yet, if there is any.
+    fn playable_move(&self, best_move: Move) -> Option<Move> {
+        if !self.rejected.contains(&best_move) {
+            return Some(best_move);
+        }
+
+        self.board
+            .as_ref()?
+            .legal_moves(Player::White)
+            .into_iter()
+            .find(|move_| !self.rejected.contains(move_))
+    }
+
+    /// last board of this game if board continues it, but with a different number of seeds
+    ///
+    /// seeds are never created or removed, so this means that either parsing the board went wrong or the server and
+    /// the engine disagree on the rules. A start position or a state referring to another command begins a new game.
+    fn seed_mismatch(&self, ref_id: Option<u32>, board: &Board) -> Option<&Board> {
+        let last_board = self.board.as_ref()?;
+
+        let new_game = board.is_start_position()
+            || board.h() != last_board.h()
+            || matches!(ref_id, Some(ref_id) if !self.refers_to(ref_id));
+
+        (!new_game && board.total_seeds() != last_board.total_seeds()).then_some(last_board)
+    }
+}
+
+/// wins, losses and draws of the agent over the session, counting the games whose final state the server sent
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Scoreboard {
+    wins: u32,
+    losses: u32,
+    draws: u32,
+}
+
+impl Scoreboard {
+    /// count the game if it's over on board (as the agent got it), returns whether the agent won, lost or drew
+    fn record(&mut self, board: &Board) -> Option<&'static str> {
+        // the agent always plays the side it sees as White
+        let us = if board.flipped() { Player::Black } else { Player::White };
+
+        let outcome = match board.winner()? {
+            GameResult::Win(player) if player == us => {
+                self.wins += 1;
+                "won"
+            }
+            GameResult::Win(_) => {
+                self.losses += 1;
+                "lost"
+            }
+            GameResult::Draw => {
+                self.draws += 1;
+                "drew"
+            }
+        };
+
+        Some(outcome)
+    }
+}
+
+impl Display for Scoreboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}W/{}L/{}D", self.wins, self.losses, self.draws)
+    }
+}
+
+/// house number to send to the server for the agent's best move
+///
+/// agents always move for the side they see as White. The server expects a move of the side to move, which is White
+/// on the board as it was sent: if the agent got it flipped, its moves have to be flipped back, and anything that
+/// isn't a move of the side to move then is an error rather than a move for the wrong side.
+fn server_move_index(move_: Move, flipped: bool) -> Result<u8, String> {
+    let server_move = if flipped { move_.flip_player() } else { move_ };
+
+    if server_move.player() != Player::White {
+        return Err(format!(
+            "Agent chose move {move_} for the side not to move ({} on the server's board)",
+            server_move.player()
+        ));
+    }
+
+    Ok(server_move.to_server_index())
+}
+
+/// what a KGP error applies to, decided by the command it references
+#[derive(Debug, PartialEq, Eq)]
+enum ErrorScope {
+    // no reference, the connection as a whole is broken (e.g. unsupported protocol version): end the session
+    Connection,
+
+    // the current game's state: give up on that game, but keep the connection
+    Game,
+
+    // one of our moves in the current game, which the server considers illegal: send another one
+    Move(Move),
+
+    // any other command of ours, e.g. a set the server didn't understand: nothing to do besides logging it
+    Command,
+}
+
+fn error_scope(ref_id: Option<u32>, game: &CurrentGame) -> ErrorScope {
+    match ref_id {
+        None => ErrorScope::Connection,
+        Some(ref_id) => match game.sent_move(ref_id) {
+            Some(move_) => ErrorScope::Move(move_),
+            None if game.refers_to(ref_id) => ErrorScope::Game,
+            None => ErrorScope::Command,
+        },
+    }
+}
+
+/// handle a stop referencing ref_id: only stops the agent if it's for the current game
+fn stop_game(agent: &mut Box<dyn Agent>, game: &CurrentGame, ref_id: Option<u32>) {
+    match ref_id {
+        Some(ref_id) if ref_id != game.id => {
+            // stale stop for a game that was already replaced, e.g. after lag
+            warn!(
+                "Server told ID {ref_id} to stop, but current ID is {}, ignoring",
+                game.id
+            );
+        }
+        _ => {
+            debug!("{} stop", game.id);
+            agent.stop();
+        }
+    }
+}
+
+/*====================================================================================================================*/
+
+fn process_command(
+    conn: &mut Connection,
+    cmd: Command,
+    agent: &mut Box<dyn Agent>,
+    game: &mut CurrentGame,
+    mode_negotiation: &mut ModeNegotiation,
+    scoreboard: &mut Scoreboard,
+    time_policy: TimeAllocationPolicy,
+) -> Result<(), KgpError> {
     // active_agents: &mut HashMap<u32, (Box<dyn Agent>, Option<Move>)>
     // let new_agent = |board: Board| Box::new(MinimaxAgent::new(board, valuation::store_diff_valuation));
 
-    let cmd = match conn.read_command() {
-        Some(cmd) => cmd,
-        None => return,
-    };
-
     // println!("{:?}", cmd);
 
     match cmd {
-        Command::Kpg {
+        Command::Kgp {
             id,
             ref_id: _,
             major,
@@ -40,43 +272,46 @@ fn process_command(conn: &mut Connection, agent: &mut Box<dyn Agent>, cur_id: &m
             patch,
         } => {
             if major != 1 {
-                conn.write_command("error protocol not supported", id);
-                eprintln!("Server tried to use unsupported protocol {major}.{minor}.{patch}");
-                std::process::exit(1);
+                conn.write_command("error protocol not supported", id)?;
+                return Err(KgpError::Protocol(format!(
+                    "Server tried to use unsupported protocol {major}.{minor}.{patch}"
+                )));
             }
 
+            // newer minor versions only add commands, which get ignored as Command::Unknown
+            info!("Server uses protocol {major}.{minor}.{patch}");
+            conn.protocol_version = Some((major, minor, patch));
+
             let name = "Sauerkraut";
             // let authors = "Moritz Gmeiner";
             // let description = "Minimax with alpha-beta pruning";
 
             let token_path = std::env::var("TOKEN_PATH").unwrap_or_else(|_| "./TOKEN".to_owned());
 
-            let token = match std::fs::read(token_path) {
-                Ok(raw_content) => String::from_utf8(raw_content).unwrap(),
+            let token = match std::fs::read_to_string(token_path) {
+                Ok(token) => token,
                 Err(err) => {
                     if err.kind() == std::io::ErrorKind::NotFound {
-                        eprintln!("No TOKEN file found");
+                        warn!("No TOKEN file found");
                         "".to_owned()
                     } else {
-                        panic!("{}", err)
+                        return Err(err.into());
                     }
                 }
             };
 
             // send server name, authors and token
-            conn.write_command(&format!("set info:name {name}"), None);
-            println!("Setting name: {name}");
+            conn.write_command(&format!("set info:name {name}"), None)?;
+            info!("Setting name: {name}");
             // conn.write_command(&format!("set info:authors {}", authors), None);
             // conn.write_command(&format!("set info:description {}", description), None);
-            conn.write_command(&format!("set auth:token {token}"), None);
+            conn.write_command(&format!("set auth:token {token}"), None)?;
             // println!("Setting token: {}", token);
 
-            conn.write_command("mode freeplay", None);
-
-            println!("Selected mode: freeplay");
+            mode_negotiation.request(conn, 0)?;
         }
         Command::State { id, ref_id, board } => {
-            let id = id.expect("Server didn't attach id to state");
+            let id = id.ok_or_else(|| KgpError::UnexpectedId("Server didn't attach id to state".to_owned()))?;
 
             /* if unsafe { CTRLC_STATUS == CtrlCStatus::ExitAfterGame } && board.our_store < 5 && board.their_store < 5 {
                 // server trying to start second game
@@ -84,59 +319,151 @@ fn process_command(conn: &mut Connection, agent: &mut Box<dyn Agent>, cur_id: &m
                 std::process::exit(0);
             } */
 
-            println!("\n\n{board}\n");
+            if let Some((idx, _)) = mode_negotiation.pending.take() {
+                // servers don't have to acknowledge the mode, starting a game accepts it implicitly
+                info!("Server started a game in mode {}", mode_negotiation.modes[idx]);
+            }
+
+            info!("New state:\n\n{board}\n");
+
+            if let Some(ref_id) = ref_id.filter(|&ref_id| !game.refers_to(ref_id)) {
+                // e.g. the state of a new game, sent in reply to the last one: play it all the same
+                warn!(
+                    "Server referenced ID {ref_id} in a new state, but current ID is {}",
+                    game.id
+                );
+            }
 
-            if let Some(ref_id) = ref_id {
-                assert_eq!(
-                    ref_id, *cur_id,
-                    "Server referenced ID {ref_id}, but current ID is {cur_id}"
+            if let Some(last_board) = game.seed_mismatch(ref_id, &board) {
+                warn!(
+                    "Seed count changed from {} to {} in game {}, the board got parsed wrong or the server disagrees on the rules\n\nLast state:\n\n{last_board}\n\nNew state:\n\n{board}\n",
+                    last_board.total_seeds(),
+                    board.total_seeds(),
+                    game.id
                 );
             }
 
+            if agent.get_state() != AgentState::Waiting {
+                // the server moved on without stopping the last search
+                agent.stop();
+            }
+
             agent.update_board(&board);
-            *cur_id = id;
+
+            let clock = game.next_clock.take();
+            *game = CurrentGame::new(id);
+            game.flipped = board.flipped();
+            game.board = Some(board.clone());
+
+            if let Some(think_time) = time_policy.think_time(clock) {
+                debug!("Clock {clock:?}, thinking for {think_time:?} ({time_policy})");
+                game.deadline = Some(Instant::now() + think_time);
+            }
+
+            if let Some(outcome) = scoreboard.record(&board) {
+                // the agent won't start on it, so no move gets sent. The next game starts with a new state, which
+                // (like this one) replaces whatever the agent was doing
+                let (our_score, their_score) = board.final_scores();
+                info!("Game {id} is over, we {outcome} {our_score}:{their_score}. Session: {scoreboard}");
+            }
 
             agent.go();
-            println!("go");
-        }
-        Command::Stop { id: _id, ref_id } => {
-            let ref_id = ref_id.unwrap();
-            assert_eq!(
-                ref_id, *cur_id,
-                "Server told ID {ref_id} to stop, but current ID is {cur_id}"
-            );
-            // let (mut agent, best_move) = active_agents.remove(&ref_id).unwrap();
-            println!("{ref_id} stop");
-            agent.stop();
-        }
-        Command::Ok { .. } => {
-            println!("ok");
+            debug!("go");
         }
+        Command::Stop { id: _id, ref_id } => stop_game(agent, game, ref_id),
+        Command::Ok { id: _, ref_id } => match mode_negotiation.answer(ref_id) {
+            Some(idx) => info!("Server accepted mode {}", mode_negotiation.modes[idx]),
+            None => debug!("ok"),
+        },
         Command::Set {
             id: _id,
             ref_id: _ref_id,
             option,
             value,
         } => {
-            println!("server set {option} to {value}");
+            info!("server set {option} to {value}");
+
+            if option == "time:clock" {
+                match value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                {
+                    Some(clock) => game.next_clock = Some(clock),
+                    None => warn!("Ignoring invalid clock {value}"),
+                }
+            }
         }
-        Command::Error { id: _, ref_id: _, msg } => {
-            eprintln!("ERROR {msg}");
-            std::process::exit(1);
+        Command::Error { id: _, ref_id, msg } => {
+            if let Some(idx) = mode_negotiation.answer(ref_id) {
+                warn!("Server rejected mode {}: {msg}", mode_negotiation.modes[idx]);
+
+                if mode_negotiation.request(conn, idx + 1)? {
+                    return Ok(());
+                }
+
+                conn.write_command("goodbye", None)?;
+                return Err(KgpError::Protocol("Server rejected all requested modes".to_owned()));
+            }
+
+            match error_scope(ref_id, game) {
+                ErrorScope::Connection => {
+                    return Err(KgpError::Protocol(format!("Server sent error: {msg}")));
+                }
+                ErrorScope::Game => {
+                    // the server moves on to the next game (or sends goodbye) on its own, just stop working on this one
+                    warn!("Server sent error for game {}, dropping it: {msg}", game.id);
+                    agent.stop();
+                }
+                ErrorScope::Move(move_) => {
+                    // keep playing: the loop in kgp_connect sends a different move in place of this one
+                    let board = game.board.as_ref().map(|board| board.to_string()).unwrap_or_default();
+                    warn!(
+                        "Server rejected our move {move_} in game {}: {msg}\n\n{board}\n",
+                        game.id
+                    );
+                    game.rejected.push(move_);
+                }
+                ErrorScope::Command => {
+                    warn!("Server sent error for command {}: {msg}", ref_id.unwrap());
+                }
+            }
         }
         Command::Ping { id, ref_id: _, msg } => {
-            conn.write_command(&format!("pong {msg}"), id);
+            conn.write_command(&format!("pong {msg}"), id)?;
         }
         Command::Pong { .. } => { /* ignore */ }
         Command::Goodbye { .. } => {
-            std::process::exit(0);
+            info!("Server said goodbye");
+            conn.close();
+        }
+        Command::Unknown { cmd, args, .. } => {
+            let (major, minor, patch) = conn.protocol_version.unwrap_or_default();
+            warn!("Ignoring unknown command \"{cmd} {args}\" (protocol {major}.{minor}.{patch})");
         }
     }
+
+    Ok(())
 }
 
-#[allow(dead_code)]
-pub fn kgp_connect(conn: Connection) {
+/// play with agent on the server behind conn, requesting the first of modes the server accepts
+///
+/// the agent gets warmed up (see Agent::warmup) before the first command from the server is read. Once the time that
+/// time_policy allows for a state is up, the agent gets stopped and its best move so far is the one that was sent,
+/// without waiting for the server's stop.
+///
+/// returns once the server says goodbye or closes the connection (or a replayed session is over, see
+/// Connection::new_replay), or with an error once the session can't go on, e.g. because the connection failed or the
+/// server rejected all modes
+pub fn kgp_connect(
+    conn: Connection,
+    modes: Vec<Mode>,
+    agent: Box<dyn Agent>,
+    time_policy: TimeAllocationPolicy,
+) -> Result<(), KgpError> {
     let mut conn = conn;
+    let mut mode_negotiation = ModeNegotiation::new(modes);
+
     /* ctrlc::set_handler(|| unsafe {
         match CTRLC_STATUS {
             CtrlCStatus::Run => {
@@ -153,29 +480,444 @@ pub fn kgp_connect(conn: Connection) {
 
     // map of agents and their last best move
     // let mut active_agents: HashMap<u32, (Box<dyn Agent>, Option<Move>)> = HashMap::new();
-    let mut agent: Box<dyn Agent> = Box::new(MinimaxAgent::new(Board::new(8, 8)));
+    let mut agent = agent;
+    agent.warmup();
+
     let mut last_best_move = None;
-    let mut id = 0;
+    let mut game = CurrentGame::new(0);
+    let mut scoreboard = Scoreboard::default();
 
     loop {
-        process_command(&mut conn, &mut agent, &mut id);
+        // everything that arrived in one go, so a burst of commands doesn't wait a tick per command
+        for cmd in conn.read_commands()? {
+            let goodbye = matches!(cmd, Command::Goodbye { .. });
+
+            let processed = process_command(
+                &mut conn,
+                cmd,
+                &mut agent,
+                &mut game,
+                &mut mode_negotiation,
+                &mut scoreboard,
+                time_policy,
+            );
+
+            // the rest of the burst doesn't matter once the session is over. A server that hung up doesn't end it
+            // here: the lines it sent before are still in the burst, the check below ends it once they are handled.
+            if processed.is_err() || goodbye {
+                agent.stop();
+                return processed;
+            }
+        }
+
+        if conn.is_closed() {
+            agent.stop();
+            return Ok(());
+        }
+
+        if let Some(idle) = conn.idle_timed_out() {
+            // only warn: the server may just be waiting for an opponent, and there is no reconnecting (yet)
+            warn!("No command from the server for {idle:.0?}, the connection may be dead");
+        }
 
         // for (&id, (agent, last_best_move)) in active_agents.iter_mut() {
         if agent.get_state() == AgentState::Waiting {
             continue;
         }
 
-        let best_move = agent.get_current_best_move();
+        let agent_move = agent.get_current_best_move();
+
+        let best_move = match game.playable_move(agent_move) {
+            Some(best_move) => best_move,
+            None => {
+                error!("Server rejected all legal moves in game {}, giving up on it", game.id);
+                agent.stop();
+                continue;
+            }
+        };
+
+        if matches!(game.deadline, Some(deadline) if Instant::now() >= deadline) {
+            // the time the policy gave this state is up, the move the agent has now is its last one
+            info!("Time for game {} is up, stopping with {best_move}", game.id);
+            agent.stop();
+        }
 
         if Some(best_move) == last_best_move {
             continue;
         }
 
-        conn.write_command(&format!("move {}", best_move.house() + 1), Some(id));
+        if best_move != agent_move {
+            warn!("Sending {best_move} in place of the agent's move {agent_move}, which the server rejected");
+        }
 
         last_best_move = Some(best_move);
+
+        match server_move_index(best_move, game.flipped) {
+            Ok(house) => {
+                let move_id = conn.write_command(&format!("move {house}"), Some(game.id))?;
+                game.moves.push((move_id, best_move));
+            }
+            Err(err) => error!("Not sending move: {err}"),
+        }
         // }
 
         std::thread::sleep(Duration::from_millis(50));
     }
 }
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use super::{error_scope, kgp_connect, server_move_index, stop_game, CurrentGame, ErrorScope, Scoreboard};
+    use crate::agent::{Agent, AgentState, FirstMoveAgent, TimeAllocationPolicy};
+    use crate::kgp::{Connection, Mode};
+    use crate::{Board, Move, Player};
+
+    #[test]
+    fn test_server_move_index() {
+        // board as sent by the server: the agent's White is the side to move
+        assert_eq!(server_move_index(Move::new(0, Player::White), false), Ok(1));
+        assert_eq!(server_move_index(Move::new(5, Player::White), false), Ok(6));
+        assert!(server_move_index(Move::new(5, Player::Black), false).is_err());
+
+        // flipped board: the side to move is the agent's Black
+        assert_eq!(server_move_index(Move::new(2, Player::Black), true), Ok(3));
+        assert!(server_move_index(Move::new(2, Player::White), true).is_err());
+
+        // same house the agent sees on its board
+        let mut board = Board::from_kgp("<3, 0, 0, 1, 2, 3, 4, 5, 6>");
+        board.flip_board();
+        let move_ = Move::new(1, Player::Black);
+        assert_eq!(board.their_houses()[move_.house() as usize], 2);
+        assert_eq!(server_move_index(move_, board.flipped()), Ok(2));
+    }
+
+    #[test]
+    fn test_scoreboard() {
+        let mut scoreboard = Scoreboard::default();
+
+        // still running
+        assert_eq!(scoreboard.record(&Board::new(3, 3)), None);
+
+        // White (us) has no moves left, 7 seeds against 0 + 6
+        assert_eq!(
+            scoreboard.record(&Board::from_kgp("<3, 7, 0, 0, 0, 0, 1, 2, 3>")),
+            Some("won")
+        );
+        assert_eq!(
+            scoreboard.record(&Board::from_kgp("<3, 2, 4, 0, 0, 0, 1, 2, 3>")),
+            Some("lost")
+        );
+        assert_eq!(
+            scoreboard.record(&Board::from_kgp("<3, 6, 3, 0, 0, 0, 3, 0, 0>")),
+            Some("drew")
+        );
+
+        // a flipped board is still the agent's view
+        let mut board = Board::from_kgp("<3, 0, 7, 1, 2, 3, 0, 0, 0>");
+        board.flip_board();
+        assert_eq!(scoreboard.record(&board), Some("won"));
+
+        assert_eq!(scoreboard.to_string(), "2W/1L/1D");
+    }
+
+    #[test]
+    fn test_error_scope() {
+        let mut game = CurrentGame::new(4);
+        game.moves
+            .extend([(7, Move::new(0, Player::White)), (9, Move::new(2, Player::White))]);
+
+        assert_eq!(error_scope(None, &game), ErrorScope::Connection);
+        assert_eq!(error_scope(Some(4), &game), ErrorScope::Game);
+        assert_eq!(
+            error_scope(Some(9), &game),
+            ErrorScope::Move(Move::new(2, Player::White))
+        );
+        assert_eq!(error_scope(Some(3), &game), ErrorScope::Command);
+    }
+
+    #[test]
+    fn test_playable_move() {
+        let mut game = CurrentGame::new(4);
+        game.board = Some(Board::from_kgp("<3, 0, 0, 1, 0, 2, 1, 1, 1>"));
+
+        let first = Move::new(0, Player::White);
+        let last = Move::new(2, Player::White);
+
+        assert_eq!(game.playable_move(last), Some(last));
+
+        // the agent sticks to its move, so a rejected one gets replaced by the first move left
+        game.rejected.push(last);
+        assert_eq!(game.playable_move(last), Some(first));
+        assert_eq!(game.playable_move(first), Some(first));
+
+        game.rejected.push(first);
+        assert_eq!(game.playable_move(last), None);
+    }
+
+    #[test]
+    fn test_seed_mismatch() {
+        let mut game = CurrentGame::new(4);
+        game.moves.push((6, Move::new(0, Player::White)));
+
+        // nothing to compare with yet
+        assert!(game
+            .seed_mismatch(None, &Board::from_kgp("<3, 1, 0, 3, 3, 3, 3, 3, 3>"))
+            .is_none());
+
+        game.board = Some(Board::from_kgp("<3, 0, 0, 3, 3, 3, 3, 3, 3>"));
+
+        // seeds moved around, but none went missing
+        let next = Board::from_kgp("<3, 1, 0, 3, 0, 4, 4, 3, 3>");
+        assert!(game.seed_mismatch(None, &next).is_none());
+        assert!(game.seed_mismatch(Some(6), &next).is_none());
+
+        // a seed too many
+        let broken = Board::from_kgp("<3, 1, 0, 3, 1, 4, 4, 3, 3>");
+        assert_eq!(game.seed_mismatch(None, &broken).map(Board::total_seeds), Some(18));
+        assert!(game.seed_mismatch(Some(4), &broken).is_some());
+
+        // new games: start positions, different boards and states for other commands
+        assert!(game.seed_mismatch(None, &Board::new(3, 4)).is_none());
+        assert!(game
+            .seed_mismatch(None, &Board::from_kgp("<4, 1, 0, 3, 0, 4, 4, 3, 3, 0, 0>"))
+            .is_none());
+        assert!(game.seed_mismatch(Some(3), &broken).is_none());
+    }
+
+    #[test]
+    fn test_stale_stop() {
+        let mut agent: Box<dyn Agent> = Box::new(FirstMoveAgent::new(4, 4));
+        agent.update_board(&Board::new(4, 4));
+        agent.go();
+
+        let game = CurrentGame::new(5);
+
+        stop_game(&mut agent, &game, Some(3));
+        assert_eq!(agent.get_state(), AgentState::Go);
+
+        stop_game(&mut agent, &game, Some(5));
+        assert_eq!(agent.get_state(), AgentState::Waiting);
+
+        // without a reference, stop whatever is running
+        agent.go();
+        stop_game(&mut agent, &game, None);
+        assert_eq!(agent.get_state(), AgentState::Waiting);
+    }
+
+    #[test]
+    fn test_replay_session() {
+        // Write into a buffer the test can still read after handing it to the connection
+        #[derive(Clone, Default)]
+        struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for SharedBuf {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let session = "4 state <3,0,0,3,3,3,3,3,3>\n6@4 stop\n\n8 state <3,1,0,0,4,4,3,3,3>\n";
+
+        let output = SharedBuf::default();
+        let conn = Connection::new_replay(session, Box::new(output.clone()));
+        kgp_connect(
+            conn,
+            vec![Mode::Freeplay],
+            Box::new(FirstMoveAgent::new(3, 3)),
+            TimeAllocationPolicy::new(),
+        )
+        .unwrap();
+
+        // the first move in each state, sent in reply to it
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output, "1@4 move 1\n3@8 move 2\n");
+    }
+
+    #[test]
+    fn test_session_errors() {
+        use crate::kgp::KgpError;
+
+        let connect = |session: &str| {
+            let conn = Connection::new_replay(session, Box::new(std::io::sink()));
+            kgp_connect(
+                conn,
+                vec![Mode::Freeplay],
+                Box::new(FirstMoveAgent::new(3, 3)),
+                TimeAllocationPolicy::new(),
+            )
+        };
+
+        assert!(matches!(connect("1 kgp 2 0 0\n"), Err(KgpError::Protocol(_))));
+        assert!(matches!(
+            connect("state <3,0,0,3,3,3,3,3,3>\n"),
+            Err(KgpError::UnexpectedId(_))
+        ));
+        assert!(matches!(connect("4 state <3,0,0,3,3>\n"), Err(KgpError::Parse(_))));
+        assert!(matches!(
+            connect("3 error server shutting down\n"),
+            Err(KgpError::Protocol(_))
+        ));
+
+        // goodbye ends the session, whatever comes after it
+        assert!(connect("1 goodbye\n3 kgp 2 0 0\n").is_ok());
+    }
+
+    /// agent that plays the first legal move and searches until it's stopped, noting the calls that matter here
+    struct LoggingAgent {
+        state: AgentState,
+        board: Board,
+        calls: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl LoggingAgent {
+        fn new(calls: &Arc<Mutex<Vec<&'static str>>>) -> Self {
+            LoggingAgent {
+                state: AgentState::Waiting,
+                board: Board::new(3, 3),
+                calls: Arc::clone(calls),
+            }
+        }
+    }
+
+    impl Agent for LoggingAgent {
+        fn update_board(&mut self, board: &Board) {
+            self.board = board.clone();
+        }
+
+        fn get_current_best_move(&mut self) -> Move {
+            self.board.legal_moves(Player::White)[0]
+        }
+
+        fn get_state(&self) -> AgentState {
+            self.state
+        }
+
+        fn go(&mut self) {
+            self.calls.lock().unwrap().push("go");
+            self.state = AgentState::Go;
+        }
+
+        fn stop(&mut self) {
+            self.calls.lock().unwrap().push("stop");
+            self.state = AgentState::Waiting;
+        }
+
+        fn ponder(&mut self) {}
+
+        fn warmup(&mut self) {
+            self.calls.lock().unwrap().push("warmup");
+        }
+    }
+
+    #[test]
+    fn test_warmup() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        let session = "4 state <3,0,0,3,3,3,3,3,3>\n6@4 stop\n8 state <3,1,0,0,4,4,3,3,3>\n";
+        let conn = Connection::new_replay(session, Box::new(std::io::sink()));
+        kgp_connect(
+            conn,
+            vec![Mode::Freeplay],
+            Box::new(LoggingAgent::new(&calls)),
+            TimeAllocationPolicy::new(),
+        )
+        .unwrap();
+
+        // once, before the first go
+        assert_eq!(*calls.lock().unwrap(), ["warmup", "go", "stop", "go", "stop"]);
+    }
+
+    #[test]
+    fn test_last_burst() {
+        use std::net::TcpListener;
+
+        // the server sends its last lines and hangs up before the client reads any of them, so the end of the
+        // connection shows up while the lines are still buffered
+        let play = |session: &'static str| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let conn = Connection::new_tcpstream(&listener.local_addr().unwrap().to_string(), true).unwrap();
+
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(session.as_bytes()).unwrap();
+            drop(stream);
+
+            let calls = Arc::new(Mutex::new(Vec::new()));
+            kgp_connect(
+                conn,
+                vec![Mode::Freeplay],
+                Box::new(LoggingAgent::new(&calls)),
+                TimeAllocationPolicy::new(),
+            )
+            .unwrap();
+
+            let calls = calls.lock().unwrap().clone();
+            calls
+        };
+
+        // every state gets searched and stopped again, by the server or once the connection is gone
+        let session = "4 state <3,0,0,3,3,3,3,3,3>\n6@4 stop\n8 state <3,1,0,0,4,4,3,3,3>\n";
+        assert_eq!(play(session), ["warmup", "go", "stop", "go", "stop"]);
+
+        // a goodbye after the last state still gets handled, so what comes after it doesn't
+        let session = "4 state <3,0,0,3,3,3,3,3,3>\n6@4 stop\n8 state <3,1,0,0,4,4,3,3,3>\n10 goodbye\n\
+                       12 state <3,1,0,0,4,4,3,3,3>\n";
+        assert_eq!(play(session), ["warmup", "go", "stop", "go", "stop"]);
+    }
+
+    #[test]
+    fn test_time_policy() {
+        use std::io::{BufRead, BufReader};
+        use std::net::TcpListener;
+
+        // the server announces the clock, but never stops the agent itself
+        let play = |clock: u32, time_policy: TimeAllocationPolicy| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let conn = Connection::new_tcpstream(&listener.local_addr().unwrap().to_string(), true).unwrap();
+
+            let server = std::thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let state = format!("2 set time:clock {clock}\n4 state <3,0,0,3,3,3,3,3,3>\n");
+                stream.write_all(state.as_bytes()).unwrap();
+
+                let mut reply = String::new();
+                BufReader::new(stream.try_clone().unwrap())
+                    .read_line(&mut reply)
+                    .unwrap();
+                stream.write_all(b"6 goodbye\n").unwrap();
+
+                reply
+            });
+
+            let calls = Arc::new(Mutex::new(Vec::new()));
+            kgp_connect(
+                conn,
+                vec![Mode::Freeplay],
+                Box::new(LoggingAgent::new(&calls)),
+                time_policy,
+            )
+            .unwrap();
+
+            let calls = calls.lock().unwrap().clone();
+            (server.join().unwrap(), calls)
+        };
+
+        // out of time right away: the agent is stopped with the move it has, before the goodbye stops it again
+        let (reply, calls) = play(0, TimeAllocationPolicy::new());
+        assert_eq!(reply.trim(), "1@4 move 1");
+        assert_eq!(calls, ["warmup", "go", "stop", "stop"]);
+
+        // time left: only the goodbye stops it
+        let (reply, calls) = play(600, TimeAllocationPolicy::Fixed(std::time::Duration::from_secs(60)));
+        assert_eq!(reply.trim(), "1@4 move 1");
+        assert_eq!(calls, ["warmup", "go", "stop"]);
+    }
+}
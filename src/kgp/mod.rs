@@ -1,7 +1,11 @@
 mod commands;
+mod error;
 mod main;
+mod mode;
 mod network;
 
 pub use commands::Command;
+pub use error::KgpError;
 pub use main::kgp_connect;
-pub use network::Connection;
+pub use mode::{parse_modes, Mode};
+pub use network::{Connection, DEFAULT_CONNECT_ATTEMPTS, DEFAULT_CONNECT_BACKOFF, DEFAULT_IDLE_TIMEOUT};
@@ -0,0 +1,62 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// game mode requested from the server after the kgp handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// play games as long as the connection stays open
+    Freeplay,
+    /// play a single game, then disconnect
+    Simple,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "freeplay" => Ok(Mode::Freeplay),
+            "simple" => Ok(Mode::Simple),
+            _ => Err(format!("Unknown mode \"{s}\", expected freeplay or simple")),
+        }
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mode::Freeplay => write!(f, "freeplay"),
+            Mode::Simple => write!(f, "simple"),
+        }
+    }
+}
+
+/// parse a comma separated list of modes, e.g. "simple,freeplay"
+pub fn parse_modes(s: &str) -> Result<Vec<Mode>, String> {
+    if s.trim().is_empty() {
+        return Err("No mode given".to_owned());
+    }
+
+    s.split(',').map(|mode| mode.trim().parse()).collect()
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_modes, Mode};
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!(parse_modes("freeplay"), Ok(vec![Mode::Freeplay]));
+        assert_eq!(parse_modes("simple, freeplay"), Ok(vec![Mode::Simple, Mode::Freeplay]));
+
+        assert_eq!(parse_modes(""), Err("No mode given".to_owned()));
+        assert_eq!(parse_modes("  "), Err("No mode given".to_owned()));
+        assert!(parse_modes("simple,tournament").is_err());
+
+        for mode in [Mode::Freeplay, Mode::Simple] {
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+    }
+}
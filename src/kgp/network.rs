@@ -1,21 +1,53 @@
+use std::collections::VecDeque;
 use std::io::{Read, Write};
 use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use log::{debug, info, trace, warn};
 
 // use tungstenite::stream::MaybeTlsStream;
 // use tungstenite::{connect, WebSocket};
 
-use super::Command;
+use super::{Command, KgpError};
+
+/// how long the server may stay silent before the client warns about it, see Connection::idle_timed_out
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// how often the client tries to reach the server and how long it waits after the first failed attempt, see
+/// Connection::connect_with_retry
+pub const DEFAULT_CONNECT_ATTEMPTS: u32 = 5;
+pub const DEFAULT_CONNECT_BACKOFF: Duration = Duration::from_millis(500);
 
-#[derive(Debug)]
 enum Stream {
     // Websocket(WebSocket<MaybeTlsStream<TcpStream>>),
-    TcpStream { stream: TcpStream, buf: String },
+    TcpStream {
+        stream: TcpStream,
+        buf: String,
+    },
+    /// lines of a recorded session instead of a server, what the client sends goes to output
+    Replay {
+        lines: VecDeque<String>,
+        output: Box<dyn Write + Send>,
+    },
 }
 
 pub struct Connection {
     stream: Stream,
 
     next_id: u32,
+
+    /// (major, minor, patch) version of KGP the server announced, once it did
+    pub protocol_version: Option<(u8, u8, u8)>,
+
+    /// silence from the server that is worth a warning, None to never warn
+    pub idle_timeout: Option<Duration>,
+
+    // when the last complete command arrived (or the connection was made) and how often idle_timed_out fired since
+    last_command: Instant,
+    idle_warnings: u32,
+
+    // the server closed the connection or the replayed session ran out of lines
+    closed: bool,
 }
 
 impl Connection {
@@ -42,22 +74,96 @@ impl Connection {
         }
     } */
 
-    #[allow(dead_code)]
-    pub fn new_tcpstream(url: &str) -> Result<Self, std::io::Error> {
-        TcpStream::connect(url).map(|stream| {
-            stream.set_nonblocking(true).unwrap();
+    /// connect to a KGP server over TCP
+    ///
+    /// nodelay disables Nagle's algorithm on the socket. KGP is a request/response protocol made of tiny messages:
+    /// with Nagle enabled, a `move` reply can sit in the kernel buffer waiting for more data to coalesce with (or for
+    /// the ACK of the previous segment), which costs precious milliseconds under a tight clock. There is basically no
+    /// reason to leave it on, but the option is kept for debugging.
+    pub fn new_tcpstream(url: &str, nodelay: bool) -> Result<Self, KgpError> {
+        let stream = TcpStream::connect(url)?;
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(nodelay)?;
 
-            let stream = Stream::TcpStream {
-                stream,
-                buf: String::new(),
-            };
+        let stream = Stream::TcpStream {
+            stream,
+            buf: String::new(),
+        };
 
-            Connection { stream, next_id: 1 }
+        Ok(Connection {
+            stream,
+            next_id: 1,
+            protocol_version: None,
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            last_command: Instant::now(),
+            idle_warnings: 0,
+            closed: false,
         })
     }
 
-    fn read(&mut self) -> Option<String> {
-        match self.stream {
+    /// connect like new_tcpstream, but try up to attempts times, waiting backoff after the first failed attempt and
+    /// twice as long after every one after that
+    ///
+    /// for clients that start at the same time as the server, which may not be listening yet. Each failed attempt gets
+    /// logged, the error after the last one says how many there were.
+    pub fn connect_with_retry(url: &str, nodelay: bool, attempts: u32, backoff: Duration) -> Result<Self, KgpError> {
+        let attempts = attempts.max(1);
+        let mut backoff = backoff;
+        let mut attempt = 1;
+
+        loop {
+            match Connection::new_tcpstream(url, nodelay) {
+                Err(KgpError::Io(err)) if attempt < attempts => {
+                    warn!("Connecting to {url} failed (attempt {attempt}/{attempts}): {err}, retrying in {backoff:?}");
+                    std::thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                    attempt += 1;
+                }
+                Err(KgpError::Io(err)) if attempts > 1 => {
+                    let msg = format!("gave up after {attempts} attempts, last error: {err}");
+                    return Err(KgpError::Io(std::io::Error::new(err.kind(), msg)));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// play back the commands of a recorded session, one per line, as if a server had sent them
+    ///
+    /// meant for reproducing the client's decisions offline: every command the client sends in reply is written to
+    /// output, one per line, to diff against what it sent in the live session. Empty lines get skipped, and once all
+    /// lines are read the connection counts as closed, which ends kgp_connect. The replay doesn't wait for anything,
+    /// so the agent only gets the time kgp_connect spends per command to search.
+    pub fn new_replay(session: &str, output: Box<dyn Write + Send>) -> Self {
+        let lines = session
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| format!("{line}\n"))
+            .collect();
+
+        Connection {
+            stream: Stream::Replay { lines, output },
+            next_id: 1,
+            protocol_version: None,
+            idle_timeout: None,
+            last_command: Instant::now(),
+            idle_warnings: 0,
+            closed: false,
+        }
+    }
+
+    /// whether there is nothing left to read, see new_tcpstream and new_replay
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// stop reading, e.g. after the server said goodbye, which ends kgp_connect
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    fn read(&mut self) -> Result<Option<String>, KgpError> {
+        let msg = match self.stream {
             /* Stream::Websocket(ref mut websocket) => match websocket.read_message() {
                 Ok(msg) => Some(msg.into_text().unwrap()),
                 Err(tungstenite::Error::Io(err)) if err.kind() == std::io::ErrorKind::WouldBlock => None,
@@ -72,27 +178,29 @@ impl Connection {
                 match stream.read(&mut read_buf) {
                     Ok(len) if len > 0 => {
                         // Some(std::str::from_utf8(&read_buf[0..len]).unwrap().to_owned())
-                        *buf += std::str::from_utf8(&read_buf[0..len]).unwrap();
+                        *buf += std::str::from_utf8(&read_buf[0..len])
+                            .map_err(|err| KgpError::Parse(format!("Server sent invalid UTF-8: {err}")))?;
 
-                        println!("New buf: \"{buf}\"");
+                        trace!("New buf: \"{buf}\"");
                     }
                     Ok(0) => {
-                        println!("Connection closed, exiting");
-                        std::process::exit(0);
+                        // lines that arrived before the server hung up still get handed out below
+                        if !self.closed {
+                            info!("Connection closed, exiting");
+                            self.closed = true;
+                        }
                     }
                     Ok(_) => unreachable!(),
                     Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
-                    Err(err) => {
-                        panic!("Error while reading from TcpStream: {err}");
-                    }
+                    Err(err) => return Err(err.into()),
                 };
 
                 if let Some(idx) = buf.find('\n') {
                     let buf_rest = buf.split_off(idx + 1);
                     let msg = std::mem::replace(buf, buf_rest);
 
-                    println!("Split \"{msg}\" from buf");
-                    println!("Buf contains \"{buf}\"");
+                    trace!("Split \"{msg}\" from buf");
+                    trace!("Buf contains \"{buf}\"");
 
                     if !msg.is_empty() {
                         Some(msg)
@@ -103,46 +211,214 @@ impl Connection {
                     None
                 }
             }
-        }
-        .map(|msg| {
-            #[cfg(debug_assertions)]
-            {
-                println!("< {msg}");
+            Stream::Replay { ref mut lines, .. } => {
+                let line = lines.pop_front();
+
+                if line.is_none() && !self.closed {
+                    info!("Replayed session is over, exiting");
+                    self.closed = true;
+                }
+
+                line
             }
-            msg
-        })
+        }
+        .inspect(|msg| debug!("< {}", msg.trim_end()));
+
+        Ok(msg)
     }
 
-    fn write(&mut self, msg: String) {
-        #[cfg(debug_assertions)]
-        {
-            println!("> {msg}");
-        }
+    fn write(&mut self, msg: String) -> Result<(), KgpError> {
+        debug!("> {}", msg.trim_end());
 
         match self.stream {
             // Stream::Websocket(ref mut websocket) => websocket.write_message(msg.into()).unwrap(),
             Stream::TcpStream { ref mut stream, buf: _ } => {
-                stream.write_all(msg.as_bytes()).unwrap();
+                stream.write_all(msg.as_bytes())?;
+                // TcpStream itself is unbuffered, but flush anyways so a buffered stream can be dropped in safely
+                stream.flush()?;
+            }
+            Stream::Replay { ref mut output, .. } => {
+                writeln!(output, "{}", msg.trim_end())?;
+                output.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// next complete command from the server, None if there is none (yet)
+    pub fn read_command(&mut self) -> Result<Option<Command>, KgpError> {
+        let cmd = self.read()?.map(|msg| msg.parse()).transpose()?;
+
+        if cmd.is_some() {
+            self.last_command = Instant::now();
+            self.idle_warnings = 0;
+        }
+
+        Ok(cmd)
+    }
+
+    /// all complete commands that have arrived, so a burst of them gets handled at once instead of one per poll
+    ///
+    /// an incomplete last line stays buffered until the rest of it arrives. A replayed session has no record of which
+    /// lines arrived together, so it still hands out one command per call.
+    pub fn read_commands(&mut self) -> Result<Vec<Command>, KgpError> {
+        let mut cmds = Vec::new();
+
+        while let Some(cmd) = self.read_command()? {
+            cmds.push(cmd);
+
+            if let Stream::Replay { .. } = self.stream {
+                break;
             }
         }
+
+        Ok(cmds)
     }
 
-    pub fn read_command(&mut self) -> Option<Command> {
-        self.read().map(|msg| msg.parse().unwrap())
+    /// time since the last command, once for every idle_timeout the server stays silent
+    ///
+    /// the read loop polls this to notice a server that went silent, e.g. after a network failure: without it, reading
+    /// from the non-blocking socket just keeps coming up empty
+    pub fn idle_timed_out(&mut self) -> Option<Duration> {
+        let idle = self.last_command.elapsed();
+
+        if idle < self.idle_timeout? * (self.idle_warnings + 1) {
+            return None;
+        }
+
+        self.idle_warnings += 1;
+
+        Some(idle)
     }
 
-    pub fn write_command(&mut self, cmd: &str, ref_id: Option<u32>) {
+    /// send cmd (as a reply to ref_id, if given) and return the id it was sent with
+    pub fn write_command(&mut self, cmd: &str, ref_id: Option<u32>) -> Result<u32, KgpError> {
+        let id = self.next_id;
+
         let mut msg = if let Some(id_ref) = ref_id {
-            format!("{}@{} ", self.next_id, id_ref)
+            format!("{id}@{id_ref} ")
         } else {
-            format!("{} ", self.next_id)
+            format!("{id} ")
         };
 
         msg += cmd;
         msg += "\r\n";
 
-        self.write(msg);
+        self.write(msg)?;
 
         self.next_id += 2;
+
+        Ok(id)
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use super::Connection;
+    use crate::kgp::Command;
+    use crate::kgp::KgpError;
+
+    #[test]
+    fn test_idle_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut conn = Connection::new_tcpstream(&listener.local_addr().unwrap().to_string(), true).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        conn.idle_timeout = Some(Duration::from_millis(50));
+        assert_eq!(conn.idle_timed_out(), None);
+
+        // fires once per timeout
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(conn.idle_timed_out().unwrap() >= Duration::from_millis(50));
+        assert_eq!(conn.idle_timed_out(), None);
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(conn.idle_timed_out().unwrap() >= Duration::from_millis(100));
+
+        // a command resets it
+        server.write_all(b"1 ping hello\r\n").unwrap();
+        while conn.read_command().unwrap().is_none() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(conn.idle_timed_out(), None);
+
+        conn.idle_timeout = None;
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(conn.idle_timed_out(), None);
+    }
+
+    #[test]
+    fn test_connect_with_retry() {
+        // nothing listens on a port that was just given up
+        let url = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        let Err(KgpError::Io(err)) = Connection::connect_with_retry(&url, true, 3, Duration::from_millis(1)) else {
+            panic!("connected to {url} without a server");
+        };
+        assert!(err.to_string().contains("after 3 attempts"), "{err}");
+
+        // a server that comes up while the client is still trying
+        let server = std::thread::spawn({
+            let url = url.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(50));
+                let listener = TcpListener::bind(&url).unwrap();
+                listener.accept().unwrap();
+            }
+        });
+
+        Connection::connect_with_retry(&url, true, 10, Duration::from_millis(10)).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_read_commands() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut conn = Connection::new_tcpstream(&listener.local_addr().unwrap().to_string(), true).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        assert!(conn.read_commands().unwrap().is_empty());
+
+        // three commands in one write, the third one incomplete
+        server.write_all(b"1 kgp 1 0 0\r\n3 ping a\r\n5@3 st").unwrap();
+
+        let mut cmds = Vec::new();
+        while cmds.len() < 2 {
+            cmds.extend(conn.read_commands().unwrap());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(cmds.len(), 2);
+        assert!(matches!(cmds[0], Command::Kgp { id: Some(1), .. }));
+        assert!(matches!(cmds[1], Command::Ping { id: Some(3), .. }));
+
+        // the rest of the last line arrives, and the server hangs up right after it
+        server.write_all(b"op\r\n").unwrap();
+        drop(server);
+
+        let mut cmds = Vec::new();
+        while !conn.is_closed() {
+            cmds.extend(conn.read_commands().unwrap());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        cmds.extend(conn.read_commands().unwrap());
+        assert_eq!(cmds.len(), 1);
+        assert!(matches!(
+            cmds[0],
+            Command::Stop {
+                id: Some(5),
+                ref_id: Some(3)
+            }
+        ));
     }
 }
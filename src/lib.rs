@@ -0,0 +1,35 @@
+//! Kalah engine: board representation, searches, agents and a client for the Kalah Game Protocol (KGP)
+//!
+//! the public API, roughly from low to high level:
+//!
+//! - [`Board`], [`Move`], [`Player`] and [`GameResult`] at the crate root, together with the valuations, evaluators
+//!   and helpers around them in [`kalah`]
+//! - the searches, each with an agent driving it: [`pvs::PVSAgent`], [`minimax::MinimaxAgent`] and the tuned
+//!   [`tournament::MinimaxAgent`], plus the slow but simple [`minimax_reference::MinimaxAgent`] to check them against
+//! - the [`agent::Agent`] trait all agents implement, simple agents (random, first move, human) and wrappers for
+//!   recording, replaying and fixed openings in [`agent`]
+//! - [`harness`] to play games and tournaments between agents, and the checks behind `kalah --selftest`
+//! - [`kgp`] to connect an agent to a KGP server, see [`kgp::Connection`] and [`kgp::kgp_connect`]
+//! - [`tablebase::solve`] to solve small positions exactly
+//! - [`transposition::EvalCache`], which PVS can use to skip evaluating positions it has seen before, and
+//!   [`transposition::SharedTable`], which PVS can use as its transposition table
+//! - building blocks that no search uses yet: [`tablebase::Tablebase`]
+//!
+//! everything else (search workers, protocol parsing details, ...) is internal
+
+pub mod agent;
+pub mod harness;
+pub mod kalah;
+pub mod kgp;
+pub mod minimax;
+pub mod minimax_reference;
+pub mod pvs;
+pub mod tablebase;
+pub mod tournament;
+pub mod transposition;
+pub mod util;
+
+#[cfg(test)]
+mod symmetry_tests;
+
+pub use kalah::{Board, GameResult, House, Move, Player};
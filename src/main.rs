@@ -1,356 +1,696 @@
-mod agent;
-mod kalah;
-mod kgp;
-mod minimax;
-mod minimax_reference;
-mod pvs;
-mod tournament;
-mod util;
+use std::time::Duration;
 
-pub use kalah::{Board, House, Move, Player};
+use log::{error, info};
 
-use crate::kgp::Connection;
+use kalah::agent::{
+    agent_by_names, Agent, OpeningAgent, RecordingAgent, ReplayAgent, TimeAllocationPolicy, AGENT_NAMES,
+};
+use kalah::harness::BenchEvalConfig;
+use kalah::kalah::valuation::valuation_by_name;
+use kalah::kgp::{
+    parse_modes, Connection, Mode, DEFAULT_CONNECT_ATTEMPTS, DEFAULT_CONNECT_BACKOFF, DEFAULT_IDLE_TIMEOUT,
+};
+use kalah::{harness, tablebase, tournament, Board, Move, Player};
 
 /*====================================================================================================================*/
 
-pub const LOG_STATS: bool = true;
-
-/*====================================================================================================================*/
-
-/* fn single_ply<const DO_LOGGING: bool>(
-    board: &mut Board,
-    playing_agent: &mut impl Agent,
-    player: Player,
-    thinking_time: Duration,
-) -> Player {
-    if DO_LOGGING {
-        println!("{board}\n");
-    }
-
-    match player {
-        Player::White => playing_agent.update_board(board),
-        Player::Black => {
-            board.flip_board();
-            playing_agent.update_board(board);
-            board.flip_board();
-        }
-    };
-
-    let start_time = std::time::Instant::now();
+/* pub fn compare_agents(board: Board, mut agent1: impl Agent, mut agent2: impl Agent) {
+    println!("{board}\n\n");
 
-    playing_agent.go();
+    agent1.update_board(&board);
+    agent1.go();
 
-    let mut player_move = playing_agent.get_current_best_move();
+    agent2.update_board(&board);
+    agent2.go();
 
-    while playing_agent.get_state() == AgentState::Go
-        && (playing_agent.is_reference() || start_time.elapsed() < thinking_time)
-    {
-        player_move = playing_agent.get_current_best_move();
+    // std::thread::sleep(Duration::from_secs(1));
 
+    while agent1.get_state() == AgentState::Go {
+        let _ = agent1.get_current_best_move();
         std::thread::sleep(Duration::from_millis(50));
     }
 
-    playing_agent.stop();
-
-    if player == Player::Black {
-        // Black thinks they're White
-        player_move = player_move.flip_player();
-    }
-
-    let valid_moves = board.legal_moves(player);
-
-    if !valid_moves.iter().any(|valid_move| *valid_move == player_move) {
-        panic!("Invalid move {player_move:?} by Player {player} in position \n{board}\n\n");
-        /* player_move = *valid_moves.choose(&mut thread_rng()).unwrap();
-        println!("Invalid move, using {} instead", player_move); */
+    while agent2.get_state() == AgentState::Go {
+        let _ = agent2.get_current_best_move();
+        std::thread::sleep(Duration::from_millis(50));
     }
+} */
 
-    if DO_LOGGING {
-        println!("{player}: playing move {player_move}");
-    }
+/* fn main() {
+    let h = 8;
+    let s = 8;
 
-    let moves_again = board.apply_move(player_move);
+    // let white_agent = agent::RandomAgent::new(h, s);
+    // let white_agent = minimax::MinimaxAgent::new(Board::new(h, s), kalah::valuation::store_diff_valuation);
+    let white_agent = pvs::PVSAgent::new(Board::new(h, s), kalah::valuation::store_diff_valuation);
+    // let white_agent = agent::FirstMoveAgent::new(h, s);
 
-    if DO_LOGGING {
-        println!();
-    }
+    // let black_agent = agent::RandomAgent::new(h, s);
+    let black_agent = minimax_reference::MinimaxAgent::new(Board::new(h, s), 6, kalah::valuation::store_diff_valuation);
+    // let black_agent = agent::FirstMoveAgent::new(h, s);
 
-    if moves_again {
-        player
-    } else {
-        !player
-    }
-}
+    harness::play_game(h, s, white_agent, black_agent);
 
-fn game_loop<const DO_LOGGING: bool>(
-    board: Board,
-    white_agent: impl Agent,
-    black_agent: impl Agent,
-    thinking_time: Duration,
-) -> Board {
-    use Player::{Black, White};
+    // let mut board = Board::new(h, s);
+    // advance_random(h, s, &mut board, 2 * h as usize);
+    // compare_agents(board, white_agent, black_agent);
+} */
 
-    let mut current_player = if !board.flipped() { White } else { Black };
+/* fn main() {
+    let h = 8;
+    let s = 8;
 
-    let mut board = board;
-    let mut white_agent = white_agent;
-    let mut black_agent = black_agent;
+    let agent1_builder: AgentBuilder =
+        &|| Box::new(minimax::MinimaxAgent::new(Board::new(h, s), kalah::valuation::store_diff_valuation));
 
-    loop {
-        current_player = match current_player {
-            White => single_ply::<DO_LOGGING>(&mut board, &mut white_agent, White, thinking_time),
-            Black => single_ply::<DO_LOGGING>(&mut board, &mut black_agent, Black, thinking_time),
-        };
+    // let agent2_builder: AgentBuilder = &|| Box::new(agent::RandomAgent::new(h, s));
+    // let agent2_builder: AgentBuilder = &|| {
+    //     Box::new(minimax_reference::MinimaxAgent::new(Board::new(h, s), 6, kalah::valuation::store_diff_valuation))
+    // };
+    let agent2_builder: AgentBuilder =
+        &|| Box::new(pvs::PVSAgent::new(Board::new(h, s), kalah::valuation::store_diff_valuation));
 
-        if !board.has_legal_move() {
-            break;
-        }
-    }
+    harness::run_match(&TournamentConfig::new(h, s), agent1_builder, agent2_builder, 4 * 8);
+} */
 
-    board
-}
+/* fn main() {
+    let url = "wss://kalah.kwarc.info/socket";
 
-#[allow(dead_code)]
-pub fn play_game<WhiteAgent, BlackAgent>(h: u8, s: u16, white_agent: WhiteAgent, black_agent: BlackAgent)
-where
-    WhiteAgent: Agent,
-    BlackAgent: Agent,
-{
-    let board = Board::new(h, s);
-    let thinking_time = Duration::from_secs(3);
+    println!("Connecting to game server at {url}...");
 
-    let board = game_loop::<true>(board, white_agent, black_agent, thinking_time);
+    let conn = Connection::new_websocket(url).expect("Failed to connect");
 
-    println!("\nFinal board:\n\n{board}\n");
+    println!("Connected to game server {url}");
 
-    match board.our_store.cmp(&board.their_store) {
-        std::cmp::Ordering::Less => println!("Black won."),
-        std::cmp::Ordering::Equal => println!("Draw."),
-        std::cmp::Ordering::Greater => println!("White won."),
-    }
+    crate::kgp::kgp_connect(conn, vec![Mode::Freeplay]);
 } */
 
-/* #[allow(dead_code)]
-pub fn test_agents<Agent1, Agent2>(
-    h: u8,
-    s: u16,
-    agent1_builder: &dyn Fn() -> Agent1,
-    agent2_builder: &dyn Fn() -> Agent2,
-    num_runs: usize,
-) where
-    Agent1: Agent + Send + 'static,
-    Agent2: Agent + Send + 'static,
-{
-    assert_eq!(num_runs % 2, 0, "num_runs must be divisible by 2");
-
-    // let num_workers = 8;
-    let num_workers = num_cpus::get() / 2;
-
-    let thinking_time = Duration::from_secs(2);
-
-    println!("Running with {num_workers} workers");
+const USAGE: &str = "\
+Usage: kalah [URL | --session FILE] [--mode MODE[,MODE...]] [--opening HOUSE] [--record FILE | --replay FILE]
+             [--idle-timeout SECONDS] [--connect-attempts N] [--connect-backoff SECONDS] [--time-policy POLICY]
+       kalah human [--houses H] [--seeds S] [--black] [--time SECONDS] [--agent AGENT] [--valuation VALUATION]
+                   [--opening HOUSE]
+       kalah solve BOARD
+       kalah bench-eval VALUATION VALUATION [--games N] [--depth D] [--seed N] [--houses H] [--seeds S]
+       kalah --selftest
+
+    URL          address of the KGP server (default: localhost:2671)
+    --session    play the server commands in FILE (one per line) instead of connecting to a server, and print the
+                 commands the engine sends in reply
+    --mode       modes to request from the server in order of preference, freeplay or simple (default: freeplay)
+    --opening    always open the game with HOUSE (1-based) instead of searching
+    --record     write every move the engine makes, with the board it was made on, to FILE
+    --replay     play the moves recorded in FILE instead of searching
+    --idle-timeout
+                 warn when the server sends nothing for SECONDS, 0 to never warn (default: 60)
+    --connect-attempts
+                 try to reach the server N times before giving up (default: 5)
+    --connect-backoff
+                 wait SECONDS after the first failed attempt, twice as long after every further one (default: 0.5)
+    --time-policy
+                 how much of the clock the server announces to use per move: fixed:SECONDS (also without a clock),
+                 fraction:F of the clock or moves:N, an even share for N more moves (default: fraction:0.05)
+
+    human        play against the engine on the console
+    --houses     number of houses per side (default: 8)
+    --seeds      number of seeds per house (default: 8)
+    --black      play as Black, i.e. let the engine move first
+    --time       thinking time of the engine per move in seconds (default: 3)
+    --agent      engine to play against: tournament, minimax, pvs, shallow, random or first (default: tournament)
+    --valuation  valuation of the minimax, pvs and shallow engines: store_diff, store_diff2, seed_diff, phase or
+                 capture_threat (default: store_diff)
+
+    solve        search a small position to the end of the game and print its result and the best move
+    BOARD        position in KGP format with the side to move first, e.g. \"<3, 0, 0, 3, 3, 3, 3, 3, 3>\"
+
+    bench-eval   play two engines searching D plies deep that only differ in their valuation against each other from
+                 the same seeded openings, and print the wins, draws and losses of the first one with an Elo estimate
+    --games      number of games, even (default: 200)
+    --depth      search depth of both engines (default: 3)
+    --seed       seed of the random openings, the same seed plays the same games (default: 0)
+    --houses     number of houses per side (default: 6)
+    --seeds      number of seeds per house (default: 4)
+
+    --selftest   check the rules, the search, the valuations and KGP parsing, print the results and exit (nonzero if
+                 anything failed)";
+
+enum Args {
+    Connect {
+        url: String,
+        modes: Vec<Mode>,
+        opening: Option<u8>,
+        record: Option<String>,
+        replay: Option<String>,
+        idle_timeout: Option<Duration>,
+        session: Option<String>,
+        connect_attempts: u32,
+        connect_backoff: Duration,
+        time_policy: TimeAllocationPolicy,
+    },
+    Human {
+        h: u8,
+        s: u16,
+        human: Player,
+        thinking_time: Duration,
+        agent: String,
+        valuation: String,
+        opening: Option<u8>,
+    },
+    Solve {
+        board: Board,
+    },
+    BenchEval {
+        config: BenchEvalConfig,
+        valuation1: String,
+        valuation2: String,
+    },
+    Selftest,
+}
 
-    let agent1_white_wins = Arc::new(AtomicU64::new(0));
-    let agent1_black_wins = Arc::new(AtomicU64::new(0));
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{option} needs a value"))?;
+    value.parse().map_err(|_| format!("Invalid value {value} for {option}"))
+}
 
-    let agent2_white_wins = Arc::new(AtomicU64::new(0));
-    let agent2_black_wins = Arc::new(AtomicU64::new(0));
+fn parse_human_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut h = 8;
+    let mut s = 8;
+    let mut human = Player::White;
+    let mut thinking_time = Duration::from_secs(3);
+    let mut agent = "tournament".to_owned();
+    let mut valuation = "store_diff".to_owned();
+    let mut opening = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--houses" => h = parse_value(&arg, args.next())?,
+            "--seeds" => s = parse_value(&arg, args.next())?,
+            "--black" => human = Player::Black,
+            "--time" => {
+                let secs: f64 = parse_value(&arg, args.next())?;
+                thinking_time =
+                    Duration::try_from_secs_f64(secs).map_err(|_| format!("Invalid value {secs} for --time"))?;
+            }
+            "--agent" => agent = parse_value(&arg, args.next())?,
+            "--valuation" => valuation = parse_value(&arg, args.next())?,
+            "--opening" => opening = Some(parse_value::<Move>(&arg, args.next())?.house()),
+            _ => return Err(format!("Unexpected argument {arg}")),
+        }
+    }
 
-    let draws = Arc::new(AtomicU64::new(0));
+    // reject bad sizes and names here rather than failing once the game starts
+    Board::try_new(h, s)?;
 
-    let pool = ThreadPool::new(num_workers);
+    if !AGENT_NAMES.contains(&agent.as_str()) {
+        return Err(format!("Unknown agent {agent}"));
+    }
+    if valuation_by_name(&valuation).is_none() {
+        return Err(format!("Unknown valuation {valuation}"));
+    }
 
-    // let mut t_handles = Vec::with_capacity(100);
+    Ok(Args::Human {
+        h,
+        s,
+        human,
+        thinking_time,
+        agent,
+        valuation,
+        opening,
+    })
+}
 
-    for _ in 0..num_runs / 2 {
-        let mut board = Board::new(h, s);
+fn parse_bench_eval_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut config = BenchEvalConfig::new();
+    let mut valuations = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => config.num_games = parse_value(&arg, args.next())?,
+            "--depth" => config.depth = parse_value(&arg, args.next())?,
+            "--seed" => config.seed = parse_value(&arg, args.next())?,
+            "--houses" => config.h = parse_value(&arg, args.next())?,
+            "--seeds" => config.s = parse_value(&arg, args.next())?,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+            _ => valuations.push(arg),
+        }
+    }
 
-        advance_random(h, s, &mut board, 2 * h as usize);
+    let [valuation1, valuation2]: [String; 2] = valuations
+        .try_into()
+        .map_err(|_| "bench-eval needs two valuations".to_owned())?;
 
-        // agent1 as White, agent2 as Black
-        pool.execute({
-            let board = board.clone();
+    // same checks bench_eval does, but before playing
+    Board::try_new(config.h, config.s)?;
 
-            let agent1 = agent1_builder();
-            let agent2 = agent2_builder();
+    for valuation in [&valuation1, &valuation2] {
+        if valuation_by_name(valuation).is_none() {
+            return Err(format!("Unknown valuation {valuation}"));
+        }
+    }
+    if config.num_games == 0 || config.num_games % 2 == 1 {
+        return Err(format!("Invalid value {} for --games, must be even", config.num_games));
+    }
 
-            let agent1_white_wins = Arc::clone(&agent1_white_wins);
-            let agent2_black_wins = Arc::clone(&agent2_black_wins);
-            let draws = Arc::clone(&draws);
+    Ok(Args::BenchEval {
+        config,
+        valuation1,
+        valuation2,
+    })
+}
 
-            move || {
-                use std::cmp::Ordering::{Equal, Greater, Less};
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut url = None;
+    let mut modes = vec![Mode::Freeplay];
+    let mut opening = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut idle_timeout = Some(DEFAULT_IDLE_TIMEOUT);
+    let mut session = None;
+    let mut connect_attempts = DEFAULT_CONNECT_ATTEMPTS;
+    let mut connect_backoff = DEFAULT_CONNECT_BACKOFF;
+    let mut time_policy = TimeAllocationPolicy::new();
+
+    let mut args = args.into_iter().peekable();
+
+    if args.peek().map(String::as_str) == Some("human") {
+        args.next();
+        return parse_human_args(args);
+    }
 
-                let board = game_loop::<false>(board, agent1, agent2, thinking_time);
+    if args.peek().map(String::as_str) == Some("solve") {
+        args.next();
 
-                match board.our_store.cmp(&board.their_store) {
-                    Less => agent2_black_wins.fetch_add(1, Ordering::Release),
-                    Equal => draws.fetch_add(1, Ordering::Release),
-                    Greater => agent1_white_wins.fetch_add(1, Ordering::Release),
-                };
-            }
-        });
+        // the board may come in one argument or split up at its spaces
+        let board: Vec<String> = args.collect();
+        if board.is_empty() {
+            return Err("solve needs a board".to_owned());
+        }
 
-        // agent2 as White, agent1 as Black
-        pool.execute({
-            let board = board.clone();
+        let board = Board::try_from_kgp(&board.join(" "))?;
+        return Ok(Args::Solve { board });
+    }
 
-            let agent1 = agent1_builder();
-            let agent2 = agent2_builder();
+    if args.peek().map(String::as_str) == Some("bench-eval") {
+        args.next();
+        return parse_bench_eval_args(args);
+    }
 
-            let agent1_black_wins = Arc::clone(&agent1_black_wins);
-            let agent2_white_wins = Arc::clone(&agent2_white_wins);
-            let draws = Arc::clone(&draws);
+    if args.peek().map(String::as_str) == Some("--selftest") {
+        args.next();
 
-            move || {
-                use std::cmp::Ordering::{Equal, Greater, Less};
+        if let Some(arg) = args.next() {
+            return Err(format!("Unexpected argument {arg}"));
+        }
 
-                let board = game_loop::<false>(board, agent2, agent1, thinking_time);
+        return Ok(Args::Selftest);
+    }
 
-                match board.our_store.cmp(&board.their_store) {
-                    Less => agent1_black_wins.fetch_add(1, Ordering::Release),
-                    Equal => draws.fetch_add(1, Ordering::Release),
-                    Greater => agent2_white_wins.fetch_add(1, Ordering::Release),
-                };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => {
+                let value = args.next().ok_or("--mode needs a value")?;
+                modes = parse_modes(&value)?;
+            }
+            "--opening" => opening = Some(parse_value::<Move>(&arg, args.next())?.house()),
+            "--record" => record = Some(parse_value(&arg, args.next())?),
+            "--replay" => replay = Some(parse_value(&arg, args.next())?),
+            "--session" => session = Some(parse_value(&arg, args.next())?),
+            "--idle-timeout" => {
+                let secs: f64 = parse_value(&arg, args.next())?;
+                let timeout = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| format!("Invalid value {secs} for --idle-timeout"))?;
+                idle_timeout = Some(timeout).filter(|timeout| !timeout.is_zero());
+            }
+            "--connect-attempts" => {
+                connect_attempts = parse_value(&arg, args.next())?;
+                if connect_attempts == 0 {
+                    return Err("Invalid value 0 for --connect-attempts".to_owned());
+                }
             }
-        });
+            "--connect-backoff" => {
+                let secs: f64 = parse_value(&arg, args.next())?;
+                connect_backoff = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| format!("Invalid value {secs} for --connect-backoff"))?;
+            }
+            "--time-policy" => {
+                let value = args.next().ok_or("--time-policy needs a value")?;
+                time_policy = value.parse()?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+            _ if url.is_none() => url = Some(arg),
+            _ => return Err(format!("Unexpected argument {arg}")),
+        }
     }
 
-    let mut num_done = 0;
-
-    match num_runs {
-        num_runs if num_runs < 10 => println!("{num_done:01}/{num_runs:01}"),
-        num_runs if num_runs < 100 => println!("{num_done:02}/{num_runs:02}"),
-        num_runs if num_runs < 1000 => println!("{num_done:03}/{num_runs:03}"),
-        _ => panic!("formatting for {num_runs} num_runs not supported"),
-    };
+    if record.is_some() && replay.is_some() {
+        return Err("--record and --replay can't be combined".to_owned());
+    }
+    if url.is_some() && session.is_some() {
+        return Err("--session replaces the server, it can't be combined with a URL".to_owned());
+    }
 
-    loop {
-        let queue_count = pool.queued_count();
-        let active_count = pool.active_count();
+    Ok(Args::Connect {
+        url: url.unwrap_or_else(|| "localhost:2671".to_owned()),
+        modes,
+        opening,
+        record,
+        replay,
+        idle_timeout,
+        session,
+        connect_attempts,
+        connect_backoff,
+        time_policy,
+    })
+}
 
-        let new_num_done = num_runs - queue_count - active_count;
+fn main() {
+    // log level can be set via RUST_LOG, e.g. RUST_LOG=debug to see all KGP traffic
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
 
-        if new_num_done > num_done {
-            num_done = new_num_done;
+    match args {
+        Args::Connect {
+            url,
+            modes,
+            opening,
+            record,
+            replay,
+            idle_timeout,
+            session,
+            connect_attempts,
+            connect_backoff,
+            time_policy,
+        } => {
+            let engine = OpeningAgent::new(tournament::MinimaxAgent::new(Board::new(8, 8)), opening);
+
+            let agent: Box<dyn Agent> = match (record, replay) {
+                (_, Some(replay)) => Box::new(ReplayAgent::from_file(&replay).expect("Failed to read replay log")),
+                (Some(record), _) => {
+                    Box::new(RecordingAgent::new(engine, &record).expect("Failed to create record log"))
+                }
+                (None, None) => Box::new(engine),
+            };
 
-            match num_runs {
-                num_runs if num_runs < 10 => println!("{num_done:01}/{num_runs:01}"),
-                num_runs if num_runs < 100 => println!("{num_done:02}/{num_runs:02}"),
-                num_runs if num_runs < 1000 => println!("{num_done:03}/{num_runs:03}"),
-                _ => panic!("formatting for {num_runs} num_runs not supported"),
+            let conn = match session {
+                Some(session) => {
+                    let session = std::fs::read_to_string(session).expect("Failed to read session");
+                    Connection::new_replay(&session, Box::new(std::io::stdout()))
+                }
+                None => {
+                    info!("Connecting to game server at {url}...");
+
+                    let mut conn = match Connection::connect_with_retry(&url, true, connect_attempts, connect_backoff) {
+                        Ok(conn) => conn,
+                        Err(err) => {
+                            error!("Failed to connect to {url}: {err}");
+                            std::process::exit(1);
+                        }
+                    };
+                    conn.idle_timeout = idle_timeout;
+
+                    info!("Connected to game server {url}");
+
+                    conn
+                }
             };
+
+            if let Err(err) = kalah::kgp::kgp_connect(conn, modes, agent, time_policy) {
+                error!("{err}");
+                std::process::exit(1);
+            }
+        }
+        Args::Human {
+            h,
+            s,
+            human,
+            thinking_time,
+            agent,
+            valuation,
+            opening,
+        } => {
+            let engine = agent_by_names(&agent, &valuation, Board::new(h, s)).unwrap();
+            let engine = OpeningAgent::new(engine, opening);
+
+            harness::play_human(h, s, human, engine, thinking_time);
+        }
+        Args::Solve { board } => {
+            println!("{board}\n");
+
+            match tablebase::solve(&board) {
+                Ok(solution) => println!("{solution}"),
+                Err(err) => {
+                    eprintln!("Can't solve {}: {err}", board.to_kgp());
+                    std::process::exit(1);
+                }
+            }
         }
+        Args::BenchEval {
+            config,
+            valuation1,
+            valuation2,
+        } => {
+            let result = harness::bench_eval(&config, &valuation1, &valuation2).unwrap();
+
+            let wins = result.agent1_white_wins + result.agent1_black_wins;
+            let losses = result.agent2_white_wins + result.agent2_black_wins;
+            let elo = match result.elo_difference() {
+                Some(elo) => format!("{elo:+.0}"),
+                None => "unbounded".to_owned(),
+            };
 
-        // if queue_count + active_count == 0 {
-        if num_done == num_runs {
-            break;
+            println!(
+                "{valuation1} vs {valuation2}: {wins} wins, {} draws, {losses} losses, score {:.1}%, Elo {elo}",
+                result.draws,
+                100.0 * result.agent1_score()
+            );
         }
+        Args::Selftest => {
+            let mut all_passed = true;
+
+            for (name, result) in harness::selftest() {
+                match result {
+                    Ok(()) => println!("{name}: ok"),
+                    Err(err) => {
+                        println!("{name}: FAILED ({err})");
+                        all_passed = false;
+                    }
+                }
+            }
 
-        std::thread::sleep(Duration::from_secs(1));
+            if !all_passed {
+                std::process::exit(1);
+            }
+        }
     }
+}
 
-    pool.join();
-
-    println!(
-        "Agent 1 wins: {}/{}",
-        agent1_white_wins.load(Ordering::Acquire),
-        agent1_black_wins.load(Ordering::Acquire)
-    );
-    println!("Draws:        {}", draws.load(Ordering::Acquire));
-    println!(
-        "Agent 2 wins: {}/{}",
-        agent2_white_wins.load(Ordering::Acquire),
-        agent2_black_wins.load(Ordering::Acquire)
-    );
-} */
-
-/* pub fn compare_agents(board: Board, mut agent1: impl Agent, mut agent2: impl Agent) {
-    println!("{board}\n\n");
-
-    agent1.update_board(&board);
-    agent1.go();
-
-    agent2.update_board(&board);
-    agent2.go();
+/* fn generate_new_token() {
+    let mut bytes: [u8; 64] = [0; 64];
 
-    // std::thread::sleep(Duration::from_secs(1));
+    thread_rng().fill(&mut bytes);
 
-    while agent1.get_state() == AgentState::Go {
-        let _ = agent1.get_current_best_move();
-        std::thread::sleep(Duration::from_millis(50));
-    }
+    let token = base64::encode(bytes);
 
-    while agent2.get_state() == AgentState::Go {
-        let _ = agent2.get_current_best_move();
-        std::thread::sleep(Duration::from_millis(50));
-    }
+    println!("{token}");
 } */
 
-/* fn main() {
-    let h = 8;
-    let s = 8;
+/*====================================================================================================================*/
 
-    // let white_agent = agent::RandomAgent::new(h, s);
-    // let white_agent = minimax::MinimaxAgent::new(Board::new(h, s), kalah::valuation::store_diff_valuation);
-    let white_agent = pvs::PVSAgent::new(Board::new(h, s), kalah::valuation::store_diff_valuation);
-    // let white_agent = agent::FirstMoveAgent::new(h, s);
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
 
-    // let black_agent = agent::RandomAgent::new(h, s);
-    let black_agent = minimax_reference::MinimaxAgent::new(Board::new(h, s), 6, kalah::valuation::store_diff_valuation);
-    // let black_agent = agent::FirstMoveAgent::new(h, s);
+    use super::{parse_args, Args};
+    use kalah::agent::TimeAllocationPolicy;
+    use kalah::harness::BenchEvalConfig;
+    use kalah::kgp::Mode;
+    use kalah::Player;
 
-    play_game(h, s, white_agent, black_agent);
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_owned()).collect()
+    }
 
-    // let mut board = Board::new(h, s);
-    // advance_random(h, s, &mut board, 2 * h as usize);
-    // compare_agents(board, white_agent, black_agent);
-} */
+    #[test]
+    fn test_parse_args() {
+        let Ok(Args::Connect { url, modes, .. }) = parse_args(args(&[])) else {
+            panic!()
+        };
+        assert_eq!(url, "localhost:2671");
+        assert_eq!(modes, vec![Mode::Freeplay]);
 
-/* fn main() {
-    let h = 8;
-    let s = 8;
+        let Ok(Args::Connect { url, modes, .. }) = parse_args(args(&["example.org:2671", "--mode", "simple,freeplay"]))
+        else {
+            panic!()
+        };
+        assert_eq!(url, "example.org:2671");
+        assert_eq!(modes, vec![Mode::Simple, Mode::Freeplay]);
 
-    let agent1_builder = &|| minimax::MinimaxAgent::new(Board::new(h, s), kalah::valuation::store_diff_valuation);
+        assert!(parse_args(args(&["--mode"])).is_err());
+        assert!(parse_args(args(&["--mode", "blitz"])).is_err());
+        assert!(parse_args(args(&["--depth", "3"])).is_err());
+        assert!(parse_args(args(&["a:1", "b:2"])).is_err());
 
-    // let agent2_builder = &|| agent::RandomAgent::new(h, s);
-    // let agent2_builder =
-    //     &|| minimax_reference::MinimaxAgent::new(Board::new(h, s), 6, kalah::valuation::store_diff_valuation);
-    let agent2_builder = &|| pvs::PVSAgent::new(Board::new(h, s), kalah::valuation::store_diff_valuation);
+        let Ok(Args::Connect { record, replay, .. }) = parse_args(args(&["--record", "game.log"])) else {
+            panic!()
+        };
+        assert_eq!((record.as_deref(), replay), (Some("game.log"), None));
+        assert!(parse_args(args(&["--record", "a.log", "--replay", "b.log"])).is_err());
 
-    test_agents(h, s, agent1_builder, agent2_builder, 4 * 8);
-} */
+        let Ok(Args::Connect { session, .. }) = parse_args(args(&["--session", "session.log"])) else {
+            panic!()
+        };
+        assert_eq!(session.as_deref(), Some("session.log"));
+        assert!(parse_args(args(&["example.org:2671", "--session", "session.log"])).is_err());
 
-/* fn main() {
-    let url = "wss://kalah.kwarc.info/socket";
+        let Ok(Args::Connect { idle_timeout, .. }) = parse_args(args(&["--idle-timeout", "2.5"])) else {
+            panic!()
+        };
+        assert_eq!(idle_timeout, Some(Duration::from_millis(2500)));
+        let Ok(Args::Connect { idle_timeout, .. }) = parse_args(args(&["--idle-timeout", "0"])) else {
+            panic!()
+        };
+        assert_eq!(idle_timeout, None);
+        assert!(parse_args(args(&["--idle-timeout", "-1"])).is_err());
+
+        let Ok(Args::Connect {
+            connect_attempts,
+            connect_backoff,
+            ..
+        }) = parse_args(args(&["--connect-attempts", "10", "--connect-backoff", "0.25"]))
+        else {
+            panic!()
+        };
+        assert_eq!((connect_attempts, connect_backoff), (10, Duration::from_millis(250)));
+        assert!(parse_args(args(&["--connect-attempts", "0"])).is_err());
 
-    println!("Connecting to game server at {url}...");
+        let Ok(Args::Connect { time_policy, .. }) = parse_args(args(&["--time-policy", "moves:30"])) else {
+            panic!()
+        };
+        assert_eq!(time_policy, TimeAllocationPolicy::MovesToGo { n: 30 });
+        assert!(parse_args(args(&["--time-policy", "fraction:2"])).is_err());
 
-    let conn = Connection::new_websocket(url).expect("Failed to connect");
+        assert!(matches!(parse_args(args(&["--selftest"])), Ok(Args::Selftest)));
+        assert!(parse_args(args(&["--selftest", "example.org:2671"])).is_err());
+    }
 
-    println!("Connected to game server {url}");
+    #[test]
+    fn test_parse_solve_args() {
+        let Ok(Args::Solve { board }) = parse_args(args(&["solve", "<2, 1, 0, 2, 0, 1, 3>"])) else {
+            panic!()
+        };
+        assert_eq!(board.to_kgp(), "<2, 1, 0, 2, 0, 1, 3>");
 
-    crate::kgp::kgp_connect(conn);
-} */
+        // unquoted, as the shell splits it up
+        let Ok(Args::Solve { board }) = parse_args(args(&["solve", "<2,", "1,", "0,", "2,", "0,", "1,", "3>"])) else {
+            panic!()
+        };
+        assert_eq!(board.to_kgp(), "<2, 1, 0, 2, 0, 1, 3>");
 
-fn main() {
-    let url = "localhost:2671";
+        assert!(parse_args(args(&["solve"])).is_err());
+        assert!(parse_args(args(&["solve", "<2, 1, 0, 2, 0, 1>"])).is_err());
+        assert!(parse_args(args(&["solve", "2, 1, 0, 2, 0, 1, 3"])).is_err());
+    }
 
-    println!("Connecting to game server at {url}...");
+    #[test]
+    fn test_parse_bench_eval_args() {
+        let Ok(Args::BenchEval {
+            config,
+            valuation1,
+            valuation2,
+        }) = parse_args(args(&["bench-eval", "store_diff", "phase"]))
+        else {
+            panic!()
+        };
+        assert_eq!(config, BenchEvalConfig::new());
+        assert_eq!((valuation1.as_str(), valuation2.as_str()), ("store_diff", "phase"));
+
+        let parsed = parse_args(args(&[
+            "bench-eval",
+            "--games",
+            "20",
+            "seed_diff",
+            "--depth",
+            "2",
+            "--seed",
+            "7",
+            "phase",
+            "--houses",
+            "4",
+        ]));
+        let Ok(Args::BenchEval { config, valuation1, .. }) = parsed else {
+            panic!()
+        };
+        assert_eq!((config.num_games, config.depth, config.seed, config.h), (20, 2, 7, 4));
+        assert_eq!(valuation1, "seed_diff");
+
+        assert!(parse_args(args(&["bench-eval", "store_diff"])).is_err());
+        assert!(parse_args(args(&["bench-eval", "store_diff", "phase", "seed_diff"])).is_err());
+        assert!(parse_args(args(&["bench-eval", "store_diff", "mobility2"])).is_err());
+        assert!(parse_args(args(&["bench-eval", "store_diff", "phase", "--games", "9"])).is_err());
+        assert!(parse_args(args(&["bench-eval", "store_diff", "phase", "--time", "1"])).is_err());
+    }
 
-    let conn = Connection::new_tcpstream(url).expect("Failed to connect");
+    #[test]
+    fn test_parse_human_args() {
+        let Ok(Args::Human {
+            h,
+            s,
+            human,
+            thinking_time,
+            ..
+        }) = parse_args(args(&["human"]))
+        else {
+            panic!()
+        };
+        assert_eq!(
+            (h, s, human, thinking_time),
+            (8, 8, Player::White, Duration::from_secs(3))
+        );
+
+        let parsed = parse_args(args(&[
+            "human", "--houses", "6", "--seeds", "4", "--black", "--time", "0.5",
+        ]));
+        let Ok(Args::Human {
+            h,
+            s,
+            human,
+            thinking_time,
+            ..
+        }) = parsed
+        else {
+            panic!()
+        };
+        assert_eq!(
+            (h, s, human, thinking_time),
+            (6, 4, Player::Black, Duration::from_millis(500))
+        );
+
+        assert!(parse_args(args(&["human", "--houses"])).is_err());
+        assert!(parse_args(args(&["human", "--houses", "0"])).is_err());
+        assert!(parse_args(args(&["human", "--seeds", "many"])).is_err());
+        assert!(parse_args(args(&["human", "--time", "-1"])).is_err());
+        assert!(parse_args(args(&["human", "localhost:2671"])).is_err());
+
+        let parsed = parse_args(args(&["human", "--agent", "pvs", "--valuation", "seed_diff"]));
+        let Ok(Args::Human { agent, valuation, .. }) = parsed else {
+            panic!()
+        };
+        assert_eq!((agent.as_str(), valuation.as_str()), ("pvs", "seed_diff"));
 
-    println!("Connected to game server {url}");
+        assert!(parse_args(args(&["human", "--agent", "alphazero"])).is_err());
+        assert!(parse_args(args(&["human", "--valuation", "mobility2"])).is_err());
 
-    crate::kgp::kgp_connect(conn);
+        let Ok(Args::Human { opening, .. }) = parse_args(args(&["human", "--opening", "3"])) else {
+            panic!()
+        };
+        assert_eq!(opening, Some(2));
+        assert!(parse_args(args(&["human", "--opening", "0"])).is_err());
+    }
 }
-
-/* fn generate_new_token() {
-    let mut bytes: [u8; 64] = [0; 64];
-
-    thread_rng().fill(&mut bytes);
-
-    let token = base64::encode(bytes);
-
-    println!("{token}");
-} */
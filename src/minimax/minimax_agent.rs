@@ -1,10 +1,17 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use log::{error, info, warn};
 
 use crate::kalah::ValuationFn;
-use crate::{Board, Move, Player};
+use crate::{Board, Move};
 
-use super::search::{minimax_search, new_shared_minimax_search_state, SharedMinimaxSearchState};
-use crate::agent::{Agent, AgentState};
+use super::search::{minimax_search, new_shared_minimax_search_state, MinimaxSearchState, SharedMinimaxSearchState};
+use crate::agent::{
+    fallback_move, lock_search_when, worker_panic, Agent, AgentState, Watchdog, FIRST_DEPTH_TIMEOUT, WATCHDOG_TIMEOUT,
+};
 
 pub struct MinimaxAgent {
     state: AgentState,
@@ -13,19 +20,79 @@ pub struct MinimaxAgent {
 
     search_state: Option<SharedMinimaxSearchState>,
 
+    // cleared to cancel the current search, or by the search itself once it's done
+    search_active: Arc<AtomicBool>,
+
+    watchdog: Option<Watchdog>,
+
+    // search thread, to notice if it panicked
+    worker: Option<JoinHandle<()>>,
+
+    // last move read from search_state, in case it's locked when asked again
+    last_best_move: Option<Move>,
+
+    // when the current search was started
+    go_time: Instant,
+
     valuation_fn: ValuationFn,
+
+    // search with an explicit stack instead of recursing, see with_explicit_stack
+    explicit_stack: bool,
+
+    // see with_max_nodes
+    max_nodes: Option<u64>,
 }
 
 impl MinimaxAgent {
-    #[allow(dead_code)]
     pub fn new(board: Board, valuation_fn: ValuationFn) -> Self {
         MinimaxAgent {
             state: AgentState::Waiting,
             board,
             search_state: None,
+            search_active: Arc::new(AtomicBool::new(false)),
+            watchdog: None,
+            worker: None,
+            last_best_move: None,
+            go_time: Instant::now(),
             valuation_fn,
+            explicit_stack: false,
+            max_nodes: None,
         }
     }
+
+    /// keep the search's line of nodes in a Vec instead of recursing on the thread's stack
+    ///
+    /// finds the same moves, but long chains of bonus moves (which don't use up search depth) can't overflow the stack
+    pub fn with_explicit_stack(mut self) -> Self {
+        self.explicit_stack = true;
+        self
+    }
+
+    /// end each search after the first completed depth that visited max_nodes nodes, even if there's time left
+    ///
+    /// the depth then doesn't depend on the machine, which makes comparing searches reproducible
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// recover from a panicked search: log it and start over, playing the best move so far until the new search has
+    /// one
+    fn restart_search(&mut self, err: &str) -> Move {
+        error!("Search thread panicked ({err}), restarting the search");
+
+        let last_best_move = self.last_best_move;
+
+        self.stop();
+        self.go();
+
+        // go forgets the best move so far, but one from a completed depth of the failed search beats the fallback
+        if last_best_move.is_some() {
+            self.last_best_move = last_best_move;
+        }
+
+        self.last_best_move.unwrap()
+    }
 }
 
 impl Agent for MinimaxAgent {
@@ -36,11 +103,40 @@ impl Agent for MinimaxAgent {
     fn get_current_best_move(&mut self) -> Move {
         assert_eq!(self.state, AgentState::Go);
 
-        if !self.search_state.as_ref().unwrap().lock().unwrap().search_active {
-            self.state = AgentState::Waiting;
+        if let Some(err) = worker_panic(&mut self.worker) {
+            return self.restart_search(&err);
+        }
+
+        // own reference, so the search can be restarted below while the result is still borrowed
+        let search_state = Arc::clone(self.search_state.as_ref().unwrap());
+
+        // give the search a moment to complete its first depth rather than handing out the fallback right away
+        let deadline = self.go_time + FIRST_DEPTH_TIMEOUT;
+        let ready = |search_state: &MinimaxSearchState| {
+            search_state.completed_depth > 0 || !self.search_active.load(Ordering::Relaxed)
+        };
+
+        let locked = match lock_search_when(&search_state, deadline, ready) {
+            Ok(locked) => locked,
+            Err(err) => return self.restart_search(&err),
+        };
+
+        if let Some(search_state) = locked {
+            self.last_best_move = Some(search_state.current_best_move);
+
+            // the search publishes its result before clearing search_active, so it's the final one here
+            if !self.search_active.load(Ordering::Relaxed) {
+                self.state = AgentState::Waiting;
+            }
+        }
+
+        if self.state == AgentState::Go && self.watchdog.as_mut().unwrap().is_stuck() {
+            warn!("Search made no progress for {WATCHDOG_TIMEOUT:?}, stopping it and playing the best move so far");
+
+            self.stop();
         }
 
-        self.search_state.as_ref().unwrap().lock().unwrap().current_best_move
+        self.last_best_move.unwrap()
     }
 
     fn get_state(&self) -> crate::agent::AgentState {
@@ -48,15 +144,37 @@ impl Agent for MinimaxAgent {
     }
 
     fn go(&mut self) {
-        // use first legal move as a fallback in case we don't complete a single search iteration, which really should
-        // not happen
-        let fallback_move = *self.board.legal_moves(Player::White).first().unwrap();
-        let search_state = new_shared_minimax_search_state(true, fallback_move);
+        if !self.board.has_legal_move() {
+            info!("Game is over, nothing to search");
+            self.state = AgentState::Waiting;
+            return;
+        }
+
+        // use best move after one ply as a fallback in case we don't complete a single search iteration, which really
+        // should not happen
+        let fallback_move = fallback_move(&self.board, self.valuation_fn);
+        let search_state = new_shared_minimax_search_state(fallback_move);
+        let search_active = Arc::new(AtomicBool::new(true));
+
+        let watchdog = Watchdog::new(WATCHDOG_TIMEOUT);
 
-        minimax_search(&self.board, self.valuation_fn, Arc::clone(&search_state));
+        let worker = minimax_search(
+            &self.board,
+            self.valuation_fn,
+            self.explicit_stack,
+            self.max_nodes,
+            Arc::clone(&search_state),
+            Arc::clone(&search_active),
+            watchdog.progress(),
+        );
 
         self.state = AgentState::Go;
         self.search_state = Some(search_state);
+        self.search_active = search_active;
+        self.watchdog = Some(watchdog);
+        self.worker = Some(worker);
+        self.last_best_move = Some(fallback_move);
+        self.go_time = Instant::now();
     }
 
     fn stop(&mut self) {
@@ -67,9 +185,11 @@ impl Agent for MinimaxAgent {
 
         self.state = AgentState::Waiting;
 
-        // set search_active to false, then drop reference
-        self.search_state.as_ref().unwrap().lock().unwrap().search_active = false;
+        // cancel the search (if go started one), then drop reference
+        self.search_active.store(false, Ordering::Relaxed);
         self.search_state = None;
+        self.watchdog = None;
+        self.worker = None;
     }
 
     fn ponder(&mut self) {
@@ -2,3 +2,6 @@ mod minimax_agent;
 mod search;
 
 pub use minimax_agent::MinimaxAgent;
+
+#[cfg(test)]
+pub use search::{search_value, search_value_explicit_stack};
@@ -1,44 +1,126 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::Instant;
 
+use log::info;
+
 use crate::kalah::valuation::{Valuation, ValuationFn};
-use crate::{Board, Move, Player, LOG_STATS};
+use crate::kalah::{BoardPool, Features};
+use crate::{Board, Move, Player};
 
 /*====================================================================================================================*/
 
 pub type SharedMinimaxSearchState = Arc<Mutex<MinimaxSearchState>>;
 
+/// result of the search so far, the lock is only taken to publish or read it
+///
+/// cancelling the search (or the search announcing that it's done) goes through a separate search_active flag, so the
+/// recursion never has to take the lock
 pub struct MinimaxSearchState {
-    pub search_active: bool,
-
     pub current_best_move: Move,
+
+    // deepest search depth that was completed, 0 while current_best_move is still the fallback
+    pub completed_depth: u32,
+
+    // value of current_best_move at completed_depth
+    pub value: Valuation,
+}
+
+impl MinimaxSearchState {
+    /// publish the best move of a completed search depth, unless the stored result is better
+    ///
+    /// the deeper result is better, at the same depth the higher value and between equal values the move from the
+    /// lower house, so with several threads reporting the stored move doesn't depend on which of them finished last
+    pub fn report(&mut self, best_move: Move, depth: u32, value: Valuation) {
+        let rank = |best_move: Move, depth: u32, value: Valuation| (depth, value, Reverse(best_move.house()));
+
+        if rank(best_move, depth, value) > rank(self.current_best_move, self.completed_depth, self.value) {
+            self.current_best_move = best_move;
+            self.completed_depth = depth;
+            self.value = value;
+        }
+    }
 }
 
-pub fn new_shared_minimax_search_state(search_active: bool, fallback_move: Move) -> SharedMinimaxSearchState {
+pub fn new_shared_minimax_search_state(fallback_move: Move) -> SharedMinimaxSearchState {
     Arc::new(Mutex::new(MinimaxSearchState {
-        search_active,
         current_best_move: fallback_move,
+        completed_depth: 0,
+        value: Valuation::TerminalBlackWin { plies: 0 },
     }))
 }
 
 /*====================================================================================================================*/
 
+/// a node of minimax_explicit_stack whose children are being searched, i.e. what a call of minimax keeps on the stack
+struct Frame {
+    board: Board,
+
+    remaining_depth: u32,
+
+    alpha: Valuation,
+    beta: Valuation,
+
+    // moves that are still to be searched
+    legal_mask: u128,
+
+    best_move: Move,
+    best_value: Valuation,
+
+    // whether the child that is being searched is the opponent's, i.e. its value has to be negated
+    their_turn: bool,
+    current_move: Move,
+}
+
+/// what entering a node turned up: its value if it's a leaf, otherwise a frame to search its children from
+enum Node {
+    Leaf(Valuation),
+    Inner(Frame),
+}
+
 struct MinimaxWorker {
     search_state: Arc<Mutex<MinimaxSearchState>>,
 
+    search_active: Arc<AtomicBool>,
+
+    // board_after_move of every node on the current line, see BoardPool
+    scratch_boards: BoardPool,
+
     valuation_fn: ValuationFn,
 
+    // search with minimax_explicit_stack instead of the recursive minimax
+    explicit_stack: bool,
+
+    // end the search after the first completed depth that visited this many nodes in total, see minimax_search
+    max_nodes: Option<u64>,
+
     total_nodes_visited: u64,
 
+    // total_nodes_visited, published for the agent's watchdog
+    progress: Arc<AtomicU64>,
+
     start_t: Instant,
 }
 
 impl MinimaxWorker {
-    pub fn new(valuation_fn: ValuationFn, search_state: SharedMinimaxSearchState) -> Self {
+    pub fn new(
+        valuation_fn: ValuationFn,
+        explicit_stack: bool,
+        search_state: SharedMinimaxSearchState,
+        search_active: Arc<AtomicBool>,
+        progress: Arc<AtomicU64>,
+    ) -> Self {
         MinimaxWorker {
             search_state,
+            search_active,
+            scratch_boards: BoardPool::new(),
             valuation_fn,
+            explicit_stack,
+            max_nodes: None,
             total_nodes_visited: 0,
+            progress,
             start_t: Instant::now(),
         }
     }
@@ -48,14 +130,17 @@ impl MinimaxWorker {
     }
 
     fn minimax(&mut self, board: &Board, remaining_depth: u32, alpha: Valuation, beta: Valuation) -> (Move, Valuation) {
-        if !self.search_state.lock().unwrap().search_active {
+        if !self.search_active.load(Ordering::Relaxed) {
             // search has been ended, search results don't matter anymore, exit thread asap
             return (Move::new(127, Player::White), Valuation::NonTerminal { value: 0 });
         }
 
         self.total_nodes_visited += 1;
+        self.progress.store(self.total_nodes_visited, Ordering::Relaxed);
 
-        if remaining_depth == 0 || !board.has_legal_move() {
+        // decided positions are leaves like finished games: the valuations treat them as terminal, so searching on
+        // would only add the plies to the horizon to their distance
+        if remaining_depth == 0 || board.is_decided().is_some() {
             return (Move::new(127, Player::White), (self.valuation_fn)(board));
         }
 
@@ -63,26 +148,34 @@ impl MinimaxWorker {
         let mut best_value = Valuation::TerminalBlackWin { plies: 0 };
         let mut alpha = alpha;
 
-        let mut board_after_move = board.clone();
+        let mut board_after_move = self.scratch_boards.take(board);
 
-        for house in 0..board.h() {
-            let move_ = Move::new(house, Player::White);
+        let mut legal_mask = board.legal_mask(Player::White);
 
-            if !board.is_legal_move(move_) {
-                continue;
-            }
+        while legal_mask != 0 {
+            // lowest set bit is the next legal house
+            let house = legal_mask.trailing_zeros() as u8;
+            legal_mask &= legal_mask - 1;
+
+            let move_ = Move::new(house, Player::White);
 
-            // let mut board_after_move = board.clone();
             board_after_move.clone_from(board);
             let their_turn = !board_after_move.apply_move(move_);
 
+            // the child counts plies from its own position, so the window has to be shifted by the ply of this move,
+            // otherwise a bound on a forced win or loss could pass for its exact value
+            let (child_alpha, child_beta) = (alpha.decrease_plies(), beta.decrease_plies());
+
             let value = if their_turn {
                 // opponent move: flip board, alpha, beta to their perspective and flip returned value to ours
                 board_after_move.flip_board();
-                -self.minimax(&board_after_move, remaining_depth - 1, -beta, -alpha).1
+                -self
+                    .minimax(&board_after_move, remaining_depth - 1, -child_beta, -child_alpha)
+                    .1
             } else {
                 // bonus move: don't decrease depth
-                self.minimax(&board_after_move, remaining_depth, alpha, beta).1
+                self.minimax(&board_after_move, remaining_depth, child_alpha, child_beta)
+                    .1
             }
             .increase_plies();
 
@@ -102,9 +195,166 @@ impl MinimaxWorker {
             }
         }
 
+        self.scratch_boards.give_back(board_after_move);
+
         (best_move, best_value)
     }
 
+    /// count board as visited and either value it (if it's a leaf) or set up the search of its children, like the
+    /// start of minimax
+    fn enter(&mut self, board: Board, remaining_depth: u32, alpha: Valuation, beta: Valuation) -> Node {
+        self.total_nodes_visited += 1;
+        self.progress.store(self.total_nodes_visited, Ordering::Relaxed);
+
+        if remaining_depth == 0 || board.is_decided().is_some() {
+            let value = (self.valuation_fn)(&board);
+            self.scratch_boards.give_back(board);
+            return Node::Leaf(value);
+        }
+
+        Node::Inner(Frame {
+            legal_mask: board.legal_mask(Player::White),
+            board,
+            remaining_depth,
+            alpha,
+            beta,
+            best_move: Move::new(127, Player::White),
+            best_value: Valuation::TerminalBlackWin { plies: 0 },
+            their_turn: false,
+            current_move: Move::new(127, Player::White),
+        })
+    }
+
+    /// same search as minimax, with the line of nodes kept in a Vec instead of on the thread's stack
+    ///
+    /// bonus moves don't use up depth, so a long chain of them makes minimax recurse much deeper than the search depth,
+    /// which could overflow the stack on boards with many seeds. Here such a chain only grows the Vec. Moves get searched
+    /// in the same order with the same windows, so the result is the same as minimax's.
+    fn minimax_explicit_stack(
+        &mut self,
+        board: &Board,
+        remaining_depth: u32,
+        alpha: Valuation,
+        beta: Valuation,
+    ) -> (Move, Valuation) {
+        let cancelled = (Move::new(127, Player::White), Valuation::NonTerminal { value: 0 });
+
+        if !self.search_active.load(Ordering::Relaxed) {
+            return cancelled;
+        }
+
+        let root = self.scratch_boards.take(board);
+
+        let mut stack = match self.enter(root, remaining_depth, alpha, beta) {
+            Node::Leaf(value) => return (Move::new(127, Player::White), value),
+            Node::Inner(frame) => vec![frame],
+        };
+
+        // value of the child of the top frame that was just searched
+        let mut child_value: Option<Valuation> = None;
+
+        loop {
+            let frame = stack.last_mut().unwrap();
+
+            if let Some(value) = child_value.take() {
+                let value = if frame.their_turn { -value } else { value }.increase_plies();
+
+                if value >= frame.best_value {
+                    frame.best_move = frame.current_move;
+                    frame.best_value = value;
+                }
+
+                if value > frame.beta {
+                    // beta cutoff, skip the remaining moves
+                    frame.legal_mask = 0;
+                } else if frame.best_value > frame.alpha {
+                    frame.alpha = frame.best_value;
+                }
+            }
+
+            if frame.legal_mask == 0 {
+                let frame = stack.pop().unwrap();
+                self.scratch_boards.give_back(frame.board);
+
+                if stack.is_empty() {
+                    return (frame.best_move, frame.best_value);
+                }
+
+                child_value = Some(frame.best_value);
+                continue;
+            }
+
+            if !self.search_active.load(Ordering::Relaxed) {
+                // search has been ended, search results don't matter anymore, exit thread asap
+                for frame in stack {
+                    self.scratch_boards.give_back(frame.board);
+                }
+                return cancelled;
+            }
+
+            // lowest set bit is the next legal house
+            let house = frame.legal_mask.trailing_zeros() as u8;
+            frame.legal_mask &= frame.legal_mask - 1;
+
+            let move_ = Move::new(house, Player::White);
+
+            let mut board_after_move = self.scratch_boards.take(&frame.board);
+            let their_turn = !board_after_move.apply_move(move_);
+
+            frame.current_move = move_;
+            frame.their_turn = their_turn;
+
+            // the child counts plies from its own position, see minimax
+            let (child_alpha, child_beta) = (frame.alpha.decrease_plies(), frame.beta.decrease_plies());
+
+            let child = if their_turn {
+                board_after_move.flip_board();
+                let remaining_depth = frame.remaining_depth - 1;
+                self.enter(board_after_move, remaining_depth, -child_beta, -child_alpha)
+            } else {
+                let remaining_depth = frame.remaining_depth;
+                self.enter(board_after_move, remaining_depth, child_alpha, child_beta)
+            };
+
+            match child {
+                Node::Leaf(value) => child_value = Some(value),
+                Node::Inner(frame) => stack.push(frame),
+            }
+        }
+    }
+
+    /// minimax or minimax_explicit_stack, whichever the worker was asked to use
+    fn search(&mut self, board: &Board, remaining_depth: u32, alpha: Valuation, beta: Valuation) -> (Move, Valuation) {
+        if self.explicit_stack {
+            self.minimax_explicit_stack(board, remaining_depth, alpha, beta)
+        } else {
+            self.minimax(board, remaining_depth, alpha, beta)
+        }
+    }
+
+    /// best move of a root that is already decided, judged one ply ahead
+    ///
+    /// the outcome can't change anymore, any move keeps it decided, so deepening would only burn the clock
+    fn decided_root(&self, board: &Board) -> (Move, Valuation) {
+        board
+            .legal_moves(Player::White)
+            .into_iter()
+            .map(|move_| {
+                let mut board_after_move = board.clone();
+
+                let value = if board_after_move.apply_move(move_) {
+                    (self.valuation_fn)(&board_after_move)
+                } else {
+                    board_after_move.flip_board();
+                    -(self.valuation_fn)(&board_after_move)
+                };
+
+                (move_, value.increase_plies())
+            })
+            .max_by_key(|&(_move, value)| value)
+            .unwrap()
+    }
+
     pub fn start_search(self, board: Board) {
         use Valuation::{TerminalBlackWin, TerminalWhiteWin};
 
@@ -114,98 +364,277 @@ impl MinimaxWorker {
 
         let mut current_best_value = Valuation::TerminalBlackWin { plies: 0 };
 
+        if board.is_decided().is_some() {
+            let (best_move, best_value) = me.decided_root(&board);
+            info!("Root is already decided, playing {best_move} with value {best_value}");
+            me.search_state.lock().unwrap().report(best_move, 1, best_value);
+            me.search_active.store(false, Ordering::Relaxed);
+            return;
+        }
+
         let alpha = TerminalBlackWin { plies: 0 };
         let beta = TerminalWhiteWin { plies: 0 };
 
         let max_depth = 6;
         // {
         for max_depth in 6.. {
+            // one board per ply, bonus moves make the pool grow past that on its own
+            me.scratch_boards.reserve(&board, max_depth as usize);
+
             let board = board.clone();
-            let (best_move, best_value) = me.minimax(&board, max_depth, alpha, beta);
-
-            if !me.search_state.lock().unwrap().search_active {
-                if LOG_STATS {
-                    println!("--------------------------------------------");
-                    println!("* Minimax worker exited after max_depth {}", max_depth - 1);
-                    println!("* Best move had value {current_best_value:?}");
-                    println!("* NPS: {:.2e} ({:?})", me.current_nps(), me.start_t.elapsed());
-                    println!("--------------------------------------------\n");
-                }
+            let (best_move, best_value) = me.search(&board, max_depth, alpha, beta);
+
+            if !me.search_active.load(Ordering::Relaxed) {
+                info!(
+                    "Minimax worker exited after max_depth {}, best move had value {current_best_value:?}, NPS: {:.2e} ({:?})",
+                    max_depth - 1,
+                    me.current_nps(),
+                    me.start_t.elapsed()
+                );
                 return;
             }
 
-            if let Valuation::TerminalWhiteWin { plies } = best_value {
-                if LOG_STATS {
-                    println!("--------------------------------------------");
-                    println!("* Found certain win in {plies} plies");
-                    println!("--------------------------------------------\n");
-                }
-                {
-                    let mut search_state = me.search_state.lock().unwrap();
-                    search_state.current_best_move = best_move;
-                    search_state.search_active = false;
-                }
+            // a shorter win (or loss) than the one found could still be beyond the horizon, see distance_is_exact
+            let exact = best_value.distance_is_exact(max_depth);
+
+            if let (Valuation::TerminalWhiteWin { plies }, true) = (best_value, exact) {
+                info!("Found certain win in {plies} plies");
+                me.search_state.lock().unwrap().report(best_move, max_depth, best_value);
+                me.search_active.store(false, Ordering::Relaxed);
                 return;
             }
 
-            if let TerminalBlackWin { plies } = best_value {
+            if let (TerminalBlackWin { plies }, true) = (best_value, exact) {
                 // all moves are certain losses, pick the one with the most plies and exit
-                if LOG_STATS {
-                    println!("--------------------------------------------");
-                    println!("* Found certain loss in {plies} plies");
-                    println!("--------------------------------------------");
-                    println!();
-                }
-                {
-                    let mut search_state = me.search_state.lock().unwrap();
-                    search_state.current_best_move = best_move;
-                    search_state.search_active = false;
-                }
+                info!("Found certain loss in {plies} plies");
+                me.search_state.lock().unwrap().report(best_move, max_depth, best_value);
+                me.search_active.store(false, Ordering::Relaxed);
                 return;
             }
 
-            me.search_state.lock().unwrap().current_best_move = best_move;
+            me.search_state.lock().unwrap().report(best_move, max_depth, best_value);
             current_best_value = best_value;
+
+            if matches!(me.max_nodes, Some(max_nodes) if me.total_nodes_visited >= max_nodes) {
+                me.search_active.store(false, Ordering::Relaxed);
+                info!(
+                    "Minimax worker used up its node budget after max_depth {max_depth}, best move {best_move} had value {best_value:?}, NPS: {:.2e} ({:?})",
+                    me.current_nps(),
+                    me.start_t.elapsed()
+                );
+                return;
+            }
         }
 
-        me.search_state.lock().unwrap().search_active = false;
+        me.search_active.store(false, Ordering::Relaxed);
 
-        if LOG_STATS {
-            println!("--------------------------------------------");
-            println!("* Minimax worker exited after search depth {max_depth}");
-            println!(
-                "* Best move {} had value {:?}",
-                me.search_state.lock().unwrap().current_best_move,
-                current_best_value
-            );
-            println!("* NPS: {:.2e} ({:?})", me.current_nps(), me.start_t.elapsed());
-            println!("--------------------------------------------\n");
-        }
+        info!(
+            "Minimax worker exited after search depth {max_depth}, best move {} had value {:?}, NPS: {:.2e} ({:?})",
+            me.search_state.lock().unwrap().current_best_move,
+            current_best_value,
+            me.current_nps(),
+            me.start_t.elapsed()
+        );
     }
 }
 
+/// best move and value of board after a single fixed depth search, with either version of minimax
+#[cfg(test)]
+fn fixed_depth_search(board: &Board, depth: u32, valuation_fn: ValuationFn, explicit_stack: bool) -> (Move, Valuation) {
+    let search_state = new_shared_minimax_search_state(Move::new(127, Player::White));
+    let search_active = Arc::new(AtomicBool::new(true));
+    let progress = Arc::new(AtomicU64::new(0));
+    let mut worker = MinimaxWorker::new(valuation_fn, explicit_stack, search_state, search_active, progress);
+
+    let alpha = Valuation::TerminalBlackWin { plies: 0 };
+    let beta = Valuation::TerminalWhiteWin { plies: 0 };
+
+    worker.search(board, depth, alpha, beta)
+}
+
+/// value of board after a single fixed depth search, for tests comparing the searches
+#[cfg(test)]
+pub fn search_value(board: &Board, depth: u32, valuation_fn: ValuationFn) -> Valuation {
+    fixed_depth_search(board, depth, valuation_fn, false).1
+}
+
+/// same as search_value, searching with minimax_explicit_stack
+#[cfg(test)]
+pub fn search_value_explicit_stack(board: &Board, depth: u32, valuation_fn: ValuationFn) -> Valuation {
+    fixed_depth_search(board, depth, valuation_fn, true).1
+}
+
 /*====================================================================================================================*/
 
-pub fn minimax_search(board: &Board, valuation_fn: ValuationFn, search_state: SharedMinimaxSearchState) {
+/// search board in a new thread until search_active gets cleared, or until a completed depth has visited max_nodes
+/// nodes if it's set
+pub fn minimax_search(
+    board: &Board,
+    valuation_fn: ValuationFn,
+    explicit_stack: bool,
+    max_nodes: Option<u64>,
+    search_state: SharedMinimaxSearchState,
+    search_active: Arc<AtomicBool>,
+    progress: Arc<AtomicU64>,
+) -> JoinHandle<()> {
     assert!(
         board.has_legal_move(),
         "Called minimax_search on board with no legal moves"
     );
 
-    let t_handle;
+    // the worker shuts down when search_active gets cleared, the handle is only for noticing if it panicked (dropping
+    // it detaches the worker)
+    std::thread::spawn({
+        let mut board = board.clone();
+        // cheap to keep up to date and lets the seed based valuations skip summing up the houses at every leaf, the
+        // legal masks save scanning the houses for has_legal_move and move generation at every node
+        board.track_features(Features::HOUSE_SEEDS | Features::LEGAL_MASK);
+        move || {
+            let mut worker: MinimaxWorker =
+                MinimaxWorker::new(valuation_fn, explicit_stack, search_state, search_active, progress);
+            worker.max_nodes = max_nodes;
+            worker.start_search(board);
+        }
+    })
+}
 
-    {
-        // let worker_board = board.clone();
+/*====================================================================================================================*/
 
-        t_handle = std::thread::spawn({
-            let board = board.clone();
-            move || {
-                let worker: MinimaxWorker = MinimaxWorker::new(valuation_fn, search_state);
-                worker.start_search(board);
+#[cfg(test)]
+mod tests {
+    use super::{fixed_depth_search, new_shared_minimax_search_state};
+    use crate::kalah::valuation::{seed_diff_valuation, store_diff_valuation, Valuation};
+    use crate::{Board, Move, Player};
+
+    #[test]
+    fn test_report_tie_break() {
+        let value = Valuation::NonTerminal { value: 3 };
+
+        // two threads finishing the same depth with equally good moves, in either order
+        for reports in [[4, 1], [1, 4]] {
+            let search_state = new_shared_minimax_search_state(Move::new(127, Player::White));
+
+            let threads: Vec<_> = reports
+                .into_iter()
+                .map(|house| {
+                    let search_state = search_state.clone();
+                    std::thread::spawn(move || {
+                        search_state
+                            .lock()
+                            .unwrap()
+                            .report(Move::new(house, Player::White), 7, value)
+                    })
+                })
+                .collect();
+
+            for thread in threads {
+                thread.join().unwrap();
             }
-        });
+
+            let search_state = search_state.lock().unwrap();
+            assert_eq!(search_state.current_best_move, Move::new(1, Player::White));
+            assert_eq!((search_state.completed_depth, search_state.value), (7, value));
+        }
+
+        let search_state = new_shared_minimax_search_state(Move::new(127, Player::White));
+        let mut search_state = search_state.lock().unwrap();
+
+        // a higher value wins at the same depth, a deeper result wins regardless of value
+        search_state.report(Move::new(1, Player::White), 7, value);
+        search_state.report(Move::new(4, Player::White), 7, Valuation::NonTerminal { value: 5 });
+        assert_eq!(search_state.current_best_move, Move::new(4, Player::White));
+
+        search_state.report(Move::new(2, Player::White), 6, Valuation::TerminalWhiteWin { plies: 3 });
+        assert_eq!(search_state.current_best_move, Move::new(4, Player::White));
+
+        search_state.report(Move::new(2, Player::White), 8, Valuation::NonTerminal { value: -2 });
+        assert_eq!(search_state.current_best_move, Move::new(2, Player::White));
     }
 
-    // detach worker thread; will get shut down automatically when search_active gets set to false
-    drop(t_handle);
+    #[test]
+    fn test_max_nodes() {
+        use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        use super::minimax_search;
+
+        let board = Board::from_kgp("<6, 2, 3, 0, 4, 1, 0, 2, 1, 3, 0, 5, 2, 1, 0>");
+
+        let search = || {
+            let search_state = new_shared_minimax_search_state(Move::new(127, Player::White));
+            let search_active = Arc::new(AtomicBool::new(true));
+
+            let worker = minimax_search(
+                &board,
+                store_diff_valuation,
+                false,
+                Some(50_000),
+                Arc::clone(&search_state),
+                Arc::clone(&search_active),
+                Arc::new(AtomicU64::new(0)),
+            );
+            worker.join().unwrap();
+            assert!(!search_active.load(Ordering::Relaxed));
+
+            let search_state = search_state.lock().unwrap();
+            (
+                search_state.current_best_move,
+                search_state.completed_depth,
+                search_state.value,
+            )
+        };
+
+        // no clock involved, so the same budget ends at the same depth with the same move every time
+        let result = search();
+        assert!(result.1 >= 6);
+        assert_eq!(search(), result);
+    }
+
+    #[test]
+    fn test_explicit_stack() {
+        use rand::seq::SliceRandom;
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1932);
+
+        // same best move and value as the recursive search, all through random games
+        for _ in 0..5 {
+            let mut board = Board::new(5, 3);
+
+            while board.has_legal_move() {
+                for depth in 1..=4 {
+                    assert_eq!(
+                        fixed_depth_search(&board, depth, store_diff_valuation, true),
+                        fixed_depth_search(&board, depth, store_diff_valuation, false),
+                        "depth {depth} on {}",
+                        board.to_kgp()
+                    );
+                }
+
+                let move_ = *board.legal_moves(Player::White).choose(&mut rng).unwrap();
+
+                if !board.apply_move(move_) {
+                    board.flip_board();
+                }
+            }
+        }
+
+        // every house holds exactly the seeds to reach the store: White can keep moving again for 20 plies, until its
+        // side is empty and the game won, all within depth 1. The explicit stack has to get through the chain on a
+        // thread with a small stack, no matter how long it is.
+        let houses: Vec<String> = (1..=8).rev().map(|seeds: u32| seeds.to_string()).collect();
+        let board = Board::from_kgp(&format!("<8, 0, 0, {}, {}>", houses.join(", "), ["1"; 8].join(", ")));
+
+        let recursive = fixed_depth_search(&board, 1, seed_diff_valuation, false);
+
+        let explicit_stack = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || fixed_depth_search(&board, 1, seed_diff_valuation, true))
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(explicit_stack, recursive);
+        assert_eq!(recursive.1, Valuation::TerminalWhiteWin { plies: 20 });
+    }
 }
@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use log::info;
+
 use crate::kalah::ValuationFn;
 use crate::{Board, Move, Player};
 
@@ -19,7 +21,6 @@ pub struct MinimaxAgent {
 }
 
 impl MinimaxAgent {
-    #[allow(dead_code)]
     pub fn new(board: Board, max_depth: u32, valuation_fn: ValuationFn) -> Self {
         MinimaxAgent {
             state: AgentState::Waiting,
@@ -51,9 +52,14 @@ impl Agent for MinimaxAgent {
     }
 
     fn go(&mut self) {
+        if !self.board.has_legal_move() {
+            info!("Game is over, nothing to search");
+            self.state = AgentState::Waiting;
+            return;
+        }
+
         // use first legal move as a fallback in case we don't complete a single search iteration, which really should
         // not happen
-        // calling go() with a board with no legal moves is illegal
         let fallback_move = *self.board.legal_moves(Player::White).first().unwrap();
         let search_state = new_shared_minimax_search_state(true, fallback_move);
 
@@ -76,9 +82,10 @@ impl Agent for MinimaxAgent {
 
         self.state = AgentState::Waiting;
 
-        // set search_active to false, then drop reference
-        self.search_state.as_ref().unwrap().lock().unwrap().search_active = false;
-        self.search_state = None;
+        // set search_active to false, then drop reference (there is none if go didn't start a search)
+        if let Some(search_state) = self.search_state.take() {
+            search_state.lock().unwrap().search_active = false;
+        }
     }
 
     fn ponder(&mut self) {
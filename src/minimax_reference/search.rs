@@ -1,11 +1,11 @@
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
+use log::debug;
+
 use crate::kalah::valuation::{Valuation, ValuationFn};
 use crate::{Board, Move, Player};
 
-const LOG_STATS: bool = false;
-
 /*====================================================================================================================*/
 
 pub type SharedMinimaxSearchState = Arc<Mutex<MinimaxSearchState>>;
@@ -56,8 +56,9 @@ impl MinimaxWorker {
         use Player::Black;
 
         if !self.search_state.lock().unwrap().search_active {
-            // search has been ended, search results don't matter anymore, exit thread asap
-            panic!("Could not complete minimax search to level 6");
+            // search has been ended, search results don't matter anymore, unwind with a dummy value that
+            // minimax_search discards
+            return (Move::new(127, Black), Valuation::NonTerminal { value: 0 });
         }
 
         if remaining_depth == 0 || !board.has_legal_move() {
@@ -101,8 +102,8 @@ impl MinimaxWorker {
         use Player::White;
 
         if !self.search_state.lock().unwrap().search_active {
-            // search has been ended, search results don't matter anymore, exit thread asap
-            panic!("Could not complete minimax search to level 6");
+            // see minimise
+            return (Move::new(127, White), Valuation::NonTerminal { value: 0 });
         }
 
         if remaining_depth == 0 || !board.has_legal_move() {
@@ -154,16 +155,29 @@ impl MinimaxWorker {
 
         let (best_move, best_value) = me.maximise(board, me.max_depth, alpha, beta);
 
-        me.search_state.lock().unwrap().current_best_move = best_move;
-        me.search_state.lock().unwrap().search_active = false;
+        {
+            let mut search_state = me.search_state.lock().unwrap();
+
+            if !search_state.search_active {
+                // stopped before it got to the end, so best_move is made up of dummy values: keep the fallback
+                debug!(
+                    "Minimax reference worker stopped before completing max_depth {} ({:?})",
+                    me.max_depth,
+                    me.start_t.elapsed()
+                );
+                return;
+            }
 
-        if LOG_STATS {
-            println!("--------------------------------------------");
-            println!("* Minimax reference worker exited after max_depth {}", me.max_depth);
-            println!("* Best move {best_move} had value {best_value:?}");
-            println!("* NPS: {:.2e} ({:?})", me.current_nps(), me.start_t.elapsed());
-            println!("--------------------------------------------\n");
+            search_state.current_best_move = best_move;
+            search_state.search_active = false;
         }
+
+        debug!(
+            "Minimax reference worker exited after max_depth {}, best move {best_move} had value {best_value:?}, NPS: {:.2e} ({:?})",
+            me.max_depth,
+            me.current_nps(),
+            me.start_t.elapsed()
+        );
     }
 }
 
@@ -192,3 +206,31 @@ pub fn start_search(board: &Board, depth: u32, valuation_fn: ValuationFn, search
     // detach worker thread; will get shut down automatically when search_active gets set to false
     drop(t_handle);
 }
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{new_shared_minimax_search_state, MinimaxWorker};
+    use crate::kalah::valuation::store_diff_valuation;
+    use crate::{Board, Move, Player};
+
+    #[test]
+    fn test_stop_mid_search() {
+        let fallback_move = Move::new(0, Player::White);
+        let search_state = new_shared_minimax_search_state(true, fallback_move);
+
+        // far too deep to finish before being stopped
+        let worker = MinimaxWorker::new(30, store_diff_valuation, Arc::clone(&search_state));
+        let handle = std::thread::spawn(move || worker.minimax_search(Board::new(6, 6)));
+
+        std::thread::sleep(Duration::from_millis(50));
+        search_state.lock().unwrap().search_active = false;
+
+        assert!(handle.join().is_ok(), "Search panicked when stopped");
+        assert_eq!(search_state.lock().unwrap().current_best_move, fallback_move);
+    }
+}
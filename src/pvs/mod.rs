@@ -1,5 +1,10 @@
 mod pvs_agent;
 mod search;
+mod weighted_agent;
 
 pub use pvs_agent::PVSAgent;
-pub use search::Line;
+pub use search::{Line, StopReason, StopRule};
+pub use weighted_agent::WeightedAgent;
+
+#[cfg(test)]
+pub use search::{search_line, search_value};
@@ -1,34 +1,137 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
 
-use crate::kalah::ValuationFn;
-use crate::{Board, Move, Player};
+use log::{error, info, warn};
 
-use super::search::{minimax_search, new_shared_minimax_search_state, SharedMinimaxSearchState};
-use crate::agent::{Agent, AgentState};
+use crate::kalah::{Evaluator, ValuationFn};
+use crate::transposition::SharedTable;
+use crate::{Board, Move};
 
-pub struct PVSAgent {
+use super::search::{
+    minimax_search, new_shared_minimax_search_state, MinimaxSearchState, SharedMinimaxSearchState, StopRule,
+};
+use crate::agent::{
+    fallback_move, lock_search_when, worker_panic, Agent, AgentState, Watchdog, FIRST_DEPTH_TIMEOUT, WATCHDOG_TIMEOUT,
+};
+
+/// agent searching with PVS, valuing the leaves with the evaluator E
+pub struct PVSAgent<E = ValuationFn> {
     state: AgentState,
 
     board: Board,
 
     search_state: Option<SharedMinimaxSearchState>,
 
-    valuation_fn: ValuationFn,
+    // cleared to cancel the current search, or by the search itself once it's done
+    search_active: Arc<AtomicBool>,
+
+    watchdog: Option<Watchdog>,
+
+    // search thread, to notice if it panicked
+    worker: Option<JoinHandle<()>>,
+
+    // last move read from search_state, in case it's locked when asked again
+    last_best_move: Option<Move>,
+
+    // when the current search was started
+    go_time: Instant,
+
+    evaluator: E,
+
+    // size of the searches' EvalCache, 0 for none
+    eval_cache_mb: usize,
+
+    // shared by all searches, entries of earlier moves stay until they get replaced
+    transposition_table: Option<Arc<SharedTable>>,
+
+    // when the searches may end on their own
+    stop_rule: StopRule,
 }
 
 impl PVSAgent {
-    #[allow(dead_code)]
     pub fn new(board: Board, valuation_fn: ValuationFn) -> Self {
+        PVSAgent::with_evaluator(board, valuation_fn)
+    }
+}
+
+impl<E: Evaluator + Clone + Send + 'static> PVSAgent<E> {
+    /// like new, for evaluators with state (see Evaluator)
+    pub fn with_evaluator(board: Board, evaluator: E) -> Self {
         PVSAgent {
             state: AgentState::Waiting,
             board,
             search_state: None,
-            valuation_fn,
+            search_active: Arc::new(AtomicBool::new(false)),
+            watchdog: None,
+            worker: None,
+            last_best_move: None,
+            go_time: Instant::now(),
+            evaluator,
+            eval_cache_mb: 0,
+            transposition_table: None,
+            stop_rule: StopRule::new(),
+        }
+    }
+
+    /// let the searches look up leaves in an EvalCache of size_mb megabytes before evaluating them (0 for no cache)
+    ///
+    /// only pays off for evaluators that are expensive compared to hashing the board, e.g. Weights with mobility
+    pub fn with_eval_cache(mut self, size_mb: usize) -> Self {
+        self.eval_cache_mb = size_mb;
+        self
+    }
+
+    /// let the searches keep the results of inner nodes in a transposition table of size_mb megabytes (0 for none)
+    ///
+    /// the table lives as long as the agent, so the search for the next move starts with what the last one found
+    pub fn with_transposition_table(mut self, size_mb: usize) -> Self {
+        self.transposition_table = (size_mb > 0).then(|| Arc::new(SharedTable::new(size_mb)));
+        self
+    }
+
+    /// let the searches end on their own according to stop_rule, see StopRule
+    ///
+    /// with a time budget the agent turns to Waiting once the search stopped early, so the move can be played before
+    /// the clock runs out
+    pub fn with_stop_rule(mut self, stop_rule: StopRule) -> Self {
+        self.stop_rule = stop_rule;
+        self
+    }
+
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// recover from a panicked search: log it and start over, playing the best move so far until the new search has
+    /// one
+    fn restart_search(&mut self, err: &str) -> Move {
+        error!("Search thread panicked ({err}), restarting the search");
+
+        let last_best_move = self.last_best_move;
+
+        self.stop();
+        self.go();
+
+        // go forgets the best move so far, but one from a completed depth of the failed search beats the fallback
+        if last_best_move.is_some() {
+            self.last_best_move = last_best_move;
         }
+
+        self.best_move_so_far()
+    }
+
+    // use best move after one ply as a fallback in case we don't complete a single search iteration, which really
+    // should not happen
+    fn best_move_so_far(&mut self) -> Move {
+        *self
+            .last_best_move
+            .get_or_insert_with(|| fallback_move(&self.board, self.evaluator.clone()))
     }
 }
 
-impl Agent for PVSAgent {
+impl<E: Evaluator + Clone + Send + 'static> Agent for PVSAgent<E> {
     fn update_board(&mut self, board: &Board) {
         self.board = board.clone();
     }
@@ -36,18 +139,43 @@ impl Agent for PVSAgent {
     fn get_current_best_move(&mut self) -> Move {
         assert_eq!(self.state, AgentState::Go);
 
-        if !self.search_state.as_ref().unwrap().lock().unwrap().search_active {
-            self.state = AgentState::Waiting;
+        if let Some(err) = worker_panic(&mut self.worker) {
+            return self.restart_search(&err);
         }
 
-        self.search_state
-            .as_ref()
-            .unwrap()
-            .lock()
-            .unwrap()
-            .principal_variation
-            .best_move()
-            .unwrap_or_else(|| self.board.legal_moves(Player::White)[0])
+        // own reference, so the search can be restarted below while the result is still borrowed
+        let search_state = Arc::clone(self.search_state.as_ref().unwrap());
+
+        // give the search a moment to complete its first depth rather than handing out the fallback right away
+        let deadline = self.go_time + FIRST_DEPTH_TIMEOUT;
+        let ready = |search_state: &MinimaxSearchState| {
+            search_state.completed_depth > 0 || !self.search_active.load(Ordering::Relaxed)
+        };
+
+        let locked = match lock_search_when(&search_state, deadline, ready) {
+            Ok(locked) => locked,
+            Err(err) => return self.restart_search(&err),
+        };
+
+        if let Some(search_state) = locked {
+            // until the first depth is done the line is still the one from the last search
+            if search_state.completed_depth > 0 {
+                self.last_best_move = search_state.principal_variation.best_move();
+            }
+
+            // the search publishes its result before clearing search_active, so it's the final one here
+            if !self.search_active.load(Ordering::Relaxed) {
+                self.state = AgentState::Waiting;
+            }
+        }
+
+        if self.state == AgentState::Go && self.watchdog.as_mut().unwrap().is_stuck() {
+            warn!("Search made no progress for {WATCHDOG_TIMEOUT:?}, stopping it and playing the best move so far");
+
+            self.stop();
+        }
+
+        self.best_move_so_far()
     }
 
     fn get_state(&self) -> crate::agent::AgentState {
@@ -55,8 +183,11 @@ impl Agent for PVSAgent {
     }
 
     fn go(&mut self) {
-        // use first legal move as a fallback in case we don't complete a single search iteration, which really should
-        // not happen
+        if !self.board.has_legal_move() {
+            info!("Game is over, nothing to search");
+            self.state = AgentState::Waiting;
+            return;
+        }
 
         let pv = if let Some(ref search_state) = self.search_state {
             search_state.lock().unwrap().principal_variation
@@ -64,12 +195,33 @@ impl Agent for PVSAgent {
             super::Line::new()
         };
 
-        let search_state = new_shared_minimax_search_state(true, pv);
+        let search_state = new_shared_minimax_search_state(pv);
+        let search_active = Arc::new(AtomicBool::new(true));
+
+        let watchdog = Watchdog::new(WATCHDOG_TIMEOUT);
+
+        if let Some(transposition_table) = &self.transposition_table {
+            transposition_table.new_search();
+        }
 
-        minimax_search(&self.board, self.valuation_fn, Arc::clone(&search_state));
+        let worker = minimax_search(
+            &self.board,
+            self.evaluator.clone(),
+            self.eval_cache_mb,
+            self.transposition_table.clone(),
+            self.stop_rule,
+            Arc::clone(&search_state),
+            Arc::clone(&search_active),
+            watchdog.progress(),
+        );
 
         self.state = AgentState::Go;
         self.search_state = Some(search_state);
+        self.search_active = search_active;
+        self.watchdog = Some(watchdog);
+        self.worker = Some(worker);
+        self.last_best_move = None;
+        self.go_time = Instant::now();
     }
 
     fn stop(&mut self) {
@@ -80,9 +232,11 @@ impl Agent for PVSAgent {
 
         self.state = AgentState::Waiting;
 
-        // set search_active to false, then drop reference
-        self.search_state.as_ref().unwrap().lock().unwrap().search_active = false;
+        // cancel the search (if go started one), then drop reference
+        self.search_active.store(false, Ordering::Relaxed);
         self.search_state = None;
+        self.watchdog = None;
+        self.worker = None;
     }
 
     fn ponder(&mut self) {
@@ -1,26 +1,106 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use crate::kalah::valuation::{Valuation, ValuationFn};
-use crate::{Board, Move, Player, LOG_STATS};
+use log::info;
+
+use crate::kalah::valuation::Valuation;
+#[cfg(test)]
+use crate::kalah::valuation::ValuationFn;
+use crate::kalah::{BoardPool, Evaluator, Features};
+use crate::transposition::{Bound, EvalCache, SharedTable};
+use crate::{Board, Move, Player};
 
 /*====================================================================================================================*/
 
 pub type SharedMinimaxSearchState = Arc<Mutex<MinimaxSearchState>>;
 
+/// result of the search so far, the lock is only taken to publish or read it
+///
+/// cancelling the search (or the search announcing that it's done) goes through a separate search_active flag, so the
+/// recursion never has to take the lock
 pub struct MinimaxSearchState {
-    pub search_active: bool,
-
     pub principal_variation: Line,
+
+    // deepest search depth that was completed, 0 while principal_variation is still the fallback
+    pub completed_depth: u32,
+
+    // why the search ended, None while it's still running
+    pub stop_reason: Option<StopReason>,
 }
 
-pub fn new_shared_minimax_search_state(search_active: bool, principal_variation: Line) -> SharedMinimaxSearchState {
+pub fn new_shared_minimax_search_state(principal_variation: Line) -> SharedMinimaxSearchState {
     Arc::new(Mutex::new(MinimaxSearchState {
-        search_active,
         principal_variation,
+        completed_depth: 0,
+        stop_reason: None,
     }))
 }
 
+/// why a search ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// the best move stayed the same for StopRule::stability_threshold depths
+    Stable,
+    /// the time budget ran out, or the agent stopped the search (which it does once its time is up)
+    Time,
+    /// the node budget ran out
+    Nodes,
+    /// the root is decided, or the search found a certain win or loss
+    Proven,
+}
+
+/// when the search may end on its own before the agent stops it
+///
+/// without a budget the search is an analysis that deepens until it's stopped or finds a proven result, with a time or
+/// node budget it also ends once either is used up after a completed depth, or once the best move has been stable for
+/// a while
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StopRule {
+    /// time the agent has for its move
+    pub time_budget: Option<Duration>,
+
+    /// nodes the search may visit, unlike the time budget it ends the search at the same depth on every machine
+    ///
+    /// only checked after a completed depth, so the last depth can take the search well past it
+    pub max_nodes: Option<u64>,
+
+    /// completed depths in a row that have to agree with the depth before on the best move to end the search early
+    pub stability_threshold: u32,
+
+    /// largest change of a non-terminal value between those depths that still counts as agreeing
+    pub stability_delta: i32,
+}
+
+impl StopRule {
+    /// no time or node budget, stability threshold 4 and delta 1
+    pub fn new() -> Self {
+        StopRule {
+            time_budget: None,
+            max_nodes: None,
+            stability_threshold: 4,
+            stability_delta: 1,
+        }
+    }
+
+    /// whether value at the current depth agrees with last_value, the one of the depth before
+    fn is_stable(&self, last_value: Valuation, value: Valuation) -> bool {
+        match (last_value, value) {
+            (Valuation::NonTerminal { value: last_value }, Valuation::NonTerminal { value }) => {
+                (value as i64 - last_value as i64).abs() <= self.stability_delta as i64
+            }
+            _ => last_value == value,
+        }
+    }
+}
+
+impl Default for StopRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /*====================================================================================================================*/
 
 const LINE_MAX_SIZE: usize = 100;
@@ -76,29 +156,104 @@ impl Line {
         }
     }
 
-    pub fn iter(&self) -> std::slice::Iter<Move> {
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
         self.moves[0..(self.len as usize)].iter()
     }
 }
 
+impl Default for Line {
+    fn default() -> Self {
+        Line::new()
+    }
+}
+
+/*====================================================================================================================*/
+
+// replay the principal variation after every completed depth in debug builds, see verify_pv
+const VERIFY_PV: bool = cfg!(debug_assertions);
+
+/// check that pv is a legal line from board and that its leaf evaluates to value with evaluator
+///
+/// the leaf value is taken back to the root the same way the search does it, i.e. negated whenever the turn changes and
+/// with one more ply for every move
+fn verify_pv(board: &Board, pv: &Line, evaluator: &impl Evaluator, value: Valuation) -> Result<(), String> {
+    let mut board = board.clone();
+    let mut turn_changes = Vec::new();
+
+    for (ply, &move_) in pv.iter().enumerate() {
+        if !board.is_legal_move(move_) {
+            return Err(format!("move {move_} at ply {ply} is illegal in position\n{board}"));
+        }
+
+        let their_turn = !board.apply_move(move_);
+        if their_turn {
+            board.flip_board();
+        }
+
+        turn_changes.push(their_turn);
+    }
+
+    let leaf_value = turn_changes
+        .iter()
+        .rev()
+        .fold(evaluator.evaluate_side_to_move(&board), |value, &their_turn| {
+            if their_turn { -value } else { value }.increase_plies()
+        });
+
+    if leaf_value != value {
+        return Err(format!(
+            "leaf of the line evaluates to {leaf_value}, but the search reported {value}"
+        ));
+    }
+
+    Ok(())
+}
+
 /*====================================================================================================================*/
 
-struct PVSWorker {
+struct PVSWorker<E> {
     search_state: Arc<Mutex<MinimaxSearchState>>,
 
-    valuation_fn: ValuationFn,
+    search_active: Arc<AtomicBool>,
+
+    // board_after_move of every node on the current line, see BoardPool
+    scratch_boards: BoardPool,
+
+    evaluator: E,
+
+    // consulted before evaluating a leaf, if the agent asked for one
+    eval_cache: Option<EvalCache>,
+
+    // results of inner nodes by zobrist_hash, if the agent asked for one
+    transposition_table: Option<Arc<SharedTable>>,
+
+    stop_rule: StopRule,
 
     total_nodes_visited: u64,
 
+    // total_nodes_visited, published for the agent's watchdog
+    progress: Arc<AtomicU64>,
+
     start_t: Instant,
 }
 
-impl PVSWorker {
-    pub fn new(valuation_fn: ValuationFn, search_state: SharedMinimaxSearchState) -> Self {
+impl<E: Evaluator> PVSWorker<E> {
+    pub fn new(
+        evaluator: E,
+        search_state: SharedMinimaxSearchState,
+        search_active: Arc<AtomicBool>,
+        progress: Arc<AtomicU64>,
+    ) -> Self {
         PVSWorker {
             search_state,
-            valuation_fn,
+            search_active,
+            scratch_boards: BoardPool::new(),
+            evaluator,
+            eval_cache: None,
+            transposition_table: None,
+            stop_rule: StopRule::new(),
             total_nodes_visited: 0,
+            progress,
             start_t: Instant::now(),
         }
     }
@@ -107,6 +262,32 @@ impl PVSWorker {
         self.total_nodes_visited as f64 / self.start_t.elapsed().as_secs_f64()
     }
 
+    /// for the log line at the end of the search
+    fn eval_cache_stats(&self) -> String {
+        match &self.eval_cache {
+            Some(eval_cache) => format!(", eval cache hit rate {:.1}%", 100.0 * eval_cache.hit_rate()),
+            None => String::new(),
+        }
+    }
+
+    /// value of a leaf, taken from the eval cache if it has the position
+    fn evaluate(&mut self, board: &Board) -> Valuation {
+        let Some(eval_cache) = self.eval_cache.as_mut() else {
+            return self.evaluator.evaluate_side_to_move(board);
+        };
+
+        let key = board.zobrist_hash();
+
+        if let Some(value) = eval_cache.probe(key) {
+            return value;
+        }
+
+        let value = self.evaluator.evaluate_side_to_move(board);
+        eval_cache.store(key, value);
+
+        value
+    }
+
     fn extend_pv(&mut self, board: &Board, pv: &mut Line) -> Valuation {
         use Valuation::{TerminalBlackWin, TerminalWhiteWin};
 
@@ -139,43 +320,106 @@ impl PVSWorker {
         beta: Valuation,
         principal_line: &mut Line,
     ) -> Valuation {
-        if !self.search_state.lock().unwrap().search_active {
+        if !self.search_active.load(Ordering::Relaxed) {
             // search has been ended, search results don't matter anymore, exit thread asap
             return Valuation::NonTerminal { value: 0 };
         }
 
         self.total_nodes_visited += 1;
+        self.progress.store(self.total_nodes_visited, Ordering::Relaxed);
 
-        if remaining_depth == 0 || !board.has_legal_move() {
+        // decided positions are leaves like finished games: the valuations treat them as terminal, so searching on
+        // would only add the plies to the horizon to their distance
+        if remaining_depth == 0 || board.is_decided().is_some() {
             principal_line.reset();
-            return (self.valuation_fn)(board);
+            return self.evaluate(board);
+        }
+
+        // a node whose moves all fail low must not pass on a stale line
+        principal_line.reset();
+
+        let key = self.transposition_table.as_ref().map(|_| board.zobrist_hash());
+        let entry = self
+            .transposition_table
+            .as_ref()
+            .zip(key)
+            .and_then(|(table, key)| table.probe(key));
+
+        if let Some(entry) = entry.filter(|entry| entry.depth as u32 >= remaining_depth) {
+            // only values that fail high or low can be taken as they are: the parent doesn't put those into its line,
+            // while an exact value inside the window would need the line it came from. For the same reason the
+            // window isn't tightened, a node searched in a narrower window could come back without a line for a
+            // value that is still inside this one.
+            let usable = match entry.bound {
+                Bound::Exact => entry.value <= alpha || entry.value >= beta,
+                Bound::Lower => entry.value >= beta,
+                Bound::Upper => entry.value <= alpha,
+            };
+
+            if usable {
+                return entry.value;
+            }
         }
 
         let mut best_value = Valuation::TerminalBlackWin { plies: 0 };
+        let mut best_move = None;
         let mut alpha = alpha;
+        let original_alpha = alpha;
 
-        let mut board_after_move = board.clone();
+        let mut board_after_move = self.scratch_boards.take(board);
 
         let mut search_line = Line::new();
 
-        for house in 0..board.h() {
-            let move_ = Move::new(house, Player::White);
+        let mut legal_mask = board.legal_mask(Player::White);
 
-            if !board.is_legal_move(move_) {
-                continue;
-            }
+        // best move of an earlier search of this position first, it's the most likely to cut off again
+        let tt_house = entry
+            .and_then(|entry| entry.best_move)
+            .map(|move_| move_.house())
+            .filter(|&house| legal_mask & (1 << house) != 0);
+
+        if let Some(house) = tt_house {
+            legal_mask &= !(1 << house);
+        }
+
+        // then the others, lowest set bit is the next legal house
+        let houses = tt_house.into_iter().chain(std::iter::from_fn(|| {
+            (legal_mask != 0).then(|| {
+                let house = legal_mask.trailing_zeros() as u8;
+                legal_mask &= legal_mask - 1;
+                house
+            })
+        }));
+
+        for house in houses {
+            let move_ = Move::new(house, Player::White);
 
-            // let mut board_after_move = board.clone();
             board_after_move.clone_from(board);
             let their_turn = !board_after_move.apply_move(move_);
 
+            // the child counts plies from its own position, so the window has to be shifted by the ply of this move,
+            // otherwise a bound on a forced win or loss could pass for its exact value
+            let (child_alpha, child_beta) = (alpha.decrease_plies(), beta.decrease_plies());
+
             let value = if their_turn {
                 // opponent move: flip board, alpha, beta to their perspective and flip returned value to ours
                 board_after_move.flip_board();
-                -self.minimax(&board_after_move, remaining_depth - 1, -beta, -alpha, &mut search_line)
+                -self.minimax(
+                    &board_after_move,
+                    remaining_depth - 1,
+                    -child_beta,
+                    -child_alpha,
+                    &mut search_line,
+                )
             } else {
                 // bonus move: don't decrease depth
-                self.minimax(&board_after_move, remaining_depth, alpha, beta, &mut search_line)
+                self.minimax(
+                    &board_after_move,
+                    remaining_depth,
+                    child_alpha,
+                    child_beta,
+                    &mut search_line,
+                )
             }
             .increase_plies();
 
@@ -185,20 +429,71 @@ impl PVSWorker {
 
             if value > beta {
                 // beta cutoff, return early
+                best_move = Some(move_);
                 break;
             }
 
             if best_value > alpha {
                 alpha = value;
+                best_move = Some(move_);
 
                 // we beat the current pv: overwrite (relative) pv with current line
                 principal_line.overwrite(move_, &search_line);
             }
         }
 
+        self.scratch_boards.give_back(board_after_move);
+
+        // a cancelled search returns made up values, they must not end up in the table
+        if let (Some(table), Some(key), true) = (
+            self.transposition_table.as_ref(),
+            key,
+            self.search_active.load(Ordering::Relaxed),
+        ) {
+            // a value equal to beta can come from a child that failed low, so it's only a bound
+            let bound = if best_value <= original_alpha {
+                Bound::Upper
+            } else if best_value >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+
+            table.store(
+                key,
+                remaining_depth.min(u8::MAX as u32) as u8,
+                bound,
+                best_value,
+                best_move,
+            );
+        }
+
         best_value
     }
 
+    /// best move of a root that is already decided, judged one ply ahead
+    ///
+    /// the outcome can't change anymore, any move keeps it decided, so deepening would only burn the clock
+    fn decided_root(&self, board: &Board) -> (Move, Valuation) {
+        board
+            .legal_moves(Player::White)
+            .into_iter()
+            .map(|move_| {
+                let mut board_after_move = board.clone();
+
+                let value = if board_after_move.apply_move(move_) {
+                    self.evaluator.evaluate_side_to_move(&board_after_move)
+                } else {
+                    board_after_move.flip_board();
+                    -self.evaluator.evaluate_side_to_move(&board_after_move)
+                };
+
+                (move_, value.increase_plies())
+            })
+            .max_by_key(|&(_move, value)| value)
+            .unwrap()
+    }
+
     pub fn start_search(self, board: Board) {
         use Valuation::{TerminalBlackWin, TerminalWhiteWin};
 
@@ -208,14 +503,34 @@ impl PVSWorker {
 
         let mut current_best_value = Valuation::TerminalBlackWin { plies: 0 };
 
+        if board.is_decided().is_some() {
+            let (best_move, best_value) = me.decided_root(&board);
+            info!("Root is already decided, playing {best_move} with value {best_value}");
+            {
+                let mut search_state = me.search_state.lock().unwrap();
+                search_state.principal_variation.overwrite(best_move, &Line::new());
+                search_state.completed_depth = 1;
+                search_state.stop_reason = Some(StopReason::Proven);
+            }
+            me.search_active.store(false, Ordering::Relaxed);
+            return;
+        }
+
         let alpha = TerminalBlackWin { plies: 0 };
         let beta = TerminalWhiteWin { plies: 0 };
 
         let mut pv = Line::new();
 
+        // completed depths in a row whose best move and value agreed with the depth before, see StopRule
+        let mut stability = 0;
+        let mut last_best: Option<(Move, Valuation)> = None;
+
         let max_depth = 6;
         // {
         for max_depth in 1.. {
+            // one board per ply, bonus moves make the pool grow past that on its own
+            me.scratch_boards.reserve(&board, max_depth as usize);
+
             if max_depth > LINE_MAX_SIZE as u32 {
                 panic!("Tried searching to depth {max_depth}, but MOVE_LINE_MAX is {LINE_MAX_SIZE}");
             }
@@ -224,89 +539,533 @@ impl PVSWorker {
 
             let best_value = me.minimax(&board, max_depth, alpha, beta, &mut pv);
 
-            if !me.search_state.lock().unwrap().search_active {
-                if LOG_STATS {
-                    println!("--------------------------------------------");
-                    println!("* Minimax worker exited after max_depth {}", max_depth - 1);
-                    println!("* Best move had value {current_best_value:?}");
-                    println!("* NPS: {:.2e} ({:?})", me.current_nps(), me.start_t.elapsed());
-                    println!("--------------------------------------------\n");
-                }
+            if !me.search_active.load(Ordering::Relaxed) {
+                me.search_state
+                    .lock()
+                    .unwrap()
+                    .stop_reason
+                    .get_or_insert(StopReason::Time);
+                info!(
+                    "PVS worker exited after max_depth {}, best move had value {current_best_value:?}, NPS: {:.2e} ({:?}){}",
+                    max_depth - 1,
+                    me.current_nps(),
+                    me.start_t.elapsed(),
+                    me.eval_cache_stats()
+                );
                 return;
             }
 
-            if let Valuation::TerminalWhiteWin { plies } = best_value {
-                if LOG_STATS {
-                    println!("--------------------------------------------");
-                    println!("* Found certain win in {plies} plies");
-                    println!("--------------------------------------------\n");
+            if VERIFY_PV {
+                if let Err(err) = verify_pv(&board, &pv, &me.evaluator, best_value) {
+                    let line: Vec<String> = pv.iter().map(|move_| move_.to_string()).collect();
+                    panic!("Inconsistent PV [{}] at depth {max_depth}: {err}", line.join(", "));
                 }
+            }
+
+            // a shorter win (or loss) than the one found could still be beyond the horizon, see distance_is_exact
+            let exact = best_value.distance_is_exact(max_depth);
+
+            if let (Valuation::TerminalWhiteWin { plies }, true) = (best_value, exact) {
+                info!("Found certain win in {plies} plies");
                 {
                     let mut search_state = me.search_state.lock().unwrap();
                     search_state.principal_variation = pv;
-                    search_state.search_active = false;
+                    search_state.completed_depth = max_depth;
+                    search_state.stop_reason = Some(StopReason::Proven);
                 }
+                me.search_active.store(false, Ordering::Relaxed);
                 return;
             }
 
-            if let TerminalBlackWin { plies } = best_value {
+            if let (TerminalBlackWin { plies }, true) = (best_value, exact) {
                 // all moves are certain losses, pick the one with the most plies and exit
-                if LOG_STATS {
-                    println!("--------------------------------------------");
-                    println!("* Found certain loss in {plies} plies");
-                    println!("--------------------------------------------");
-                    println!();
-                }
+                info!("Found certain loss in {plies} plies");
                 {
                     let mut search_state = me.search_state.lock().unwrap();
                     search_state.principal_variation = pv;
-                    search_state.search_active = false;
+                    search_state.completed_depth = max_depth;
+                    search_state.stop_reason = Some(StopReason::Proven);
                 }
+                me.search_active.store(false, Ordering::Relaxed);
                 return;
             }
 
-            me.search_state.lock().unwrap().principal_variation = pv;
+            let best_move = pv.best_move().unwrap();
+
+            stability = match last_best {
+                Some((last_move, last_value))
+                    if last_move == best_move && me.stop_rule.is_stable(last_value, best_value) =>
+                {
+                    stability + 1
+                }
+                _ => 0,
+            };
+            last_best = Some((best_move, best_value));
+
+            // only a search with a budget ends early, an analysis goes on until it's stopped
+            let StopRule {
+                time_budget, max_nodes, ..
+            } = me.stop_rule;
+
+            let stop_reason = if time_budget.is_none() && max_nodes.is_none() {
+                None
+            } else if stability >= me.stop_rule.stability_threshold {
+                Some(StopReason::Stable)
+            } else if matches!(max_nodes, Some(max_nodes) if me.total_nodes_visited >= max_nodes) {
+                Some(StopReason::Nodes)
+            } else if matches!(time_budget, Some(time_budget) if me.start_t.elapsed() >= time_budget) {
+                Some(StopReason::Time)
+            } else {
+                None
+            };
+
+            {
+                let mut search_state = me.search_state.lock().unwrap();
+                search_state.principal_variation = pv;
+                search_state.completed_depth = max_depth;
+                search_state.stop_reason = stop_reason;
+            }
             current_best_value = best_value;
+
+            if let Some(stop_reason) = stop_reason {
+                me.search_active.store(false, Ordering::Relaxed);
+                info!(
+                    "PVS worker stopped ({stop_reason:?}) after max_depth {max_depth}, best move {best_move} had value {best_value:?}, NPS: {:.2e} ({:?}){}",
+                    me.current_nps(),
+                    me.start_t.elapsed(),
+                    me.eval_cache_stats()
+                );
+                return;
+            }
         }
 
-        me.search_state.lock().unwrap().search_active = false;
+        me.search_active.store(false, Ordering::Relaxed);
 
-        if LOG_STATS {
-            println!("--------------------------------------------");
-            println!("* Minimax worker exited after search depth {max_depth}");
-            println!(
-                "* Best move {} had value {:?}",
-                me.search_state.lock().unwrap().principal_variation.best_move().unwrap(),
-                current_best_value
-            );
-            println!("* NPS: {:.2e} ({:?})", me.current_nps(), me.start_t.elapsed());
-            println!("--------------------------------------------\n");
-        }
+        info!(
+            "PVS worker exited after search depth {max_depth}, best move {} had value {:?}, NPS: {:.2e} ({:?}){}",
+            me.search_state.lock().unwrap().principal_variation.best_move().unwrap(),
+            current_best_value,
+            me.current_nps(),
+            me.start_t.elapsed(),
+            me.eval_cache_stats()
+        );
     }
 }
 
 /*====================================================================================================================*/
 
-pub fn minimax_search(board: &Board, valuation_fn: ValuationFn, search_state: SharedMinimaxSearchState) {
+/// search board in a new thread, with an EvalCache of eval_cache_mb megabytes unless that is 0 and with
+/// transposition_table if there is one, until stop_rule ends it or search_active gets cleared
+#[allow(clippy::too_many_arguments)]
+pub fn minimax_search<E: Evaluator + Send + 'static>(
+    board: &Board,
+    evaluator: E,
+    eval_cache_mb: usize,
+    transposition_table: Option<Arc<SharedTable>>,
+    stop_rule: StopRule,
+    search_state: SharedMinimaxSearchState,
+    search_active: Arc<AtomicBool>,
+    progress: Arc<AtomicU64>,
+) -> JoinHandle<()> {
     assert!(
         board.has_legal_move(),
         "Called minimax_search on board with no legal moves"
     );
 
-    let t_handle;
+    // the worker shuts down when search_active gets cleared, the handle is only for noticing if it panicked (dropping
+    // it detaches the worker)
+    std::thread::spawn({
+        let mut board = board.clone();
+        // cheap to keep up to date and lets the seed based valuations skip summing up the houses at every leaf, the
+        // legal masks save scanning the houses for has_legal_move and move generation at every node. The tables are
+        // keyed by zobrist_hash, which is O(1) when tracked.
+        let features = Features::HOUSE_SEEDS | Features::LEGAL_MASK;
+        board.track_features(match transposition_table.is_some() || eval_cache_mb > 0 {
+            true => features | Features::ZOBRIST,
+            false => features,
+        });
+        move || {
+            let mut worker = PVSWorker::new(evaluator, search_state, search_active, progress);
+            worker.eval_cache = (eval_cache_mb > 0).then(|| EvalCache::new(eval_cache_mb));
+            worker.transposition_table = transposition_table;
+            worker.stop_rule = stop_rule;
+            worker.start_search(board);
+        }
+    })
+}
+
+/// value of board after a single fixed depth search, for tests comparing the searches
+#[cfg(test)]
+pub fn search_value(board: &Board, depth: u32, valuation_fn: ValuationFn) -> Valuation {
+    search_line(board, depth, valuation_fn).0
+}
+
+/// value and principal variation of board after a single fixed depth search with evaluator
+#[cfg(test)]
+pub fn search_line<E: Evaluator>(board: &Board, depth: u32, evaluator: E) -> (Valuation, Vec<Move>) {
+    let search_state = new_shared_minimax_search_state(Line::new());
+    let search_active = Arc::new(AtomicBool::new(true));
+    let mut worker = PVSWorker::new(evaluator, search_state, search_active, Arc::new(AtomicU64::new(0)));
+
+    let alpha = Valuation::TerminalBlackWin { plies: 0 };
+    let beta = Valuation::TerminalWhiteWin { plies: 0 };
+
+    let mut pv = Line::new();
+    let value = worker.minimax(board, depth, alpha, beta, &mut pv);
+
+    (value, pv.iter().copied().collect())
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicU64};
+    use std::sync::Arc;
+
+    use super::{
+        minimax_search, new_shared_minimax_search_state, search_value, verify_pv, Line, PVSWorker, StopReason, StopRule,
+    };
+    use crate::kalah::valuation::{store_diff_valuation, Valuation};
+    use crate::{Board, Move, Player};
+
+    // exhaustive negamax to the end of the game (or the point it's decided), so wins and losses have their exact
+    // distance: the shortest win, or the longest loss
+    fn solve(board: &Board) -> Valuation {
+        if board.is_decided().is_some() {
+            return store_diff_valuation(board);
+        }
+
+        board
+            .legal_moves(Player::White)
+            .into_iter()
+            .map(|move_| {
+                let mut board = board.clone();
+
+                if board.apply_move(move_) {
+                    solve(&board)
+                } else {
+                    board.flip_board();
+                    -solve(&board)
+                }
+                .increase_plies()
+            })
+            .max()
+            .unwrap()
+    }
+
+    /// deepen like start_search until the value is a win or loss at its exact distance
+    fn search_until_exact(board: &Board) -> Valuation {
+        (1..)
+            .map(|depth| (depth, search_value(board, depth, store_diff_valuation)))
+            .find(|(depth, value)| value.distance_is_exact(*depth))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn test_win_distance() {
+        use rand::seq::SliceRandom;
+
+        // White (to move) wins in 2 plies: house 3 lands in the store, then house 2 captures the 3 seeds opposite of
+        // house 3, which gives White 7 of the 9 seeds. Neither move on its own is enough, and Black has no say in it.
+        let board = Board::from_kgp("<3, 2, 0, 0, 1, 1, 3, 1, 1>");
+        assert_eq!(solve(&board), Valuation::TerminalWhiteWin { plies: 2 });
+        assert_eq!(search_until_exact(&board), Valuation::TerminalWhiteWin { plies: 2 });
+
+        let mut rng = rand::thread_rng();
+        let mut num_checked = 0;
+
+        // positions late in random games on a small board, where forced results are common
+        while num_checked < 50 {
+            let mut board = Board::new(3, 3);
+
+            for _ in 0..6 {
+                if !board.has_legal_move() {
+                    break;
+                }
+
+                let move_ = *board.legal_moves(Player::White).choose(&mut rng).unwrap();
 
-    {
-        // let worker_board = board.clone();
+                if !board.apply_move(move_) {
+                    board.flip_board();
+                }
+            }
+
+            if board.is_decided().is_some() {
+                continue;
+            }
+
+            let exact = solve(&board);
 
-        t_handle = std::thread::spawn({
-            let board = board.clone();
-            move || {
-                let worker: PVSWorker = PVSWorker::new(valuation_fn, search_state);
-                worker.start_search(board);
+            if let Valuation::TerminalWhiteWin { .. } | Valuation::TerminalBlackWin { .. } = exact {
+                assert_eq!(search_until_exact(&board), exact, "{}", board.to_kgp());
+                num_checked += 1;
             }
+        }
+    }
+
+    #[test]
+    fn test_decided_root() {
+        // our store holds 5 of the 9 seeds, so the game is won no matter what, and lost when flipped
+        let won = Board::from_kgp("<3, 5, 0, 1, 1, 1, 1, 0, 0>");
+        let mut lost = won.clone();
+        lost.flip_board();
+
+        for board in [won, lost] {
+            let search_state = new_shared_minimax_search_state(Line::new());
+            let search_active = Arc::new(AtomicBool::new(true));
+
+            let worker = minimax_search(
+                &board,
+                store_diff_valuation,
+                0,
+                None,
+                StopRule::new(),
+                Arc::clone(&search_state),
+                Arc::clone(&search_active),
+                Arc::new(AtomicU64::new(0)),
+            );
+
+            // the worker gives up right away instead of deepening until it gets stopped
+            worker.join().unwrap();
+            assert!(!search_active.load(std::sync::atomic::Ordering::Relaxed));
+
+            let search_state = search_state.lock().unwrap();
+            assert_eq!(search_state.completed_depth, 1);
+            assert_eq!(search_state.stop_reason, Some(StopReason::Proven));
+
+            let best_move = search_state.principal_variation.best_move().unwrap();
+            assert!(board.is_legal_move(best_move));
+
+            let mut board_after_move = board.clone();
+            board_after_move.apply_move(best_move);
+            assert_eq!(board_after_move.is_decided(), board.is_decided());
+        }
+    }
+
+    #[test]
+    fn test_stop_rule() {
+        use std::sync::atomic::Ordering;
+        use std::time::Duration;
+
+        let board = Board::new(6, 4);
+
+        let search = |stop_rule: StopRule| {
+            let search_state = new_shared_minimax_search_state(Line::new());
+            let search_active = Arc::new(AtomicBool::new(true));
+
+            let worker = minimax_search(
+                &board,
+                store_diff_valuation,
+                0,
+                None,
+                stop_rule,
+                Arc::clone(&search_state),
+                Arc::clone(&search_active),
+                Arc::new(AtomicU64::new(0)),
+            );
+
+            (search_state, search_active, worker)
+        };
+
+        // any change of the value counts as stable, so it ends once two depths in a row agree on the move
+        let stable = StopRule {
+            time_budget: Some(Duration::from_secs(60)),
+            max_nodes: None,
+            stability_threshold: 2,
+            stability_delta: i32::MAX,
+        };
+        let (search_state, search_active, worker) = search(stable);
+        worker.join().unwrap();
+        assert!(!search_active.load(Ordering::Relaxed));
+
+        let search_state = search_state.lock().unwrap();
+        assert_eq!(search_state.stop_reason, Some(StopReason::Stable));
+        assert!(search_state.completed_depth >= 3);
+        drop(search_state);
+
+        // a used up budget ends it after the first depth
+        let (search_state, _, worker) = search(StopRule {
+            time_budget: Some(Duration::ZERO),
+            stability_threshold: u32::MAX,
+            ..stable
+        });
+        worker.join().unwrap();
+
+        let search_state = search_state.lock().unwrap();
+        assert_eq!(search_state.stop_reason, Some(StopReason::Time));
+        assert_eq!(search_state.completed_depth, 1);
+        drop(search_state);
+
+        // an analysis doesn't stop early, no matter how stable it is
+        let (search_state, search_active, worker) = search(StopRule {
+            time_budget: None,
+            stability_threshold: 0,
+            ..stable
         });
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(search_active.load(Ordering::Relaxed));
+        assert_eq!(search_state.lock().unwrap().stop_reason, None);
+
+        search_active.store(false, Ordering::Relaxed);
+        worker.join().unwrap();
+        assert_eq!(search_state.lock().unwrap().stop_reason, Some(StopReason::Time));
+    }
+
+    #[test]
+    fn test_max_nodes() {
+        let board = Board::from_kgp("<6, 2, 3, 0, 4, 1, 0, 2, 1, 3, 0, 5, 2, 1, 0>");
+
+        let search = || {
+            let search_state = new_shared_minimax_search_state(Line::new());
+
+            let worker = minimax_search(
+                &board,
+                store_diff_valuation,
+                0,
+                None,
+                StopRule {
+                    max_nodes: Some(20_000),
+                    stability_threshold: u32::MAX,
+                    ..StopRule::new()
+                },
+                Arc::clone(&search_state),
+                Arc::new(AtomicBool::new(true)),
+                Arc::new(AtomicU64::new(0)),
+            );
+            worker.join().unwrap();
+
+            let search_state = search_state.lock().unwrap();
+            assert_eq!(search_state.stop_reason, Some(StopReason::Nodes));
+
+            let line: Vec<Move> = search_state.principal_variation.iter().copied().collect();
+            (search_state.completed_depth, line)
+        };
+
+        // no clock involved, so the same budget ends at the same depth with the same line every time
+        let (completed_depth, line) = search();
+        assert!(completed_depth > 1);
+        assert_eq!(search(), (completed_depth, line));
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        use crate::kalah::Weights;
+        use crate::transposition::EvalCache;
+
+        let board = Board::from_kgp("<6, 2, 3, 0, 4, 1, 0, 2, 1, 3, 0, 5, 2, 1, 0>");
+        let mut weights = Weights::new(6);
+        weights.mobility = 3;
+
+        let search = |eval_cache: Option<EvalCache>| {
+            let search_state = new_shared_minimax_search_state(Line::new());
+            let search_active = Arc::new(AtomicBool::new(true));
+            let mut worker = PVSWorker::new(
+                weights.clone(),
+                search_state,
+                search_active,
+                Arc::new(AtomicU64::new(0)),
+            );
+            worker.eval_cache = eval_cache;
+
+            let mut pv = Line::new();
+            let alpha = Valuation::TerminalBlackWin { plies: 0 };
+            let beta = Valuation::TerminalWhiteWin { plies: 0 };
+            let value = worker.minimax(&board, 6, alpha, beta, &mut pv);
+
+            (value, pv.best_move(), worker.eval_cache)
+        };
+
+        let (value, best_move, _) = search(None);
+        let (cached_value, cached_best_move, eval_cache) = search(Some(EvalCache::new(1)));
+
+        // cached values are the evaluator's, so the search can't tell the difference
+        assert_eq!((cached_value, cached_best_move), (value, best_move));
+
+        // bonus moves and different move orders reach the same leaves
+        assert!(eval_cache.unwrap().hit_rate() > 0.0);
+    }
+
+    #[test]
+    fn test_transposition_table() {
+        use std::sync::atomic::Ordering;
+
+        use crate::transposition::SharedTable;
+
+        // solved from the start within a few depths, with plenty of transpositions through bonus moves on the way
+        let board = Board::new(4, 3);
+
+        let solve = |transposition_table: Option<Arc<SharedTable>>| {
+            let search_state = new_shared_minimax_search_state(Line::new());
+            let progress = Arc::new(AtomicU64::new(0));
+
+            let worker = minimax_search(
+                &board,
+                store_diff_valuation,
+                0,
+                transposition_table,
+                StopRule::new(),
+                Arc::clone(&search_state),
+                Arc::new(AtomicBool::new(true)),
+                Arc::clone(&progress),
+            );
+            worker.join().unwrap();
+
+            let search_state = search_state.lock().unwrap();
+            assert_eq!(search_state.stop_reason, Some(StopReason::Proven));
+
+            (
+                search_state.principal_variation.best_move(),
+                search_state.completed_depth,
+                progress.load(Ordering::Relaxed),
+            )
+        };
+
+        let (best_move, depth, nodes) = solve(None);
+        let (tt_best_move, tt_depth, tt_nodes) = solve(Some(Arc::new(SharedTable::new(1))));
+
+        // same result (and in debug builds start_search checks the line it came with), for far fewer nodes
+        assert_eq!((tt_best_move, tt_depth), (best_move, depth));
+        assert!(2 * tt_nodes < nodes, "{tt_nodes} nodes with the table, {nodes} without");
     }
 
-    // detach worker thread; will get shut down automatically when search_active gets set to false
-    drop(t_handle);
+    #[test]
+    fn test_verify_pv() {
+        use Valuation::{TerminalBlackWin, TerminalWhiteWin};
+
+        for board in [
+            Board::new(4, 3),
+            Board::from_kgp("<6, 2, 3, 0, 4, 1, 0, 2, 1, 3, 0, 5, 2, 1, 0>"),
+        ] {
+            for depth in 1..=6 {
+                let search_state = new_shared_minimax_search_state(Line::new());
+                let search_active = Arc::new(AtomicBool::new(true));
+                let mut worker = PVSWorker::new(
+                    store_diff_valuation,
+                    search_state,
+                    search_active,
+                    Arc::new(AtomicU64::new(0)),
+                );
+
+                let mut pv = Line::new();
+                let alpha = TerminalBlackWin { plies: 0 };
+                let beta = TerminalWhiteWin { plies: 0 };
+                let value = worker.minimax(&board, depth, alpha, beta, &mut pv);
+
+                if let Err(err) = verify_pv(&board, &pv, &store_diff_valuation, value) {
+                    panic!("depth {depth}: {err}");
+                }
+            }
+        }
+
+        // second house lands in the store, so moving it again right after is illegal
+        let board = Board::new(4, 3);
+        let mut pv = Line::new();
+        let mut tail = Line::new();
+        tail.overwrite(Move::new(1, Player::White), &Line::new());
+        pv.overwrite(Move::new(1, Player::White), &tail);
+
+        assert!(verify_pv(&board, &pv, &store_diff_valuation, store_diff_valuation(&board)).is_err());
+    }
 }
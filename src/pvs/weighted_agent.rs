@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use log::warn;
+
+use crate::kalah::Weights;
+use crate::Board;
+
+use super::PVSAgent;
+
+/// PVS agent valuing positions with tuned Weights
+pub type WeightedAgent = PVSAgent<Weights>;
+
+impl PVSAgent<Weights> {
+    /// agent with the weights from the file at path (see Weights for the format)
+    ///
+    /// a file that can't be read, is malformed or has weights for a different number of houses is logged and replaced
+    /// by the default weights, so a bad file doesn't keep the agent from playing
+    pub fn from_file(path: impl AsRef<Path>, board: Board) -> Self {
+        let path = path.as_ref();
+
+        let weights = Weights::load(path).and_then(|weights| {
+            if weights.houses.len() == board.h() as usize {
+                Ok(weights)
+            } else {
+                Err(format!(
+                    "{} house weights for a board with {} houses",
+                    weights.houses.len(),
+                    board.h()
+                ))
+            }
+        });
+
+        let weights = weights.unwrap_or_else(|err| {
+            warn!("Using default weights, failed to load {}: {err}", path.display());
+            Weights::new(board.h())
+        });
+
+        PVSAgent::with_evaluator(board, weights)
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::WeightedAgent;
+    use crate::agent::{Agent, AgentState};
+    use crate::kalah::Weights;
+    use crate::{Board, Player};
+
+    #[test]
+    fn test_weighted_agent_from_file() {
+        let path = std::env::temp_dir().join(format!("kalah_test_weighted_agent_{}.txt", std::process::id()));
+
+        let weights = Weights {
+            houses: vec![1, 2, 3, 4],
+            store: 3,
+            mobility: 1,
+        };
+        weights.save(&path).unwrap();
+
+        let agent = WeightedAgent::from_file(&path, Board::new(4, 3));
+        assert_eq!(*agent.evaluator(), weights);
+
+        // wrong number of houses
+        let agent = WeightedAgent::from_file(&path, Board::new(6, 3));
+        assert_eq!(*agent.evaluator(), Weights::new(6));
+
+        std::fs::write(&path, "houses 1 2 3 4\nstore three\nmobility 1\n").unwrap();
+        let agent = WeightedAgent::from_file(&path, Board::new(4, 3));
+        assert_eq!(*agent.evaluator(), Weights::new(4));
+
+        std::fs::remove_file(&path).unwrap();
+
+        // and it plays
+        let board = Board::new(4, 3);
+        let mut agent = WeightedAgent::from_file(&path, board.clone());
+        agent.go();
+        assert!(board
+            .legal_moves(Player::White)
+            .contains(&agent.get_current_best_move()));
+        agent.stop();
+        assert_eq!(agent.get_state(), AgentState::Waiting);
+    }
+}
@@ -0,0 +1,250 @@
+// tests for the White/Black perspective handling: flip_board, Valuation's Neg and the handoff to the opponent in the
+// searches. Any sign mistake there breaks one of these symmetries.
+
+use rand::seq::SliceRandom;
+
+use crate::kalah::valuation::{Valuation, ValuationFn, VALUATIONS};
+use crate::{Board, Player};
+
+/// all positions of a few random games, always seen from the player to move
+fn random_positions(h: u8, s: u16, num_games: usize) -> Vec<Board> {
+    let mut rng = rand::thread_rng();
+    let mut positions = Vec::new();
+
+    for _ in 0..num_games {
+        let mut board = Board::new(h, s);
+
+        while board.has_legal_move() {
+            positions.push(board.clone());
+
+            let move_ = *board.legal_moves(Player::White).choose(&mut rng).unwrap();
+
+            if !board.apply_move(move_) {
+                board.flip_board();
+            }
+        }
+
+        positions.push(board);
+    }
+
+    positions
+}
+
+fn flipped(board: &Board) -> Board {
+    let mut board = board.clone();
+    board.flip_board();
+    board
+}
+
+#[test]
+fn test_valuation_symmetry() {
+    // all valuations only look at differences between the two sides, so flipping the board has to negate them
+    for board in random_positions(6, 4, 20) {
+        for &(name, valuation_fn) in VALUATIONS {
+            assert_eq!(
+                valuation_fn(&board),
+                -valuation_fn(&flipped(&board)),
+                "{name} on {}",
+                board.to_kgp()
+            );
+        }
+    }
+}
+
+/// plain negamax without any pruning: always flips the board and negates the value for the opponent
+fn negamax(board: &Board, depth: u32, valuation_fn: ValuationFn) -> Valuation {
+    if depth == 0 || board.is_decided().is_some() {
+        return valuation_fn(board);
+    }
+
+    board
+        .legal_moves(Player::White)
+        .into_iter()
+        .map(|move_| {
+            let mut board = board.clone();
+
+            if board.apply_move(move_) {
+                negamax(&board, depth, valuation_fn)
+            } else {
+                board.flip_board();
+                -negamax(&board, depth - 1, valuation_fn)
+            }
+            .increase_plies()
+        })
+        .max()
+        .unwrap()
+}
+
+/// the same search without ever flipping the board: Black's moves are applied as Black's, values stay from White's
+/// perspective and get negated only to let Black pick its best one
+fn search_unflipped(board: &Board, depth: u32, valuation_fn: ValuationFn, player: Player) -> Valuation {
+    if depth == 0 || board.is_decided().is_some() {
+        return match player {
+            Player::White => valuation_fn(board),
+            Player::Black => -valuation_fn(board),
+        };
+    }
+
+    let opponent = match player {
+        Player::White => Player::Black,
+        Player::Black => Player::White,
+    };
+
+    board
+        .legal_moves(player)
+        .into_iter()
+        .map(|move_| {
+            let mut board = board.clone();
+
+            if board.apply_move(move_) {
+                search_unflipped(&board, depth, valuation_fn, player)
+            } else {
+                -search_unflipped(&board, depth - 1, valuation_fn, opponent)
+            }
+            .increase_plies()
+        })
+        .max()
+        .unwrap()
+}
+
+/// equal, except that draws and even positions count the same
+///
+/// both sides prefer an even position over a draw and longer draws over shorter ones, so for these negating a value
+/// doesn't reverse the order. Alpha-beta relies on that, which is why it can end up with a different one of them than
+/// a search without pruning.
+fn same_up_to_even(v1: Valuation, v2: Valuation) -> bool {
+    let is_even = |v| matches!(v, Valuation::NonTerminal { value: 0 } | Valuation::TerminalDraw { .. });
+
+    v1 == v2 || (is_even(v1) && is_even(v2))
+}
+
+#[test]
+fn test_search_symmetry() {
+    const DEPTH: u32 = 3;
+
+    let valuation_fn: ValuationFn = crate::kalah::valuation::store_diff_valuation;
+
+    for board in random_positions(4, 3, 10) {
+        let value = negamax(&board, DEPTH, valuation_fn);
+
+        // flipping the board makes the side to move Black, whose value is the negation of White's
+        assert_eq!(
+            search_unflipped(&board, DEPTH, valuation_fn, Player::White),
+            value,
+            "{}",
+            board.to_kgp()
+        );
+        assert_eq!(
+            search_unflipped(&flipped(&board), DEPTH, valuation_fn, Player::Black),
+            value,
+            "{}",
+            board.to_kgp()
+        );
+
+        if !board.has_legal_move() {
+            continue;
+        }
+
+        // the actual searches with all their pruning have to find the same value
+        let values: [(&str, Valuation); 4] = [
+            ("minimax", crate::minimax::search_value(&board, DEPTH, valuation_fn)),
+            (
+                "minimax with explicit stack",
+                crate::minimax::search_value_explicit_stack(&board, DEPTH, valuation_fn),
+            ),
+            ("pvs", crate::pvs::search_value(&board, DEPTH, valuation_fn)),
+            // always uses store_diff_valuation
+            ("tournament", crate::tournament::search_value(&board, DEPTH)),
+        ];
+
+        for (name, search_value) in values {
+            assert!(
+                same_up_to_even(search_value, value),
+                "{name} on {}: {search_value} vs {value}",
+                board.to_kgp()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_mirror_move() {
+    use crate::kalah::Features;
+    use crate::Move;
+
+    // the features as the untracked board computes them, from its houses
+    let features = |board: &Board| {
+        let board = Board::from_parts(
+            board.h(),
+            board.our_houses().to_vec(),
+            board.their_houses().to_vec(),
+            board.our_store(),
+            board.their_store(),
+            false,
+        );
+        (
+            board.house_seeds(),
+            board.non_empty_houses(),
+            board.legal_mask(Player::White),
+            board.legal_mask(Player::Black),
+        )
+    };
+
+    for (h, s) in [(1, 1), (3, 3), (6, 4), (8, 8)] {
+        for board in random_positions(h, s, 5) {
+            for move_ in board.legal_moves(Player::White) {
+                let mut expected = board.clone();
+                let bonus = expected.apply_move(move_);
+
+                // the same move by Black on the flipped board, flipped back
+                let mut mirrored = flipped(&board);
+                mirrored.track_features(Features::ALL);
+                let mirrored_bonus = mirrored.apply_move(Move::new(move_.house(), Player::Black));
+                mirrored.flip_board();
+
+                assert_eq!(mirrored.to_kgp(), expected.to_kgp(), "{move_} on {}", board.to_kgp());
+                assert_eq!(mirrored_bonus, bonus, "{move_} on {}", board.to_kgp());
+
+                // and on a mirror image built from the houses, without flip_board
+                let mut mirror_image = Board::from_parts(
+                    h,
+                    board.their_houses().to_vec(),
+                    board.our_houses().to_vec(),
+                    board.their_store(),
+                    board.our_store(),
+                    false,
+                );
+                assert_eq!(mirror_image.apply_move(Move::new(move_.house(), Player::Black)), bonus);
+                assert_eq!(
+                    mirror_image.their_houses(),
+                    expected.our_houses(),
+                    "{move_} on {}",
+                    board.to_kgp()
+                );
+                assert_eq!(
+                    mirror_image.our_houses(),
+                    expected.their_houses(),
+                    "{move_} on {}",
+                    board.to_kgp()
+                );
+                assert_eq!(
+                    (mirror_image.their_store(), mirror_image.our_store()),
+                    (expected.our_store(), expected.their_store())
+                );
+
+                // features kept up to date through the flips agree with the ones computed from scratch
+                assert_eq!(
+                    (
+                        mirrored.house_seeds(),
+                        mirrored.non_empty_houses(),
+                        mirrored.legal_mask(Player::White),
+                        mirrored.legal_mask(Player::Black)
+                    ),
+                    features(&mirrored),
+                    "{move_} on {}",
+                    board.to_kgp()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod solver;
+mod table;
+
+pub use solver::{solve, Solution};
+pub use table::Tablebase;
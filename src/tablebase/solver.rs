@@ -0,0 +1,126 @@
+use std::fmt::Display;
+
+use super::table::{Tablebase, MAX_SOLVE_POSITIONS};
+use crate::kalah::Valuation;
+use crate::{Board, Move, Player};
+
+/// game-theoretic result of a position, see solve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solution {
+    /// value from the perspective of the side to move (White), with the exact number of plies to the end of the game
+    pub value: Valuation,
+
+    /// a move that achieves value: the shortest win, or the longest loss or draw
+    pub best_move: Move,
+}
+
+impl Display for Solution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (result, plies) = match self.value {
+            Valuation::TerminalWhiteWin { plies } => ("wins", plies),
+            Valuation::TerminalBlackWin { plies } => ("loses", plies),
+            Valuation::TerminalDraw { plies } => ("draws", plies),
+            Valuation::NonTerminal { .. } => unreachable!("Solved positions are always terminal"),
+        };
+
+        write!(
+            f,
+            "The side to move {result} after {plies} plies, best move: {}",
+            self.best_move
+        )
+    }
+}
+
+/// solve board with White to move by searching every line to the end of the game, i.e. without a depth limit
+///
+/// unlike the searches this doesn't stop at decided positions, so the number of plies is the distance to the end of
+/// the game. Positions get memoized like in a Tablebase, refuses boards that could have more than MAX_SOLVE_POSITIONS
+/// of them (at most 8 houses, and only a few seeds for that many).
+pub fn solve(board: &Board) -> Result<Solution, String> {
+    if !board.has_legal_move() {
+        return Err("The game is already over".to_owned());
+    }
+
+    let (our_house_seeds, their_house_seeds) = board.house_seeds();
+    let mut tablebase = Tablebase::empty(board.h(), our_house_seeds + their_house_seeds, MAX_SOLVE_POSITIONS)
+        .map_err(|err| format!("Board is too large to solve: {err}"))?;
+
+    let solution = board
+        .legal_moves(Player::White)
+        .into_iter()
+        .map(|move_| {
+            let mut board_after_move = board.clone();
+
+            let value = if board_after_move.apply_move(move_) {
+                tablebase.solve(&board_after_move)
+            } else {
+                board_after_move.flip_board();
+                -tablebase.solve(&board_after_move)
+            }
+            .increase_plies();
+
+            Solution {
+                value,
+                best_move: move_,
+            }
+        })
+        .max_by_key(|solution| solution.value)
+        .unwrap();
+
+    Ok(solution)
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::{solve, Solution};
+    use crate::kalah::Valuation;
+    use crate::tablebase::Tablebase;
+    use crate::{Board, Move, Player};
+
+    #[test]
+    fn test_solve() {
+        // White moves its only seed into the store and runs out of seeds, Black sweeps its seed: 1:1
+        assert_eq!(
+            solve(&Board::from_kgp("<1, 0, 0, 1, 1>")),
+            Ok(Solution {
+                value: Valuation::TerminalDraw { plies: 1 },
+                best_move: Move::new(0, Player::White),
+            })
+        );
+
+        let tablebase = Tablebase::generate(2, 8).unwrap();
+
+        for kgp in [
+            "<2, 0, 0, 2, 2, 2, 2>",
+            "<2, 3, 1, 1, 0, 2, 1>",
+            "<2, 0, 4, 3, 1, 0, 2>",
+        ] {
+            let board = Board::from_kgp(kgp);
+            let solution = solve(&board).unwrap();
+
+            assert_eq!(Some(solution.value), tablebase.probe(&board), "{kgp}");
+
+            // the best move leads to a position of the same value
+            let mut board_after_move = board.clone();
+            let child_value = if board_after_move.apply_move(solution.best_move) {
+                tablebase.probe(&board_after_move)
+            } else {
+                board_after_move.flip_board();
+                tablebase.probe(&board_after_move).map(|value| -value)
+            };
+            assert_eq!(
+                child_value.map(Valuation::increase_plies),
+                Some(solution.value),
+                "{kgp}"
+            );
+        }
+
+        // the standard 3x3 start position is still small enough
+        assert!(solve(&Board::new(3, 3)).is_ok());
+
+        assert!(solve(&Board::new(6, 6)).is_err());
+        assert!(solve(&Board::from_kgp("<2, 3, 5, 0, 0, 0, 0>")).is_err());
+    }
+}
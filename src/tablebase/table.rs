@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use crate::kalah::{SweepRule, Valuation};
+use crate::{Board, House, Player};
+
+/*====================================================================================================================*/
+
+/// upper limit on the number of positions a tablebase may cover, about 100 MB worth of entries
+pub const MAX_POSITIONS: u64 = 4_000_000;
+
+/// upper limit on the number of positions when solving a single position, see solve: only the positions reachable
+/// from it get stored, usually a small fraction of all positions
+pub const MAX_SOLVE_POSITIONS: u64 = 50_000_000;
+
+pub const MAX_H: u8 = 8;
+pub const MAX_SEEDS: u16 = u8::MAX as u16;
+
+// houses packed into a u128 (8 bits per house), store difference clamped to [-(max_seeds + 1), max_seeds + 1]
+type Key = (u128, i16);
+
+/// endgame tablebase for boards with few houses and few seeds left in the houses
+///
+/// Holds the game-theoretic value of every position with h houses per side and at most max_seeds seeds in all houses
+/// combined, computed by backward induction: since seeds either move towards their own store or into a store (which
+/// never decreases), no position can repeat and the game graph is acyclic, so every position can be solved from the
+/// values of its successors.
+///
+/// The value of a position only depends on the houses and the store difference, and once the store difference
+/// exceeds the number of seeds left in the houses the winner is determined, so all larger differences share the
+/// entry of max_seeds + 1. This bounds the number of positions by
+///
+/// ```text
+/// binomial(max_seeds + 2h, 2h) * (2 * max_seeds + 3)
+/// ```
+///
+/// which has to stay below MAX_POSITIONS, e.g. h = 4 allows up to 10 seeds (~1M positions), h = 6 up to 5 seeds
+/// (~80k positions). Generation time is roughly linear in the number of positions.
+pub struct Tablebase {
+    h: u8,
+    max_seeds: u16,
+
+    table: HashMap<Key, Valuation>,
+}
+
+impl Tablebase {
+    /// number of positions covered by a tablebase for h houses and max_seeds seeds
+    pub fn num_positions(h: u8, max_seeds: u16) -> u64 {
+        let n = max_seeds as u64 + 2 * h as u64;
+        let k = 2 * h as u64;
+
+        // binomial(n, k), computed incrementally so intermediate values stay small
+        let configurations = (1..=k).fold(1u64, |acc, i| acc.saturating_mul(n - k + i) / i);
+
+        configurations.saturating_mul(2 * max_seeds as u64 + 3)
+    }
+
+    /// solve all positions with h houses and at most max_seeds seeds in the houses
+    pub fn generate(h: u8, max_seeds: u16) -> Result<Tablebase, String> {
+        let mut tablebase = Tablebase::empty(h, max_seeds, MAX_POSITIONS)?;
+        tablebase.table.reserve(Tablebase::num_positions(h, max_seeds) as usize);
+
+        let mut houses = vec![0; 2 * h as usize];
+        let max_diff = max_seeds as i16 + 1;
+
+        // enumerate all distributions of at most max_seeds seeds over the 2h houses
+        loop {
+            let seeds: u16 = houses.iter().sum();
+
+            if seeds <= max_seeds {
+                // store differences beyond +-max_diff behave like +-max_diff
+                for store_diff in -max_diff..=max_diff {
+                    let (our_store, their_store) = if store_diff >= 0 {
+                        (store_diff as House, 0)
+                    } else {
+                        (0, (-store_diff) as House)
+                    };
+
+                    let board = Board::from_parts(
+                        h,
+                        houses[..h as usize].to_vec(),
+                        houses[h as usize..].to_vec(),
+                        our_store,
+                        their_store,
+                        false,
+                    );
+
+                    tablebase.solve(&board);
+                }
+            }
+
+            // advance to the next distribution, odometer-style, skipping those with too many seeds
+            let mut idx = 0;
+            loop {
+                if idx == houses.len() {
+                    return Ok(tablebase);
+                }
+
+                houses[idx] += 1;
+
+                if houses.iter().sum::<u16>() <= max_seeds {
+                    break;
+                }
+
+                houses[idx] = 0;
+                idx += 1;
+            }
+        }
+    }
+
+    /// tablebase without any positions yet, for h houses and max_seeds seeds covering at most max_positions positions
+    pub(super) fn empty(h: u8, max_seeds: u16, max_positions: u64) -> Result<Tablebase, String> {
+        if h == 0 || h > MAX_H {
+            return Err(format!("Tablebase only supports 1..={MAX_H} houses, got {h}"));
+        }
+
+        if max_seeds > MAX_SEEDS {
+            return Err(format!(
+                "Tablebase only supports up to {MAX_SEEDS} seeds, got {max_seeds}"
+            ));
+        }
+
+        let num_positions = Tablebase::num_positions(h, max_seeds);
+
+        if num_positions > max_positions {
+            return Err(format!(
+                "Tablebase for h = {h}, max_seeds = {max_seeds} would contain {num_positions} positions \
+                 (limit: {max_positions})"
+            ));
+        }
+
+        Ok(Tablebase {
+            h,
+            max_seeds,
+            table: HashMap::new(),
+        })
+    }
+
+    pub fn h(&self) -> u8 {
+        self.h
+    }
+
+    pub fn max_seeds(&self) -> u16 {
+        self.max_seeds
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// game-theoretic value of board with White to move, from White's perspective (like a ValuationFn)
+    ///
+    /// returns None if the board is not covered by the tablebase (different h, too many seeds or a sweep rule other
+    /// than the standard one the tablebase was generated with)
+    pub fn probe(&self, board: &Board) -> Option<Valuation> {
+        if board.h() != self.h
+            || self.seeds_in_houses(board) > self.max_seeds
+            || board.sweep_rule() != SweepRule::OwnSide
+        {
+            return None;
+        }
+
+        if !board.has_legal_move() {
+            return Some(terminal_valuation(board));
+        }
+
+        self.table.get(&self.key(board)).copied()
+    }
+
+    fn seeds_in_houses(&self, board: &Board) -> u16 {
+        board.our_houses().iter().sum::<u16>() + board.their_houses().iter().sum::<u16>()
+    }
+
+    fn key(&self, board: &Board) -> Key {
+        let houses = board
+            .our_houses()
+            .iter()
+            .chain(board.their_houses())
+            .fold(0u128, |key, &house| (key << 8) | house as u128);
+
+        let max_diff = self.max_seeds as i32 + 1;
+        let store_diff = (board.our_store() as i32 - board.their_store() as i32).clamp(-max_diff, max_diff);
+
+        (houses, store_diff as i16)
+    }
+
+    /// value of board like probe, solving it (and every position reachable from it) first if it isn't in the table
+    pub(super) fn solve(&mut self, board: &Board) -> Valuation {
+        if !board.has_legal_move() {
+            return terminal_valuation(board);
+        }
+
+        let key = self.key(board);
+
+        if let Some(&value) = self.table.get(&key) {
+            return value;
+        }
+
+        let mut best_value = Valuation::TerminalBlackWin { plies: 0 };
+
+        let mut board_after_move = board.clone();
+
+        for move_ in board.legal_moves(Player::White) {
+            board_after_move.clone_from(board);
+
+            let value = if board_after_move.apply_move(move_) {
+                // bonus move: still our turn
+                self.solve(&board_after_move)
+            } else {
+                board_after_move.flip_board();
+                -self.solve(&board_after_move)
+            }
+            .increase_plies();
+
+            if value > best_value {
+                best_value = value;
+            }
+        }
+
+        self.table.insert(key, best_value);
+
+        best_value
+    }
+}
+
+/// value of a finished game (or a board on which one side has run out of seeds)
+fn terminal_valuation(board: &Board) -> Valuation {
+    let mut board = board.clone();
+    board.finish_game();
+
+    match board.our_store().cmp(&board.their_store()) {
+        std::cmp::Ordering::Greater => Valuation::TerminalWhiteWin { plies: 0 },
+        std::cmp::Ordering::Less => Valuation::TerminalBlackWin { plies: 0 },
+        std::cmp::Ordering::Equal => Valuation::TerminalDraw { plies: 0 },
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::{terminal_valuation, Tablebase};
+    use crate::kalah::Valuation;
+    use crate::{Board, Player};
+
+    // plain exhaustive minimax without any memoization, as a reference
+    fn solve_exhaustive(board: &Board) -> Valuation {
+        if !board.has_legal_move() {
+            return terminal_valuation(board);
+        }
+
+        board
+            .legal_moves(Player::White)
+            .into_iter()
+            .map(|move_| {
+                let mut board = board.clone();
+
+                if board.apply_move(move_) {
+                    solve_exhaustive(&board)
+                } else {
+                    board.flip_board();
+                    -solve_exhaustive(&board)
+                }
+                .increase_plies()
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_num_positions() {
+        // binomial(2 + 2, 2) = 6 distributions, 2 * 2 + 3 = 7 store differences
+        assert_eq!(Tablebase::num_positions(1, 2), 42);
+
+        assert!(Tablebase::generate(0, 4).is_err());
+        assert!(Tablebase::generate(6, 40).is_err());
+    }
+
+    #[test]
+    fn test_probe_simple() {
+        let tablebase = Tablebase::generate(1, 4).unwrap();
+
+        // White moves its only seed into the store and runs out of seeds, Black sweeps its seed: 1:1
+        assert_eq!(
+            tablebase.probe(&Board::from_kgp("<1, 0, 0, 1, 1>")),
+            Some(Valuation::TerminalDraw { plies: 1 })
+        );
+
+        assert_eq!(
+            tablebase.probe(&Board::from_kgp("<1, 5, 0, 1, 1>")),
+            Some(Valuation::TerminalWhiteWin { plies: 1 })
+        );
+
+        // finished game
+        assert_eq!(
+            tablebase.probe(&Board::from_kgp("<1, 3, 5, 0, 0>")),
+            Some(Valuation::TerminalBlackWin { plies: 0 })
+        );
+
+        // not covered
+        assert_eq!(tablebase.probe(&Board::from_kgp("<1, 0, 0, 3, 3>")), None);
+        assert_eq!(tablebase.probe(&Board::from_kgp("<2, 0, 0, 1, 1, 1, 1>")), None);
+    }
+
+    #[test]
+    fn test_probe_matches_exhaustive_search() {
+        let mut rng = thread_rng();
+
+        for h in 2..=3 {
+            let max_seeds = 6;
+            let tablebase = Tablebase::generate(h, max_seeds).unwrap();
+
+            for _ in 0..200 {
+                let mut our_houses = vec![0; h as usize];
+                let mut their_houses = vec![0; h as usize];
+
+                for _ in 0..rng.gen_range(0..=max_seeds) {
+                    match rng.gen_bool(0.5) {
+                        true => our_houses[rng.gen_range(0..h as usize)] += 1,
+                        false => their_houses[rng.gen_range(0..h as usize)] += 1,
+                    }
+                }
+
+                let our_store = rng.gen_range(0..12);
+                let their_store = rng.gen_range(0..12);
+
+                let board = Board::from_parts(h, our_houses, their_houses, our_store, their_store, false);
+
+                assert_eq!(
+                    tablebase.probe(&board),
+                    Some(solve_exhaustive(&board)),
+                    "Tablebase disagrees with exhaustive search on {board:?}"
+                );
+            }
+        }
+    }
+}
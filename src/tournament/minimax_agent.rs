@@ -1,9 +1,18 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
 
-use crate::{Board, Move, Player};
+use log::{error, info, warn};
 
-use super::search::{minimax_search, new_shared_minimax_search_state, SharedMinimaxSearchState};
-use crate::agent::{Agent, AgentState};
+use crate::{Board, Move};
+
+use super::search::{
+    minimax_search, new_shared_minimax_search_state, MinimaxSearchState, SharedMinimaxSearchState, VALUATION_FN,
+};
+use crate::agent::{
+    fallback_move, lock_search_when, worker_panic, Agent, AgentState, Watchdog, FIRST_DEPTH_TIMEOUT, WATCHDOG_TIMEOUT,
+};
 
 pub struct MinimaxAgent {
     state: AgentState,
@@ -11,16 +20,52 @@ pub struct MinimaxAgent {
     board: Board,
 
     search_state: Option<SharedMinimaxSearchState>,
+
+    // cleared to cancel the current search, or by the search itself once it's done
+    search_active: Arc<AtomicBool>,
+
+    watchdog: Option<Watchdog>,
+
+    // search thread, to notice if it panicked
+    worker: Option<JoinHandle<()>>,
+
+    // last move read from search_state, in case it's locked when asked again
+    last_best_move: Option<Move>,
+
+    // when the current search was started
+    go_time: Instant,
 }
 
 impl MinimaxAgent {
-    #[allow(dead_code)]
     pub fn new(board: Board) -> Self {
         MinimaxAgent {
             state: AgentState::Waiting,
             board,
             search_state: None,
+            search_active: Arc::new(AtomicBool::new(false)),
+            watchdog: None,
+            worker: None,
+            last_best_move: None,
+            go_time: Instant::now(),
+        }
+    }
+
+    /// recover from a panicked search: log it and start over, playing the best move so far until the new search has
+    /// one
+    fn restart_search(&mut self, err: &str) -> Move {
+        error!("Search thread panicked ({err}), restarting the search");
+
+        let last_best_move = self.last_best_move;
+
+        self.stop();
+        self.go();
+
+        // go forgets the best move so far, but one from a completed depth of the failed search beats the fallback
+        if last_best_move.is_some() {
+            self.last_best_move = last_best_move;
         }
+
+        self.last_best_move.unwrap()
     }
 }
 
@@ -32,11 +77,40 @@ impl Agent for MinimaxAgent {
     fn get_current_best_move(&mut self) -> Move {
         assert_eq!(self.state, AgentState::Go);
 
-        if !self.search_state.as_ref().unwrap().lock().unwrap().search_active {
-            self.state = AgentState::Waiting;
+        if let Some(err) = worker_panic(&mut self.worker) {
+            return self.restart_search(&err);
+        }
+
+        // own reference, so the search can be restarted below while the result is still borrowed
+        let search_state = Arc::clone(self.search_state.as_ref().unwrap());
+
+        // give the search a moment to complete its first depth rather than handing out the fallback right away
+        let deadline = self.go_time + FIRST_DEPTH_TIMEOUT;
+        let ready = |search_state: &MinimaxSearchState| {
+            search_state.completed_depth > 0 || !self.search_active.load(Ordering::Relaxed)
+        };
+
+        let locked = match lock_search_when(&search_state, deadline, ready) {
+            Ok(locked) => locked,
+            Err(err) => return self.restart_search(&err),
+        };
+
+        if let Some(search_state) = locked {
+            self.last_best_move = Some(search_state.current_best_move);
+
+            // the search publishes its result before clearing search_active, so it's the final one here
+            if !self.search_active.load(Ordering::Relaxed) {
+                self.state = AgentState::Waiting;
+            }
         }
 
-        self.search_state.as_ref().unwrap().lock().unwrap().current_best_move
+        if self.state == AgentState::Go && self.watchdog.as_mut().unwrap().is_stuck() {
+            warn!("Search made no progress for {WATCHDOG_TIMEOUT:?}, stopping it and playing the best move so far");
+
+            self.stop();
+        }
+
+        self.last_best_move.unwrap()
     }
 
     fn get_state(&self) -> crate::agent::AgentState {
@@ -44,15 +118,34 @@ impl Agent for MinimaxAgent {
     }
 
     fn go(&mut self) {
-        // use first legal move as a fallback in case we don't complete a single search iteration, which really should
-        // not happen
-        let fallback_move = *self.board.legal_moves(Player::White).first().unwrap();
-        let search_state = new_shared_minimax_search_state(true, fallback_move);
+        if !self.board.has_legal_move() {
+            info!("Game is over, nothing to search");
+            self.state = AgentState::Waiting;
+            return;
+        }
+
+        // use best move after one ply as a fallback in case we don't complete a single search iteration, which really
+        // should not happen
+        let fallback_move = fallback_move(&self.board, VALUATION_FN);
+        let search_state = new_shared_minimax_search_state(fallback_move);
+        let search_active = Arc::new(AtomicBool::new(true));
 
-        minimax_search(&self.board, Arc::clone(&search_state));
+        let watchdog = Watchdog::new(WATCHDOG_TIMEOUT);
+
+        let worker = minimax_search(
+            &self.board,
+            Arc::clone(&search_state),
+            Arc::clone(&search_active),
+            watchdog.progress(),
+        );
 
         self.state = AgentState::Go;
         self.search_state = Some(search_state);
+        self.search_active = search_active;
+        self.watchdog = Some(watchdog);
+        self.worker = Some(worker);
+        self.last_best_move = Some(fallback_move);
+        self.go_time = Instant::now();
     }
 
     fn stop(&mut self) {
@@ -63,9 +156,11 @@ impl Agent for MinimaxAgent {
 
         self.state = AgentState::Waiting;
 
-        // set search_active to false, then drop reference
-        self.search_state.as_ref().unwrap().lock().unwrap().search_active = false;
+        // cancel the search (if go started one), then drop reference
+        self.search_active.store(false, Ordering::Relaxed);
         self.search_state = None;
+        self.watchdog = None;
+        self.worker = None;
     }
 
     fn ponder(&mut self) {
@@ -73,3 +168,125 @@ impl Agent for MinimaxAgent {
         todo!()
     }
 }
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use super::MinimaxAgent;
+    use crate::agent::{Agent, AgentState, WATCHDOG_TIMEOUT};
+    use crate::{Board, Player};
+
+    #[test]
+    fn test_watchdog_stuck_search() {
+        let board = Board::new(6, 6);
+
+        let mut agent = MinimaxAgent::new(board.clone());
+        agent.go();
+
+        let search_state = Arc::clone(agent.search_state.as_ref().unwrap());
+        let search_active = Arc::clone(&agent.search_active);
+
+        {
+            // hold the lock like a stuck worker would, the search blocks on it once it completes a depth
+            let _guard = search_state.lock().unwrap();
+
+            let start = Instant::now();
+
+            while agent.get_state() == AgentState::Go {
+                let best_move = agent.get_current_best_move();
+                assert!(board.legal_moves(Player::White).contains(&best_move));
+
+                assert!(
+                    start.elapsed() < 2 * WATCHDOG_TIMEOUT,
+                    "Watchdog didn't notice stuck search"
+                );
+
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        }
+
+        // the search got told to exit without having to take the lock
+        assert!(!search_active.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_first_move_after_completed_depth() {
+        let mut agent = MinimaxAgent::new(Board::new(6, 6));
+
+        agent.go();
+        let _ = agent.get_current_best_move();
+
+        // asking right after go must not have returned the fallback move
+        let search_state = agent.search_state.as_ref().unwrap();
+        assert!(search_state.lock().unwrap().completed_depth > 0);
+
+        agent.stop();
+    }
+
+    #[test]
+    fn test_stop_search_promptly() {
+        let mut agent = MinimaxAgent::new(Board::new(6, 6));
+
+        agent.go();
+        let progress = agent.watchdog.as_ref().unwrap().progress();
+
+        std::thread::sleep(Duration::from_millis(50));
+        agent.stop();
+
+        // the worker checks the flag at every node, so it should be gone well before the next check
+        std::thread::sleep(Duration::from_millis(50));
+        let nodes = progress.load(Ordering::Relaxed);
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(
+            progress.load(Ordering::Relaxed),
+            nodes,
+            "Search kept running after stop"
+        );
+    }
+
+    #[test]
+    fn test_search_panic() {
+        let board = Board::new(6, 6);
+
+        let mut agent = MinimaxAgent::new(board.clone());
+        agent.go();
+
+        // worker panicking while it holds the search state
+        let search_state = Arc::clone(agent.search_state.as_ref().unwrap());
+        let _ = std::thread::spawn(move || {
+            let _guard = search_state.lock().unwrap();
+            panic!("injected panic");
+        })
+        .join();
+
+        assert!(board
+            .legal_moves(Player::White)
+            .contains(&agent.get_current_best_move()));
+
+        // the search got restarted with a fresh state
+        assert_eq!(agent.get_state(), AgentState::Go);
+        assert!(!agent.search_state.as_ref().unwrap().is_poisoned());
+
+        // worker dying without holding the lock, in place of the real one
+        agent.search_active.store(false, Ordering::Relaxed);
+        let worker = std::thread::spawn(|| panic!("injected panic"));
+        while !worker.is_finished() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        agent.worker = Some(worker);
+
+        assert!(board
+            .legal_moves(Player::White)
+            .contains(&agent.get_current_best_move()));
+        assert_eq!(agent.get_state(), AgentState::Go);
+        assert!(agent.search_active.load(Ordering::Relaxed));
+
+        agent.stop();
+    }
+}
@@ -4,3 +4,6 @@ mod minimax_agent;
 mod search;
 
 pub use minimax_agent::MinimaxAgent;
+
+#[cfg(test)]
+pub use search::search_value;
@@ -1,24 +1,53 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 
 use crate::kalah::valuation::Valuation;
+use crate::kalah::{BoardPool, Features};
 use crate::{Board, Move, Player};
 
-const VALUATION_FN: fn(&Board) -> Valuation = crate::kalah::valuation::store_diff_valuation;
+pub const VALUATION_FN: fn(&Board) -> Valuation = crate::kalah::valuation::store_diff_valuation;
 
 /*====================================================================================================================*/
 
 pub type SharedMinimaxSearchState = Arc<Mutex<MinimaxSearchState>>;
 
+/// result of the search so far, the lock is only taken to publish or read it
+///
+/// cancelling the search (or the search announcing that it's done) goes through a separate search_active flag, so the
+/// recursion never has to take the lock
 pub struct MinimaxSearchState {
-    pub search_active: bool,
-
     pub current_best_move: Move,
+
+    // deepest search depth that was completed, 0 while current_best_move is still the fallback
+    pub completed_depth: u32,
+
+    // value of current_best_move at completed_depth
+    pub value: Valuation,
 }
 
-pub fn new_shared_minimax_search_state(search_active: bool, fallback_move: Move) -> SharedMinimaxSearchState {
+impl MinimaxSearchState {
+    /// publish the best move of a completed search depth, unless the stored result is better
+    ///
+    /// the deeper result is better, at the same depth the higher value and between equal values the move from the
+    /// lower house, so with several threads reporting the stored move doesn't depend on which of them finished last
+    pub fn report(&mut self, best_move: Move, depth: u32, value: Valuation) {
+        let rank = |best_move: Move, depth: u32, value: Valuation| (depth, value, Reverse(best_move.house()));
+
+        if rank(best_move, depth, value) > rank(self.current_best_move, self.completed_depth, self.value) {
+            self.current_best_move = best_move;
+            self.completed_depth = depth;
+            self.value = value;
+        }
+    }
+}
+
+pub fn new_shared_minimax_search_state(fallback_move: Move) -> SharedMinimaxSearchState {
     Arc::new(Mutex::new(MinimaxSearchState {
-        search_active,
         current_best_move: fallback_move,
+        completed_depth: 0,
+        value: Valuation::TerminalBlackWin { plies: 0 },
     }))
 }
 
@@ -26,20 +55,41 @@ pub fn new_shared_minimax_search_state(search_active: bool, fallback_move: Move)
 
 struct MinimaxWorker {
     search_state: Arc<Mutex<MinimaxSearchState>>,
+
+    search_active: Arc<AtomicBool>,
+
+    // board_after_move of every node on the current line, see BoardPool
+    scratch_boards: BoardPool,
+
+    // number of visited nodes, for the agent's watchdog
+    progress: Arc<AtomicU64>,
 }
 
 impl MinimaxWorker {
-    pub fn new(search_state: SharedMinimaxSearchState) -> Self {
-        MinimaxWorker { search_state }
+    pub fn new(
+        search_state: SharedMinimaxSearchState,
+        search_active: Arc<AtomicBool>,
+        progress: Arc<AtomicU64>,
+    ) -> Self {
+        MinimaxWorker {
+            search_state,
+            search_active,
+            scratch_boards: BoardPool::new(),
+            progress,
+        }
     }
 
     fn minimax(&mut self, board: &Board, remaining_depth: u32, alpha: Valuation, beta: Valuation) -> (Move, Valuation) {
-        if !self.search_state.lock().unwrap().search_active {
+        if !self.search_active.load(Ordering::Relaxed) {
             // search has been ended, search results don't matter anymore, exit thread asap
             return (Move::new(127, Player::White), Valuation::NonTerminal { value: 0 });
         }
 
-        if remaining_depth == 0 || !board.has_legal_move() {
+        self.progress.fetch_add(1, Ordering::Relaxed);
+
+        // decided positions are leaves like finished games: the valuations treat them as terminal, so searching on
+        // would only add the plies to the horizon to their distance
+        if remaining_depth == 0 || board.is_decided().is_some() {
             return (Move::new(127, Player::White), VALUATION_FN(board));
         }
 
@@ -47,26 +97,34 @@ impl MinimaxWorker {
         let mut best_value = Valuation::TerminalBlackWin { plies: 0 };
         let mut alpha = alpha;
 
-        let mut board_after_move = board.clone();
+        let mut board_after_move = self.scratch_boards.take(board);
 
-        for house in 0..board.h() {
-            let move_ = Move::new(house, Player::White);
+        let mut legal_mask = board.legal_mask(Player::White);
 
-            if !board.is_legal_move(move_) {
-                continue;
-            }
+        while legal_mask != 0 {
+            // lowest set bit is the next legal house
+            let house = legal_mask.trailing_zeros() as u8;
+            legal_mask &= legal_mask - 1;
+
+            let move_ = Move::new(house, Player::White);
 
-            // let mut board_after_move = board.clone();
             board_after_move.clone_from(board);
             let their_turn = !board_after_move.apply_move(move_);
 
+            // the child counts plies from its own position, so the window has to be shifted by the ply of this move,
+            // otherwise a bound on a forced win or loss could pass for its exact value
+            let (child_alpha, child_beta) = (alpha.decrease_plies(), beta.decrease_plies());
+
             let value = if their_turn {
                 // opponent move: flip board, alpha, beta to their perspective and flip returned value to ours
                 board_after_move.flip_board();
-                -self.minimax(&board_after_move, remaining_depth - 1, -beta, -alpha).1
+                -self
+                    .minimax(&board_after_move, remaining_depth - 1, -child_beta, -child_alpha)
+                    .1
             } else {
                 // bonus move: don't decrease depth
-                self.minimax(&board_after_move, remaining_depth, alpha, beta).1
+                self.minimax(&board_after_move, remaining_depth, child_alpha, child_beta)
+                    .1
             }
             .increase_plies();
 
@@ -86,73 +144,118 @@ impl MinimaxWorker {
             }
         }
 
+        self.scratch_boards.give_back(board_after_move);
+
         (best_move, best_value)
     }
 
+    /// best move of a root that is already decided, judged one ply ahead
+    ///
+    /// the outcome can't change anymore, any move keeps it decided, so deepening would only burn the clock
+    fn decided_root(&self, board: &Board) -> (Move, Valuation) {
+        board
+            .legal_moves(Player::White)
+            .into_iter()
+            .map(|move_| {
+                let mut board_after_move = board.clone();
+
+                let value = if board_after_move.apply_move(move_) {
+                    VALUATION_FN(&board_after_move)
+                } else {
+                    board_after_move.flip_board();
+                    -VALUATION_FN(&board_after_move)
+                };
+
+                (move_, value.increase_plies())
+            })
+            .max_by_key(|&(_move, value)| value)
+            .unwrap()
+    }
+
     pub fn start_search(self, board: Board) {
         use Valuation::{TerminalBlackWin, TerminalWhiteWin};
 
         let mut me = self;
 
+        if board.is_decided().is_some() {
+            let (best_move, best_value) = me.decided_root(&board);
+            me.search_state.lock().unwrap().report(best_move, 1, best_value);
+            me.search_active.store(false, Ordering::Relaxed);
+            return;
+        }
+
         let alpha = TerminalBlackWin { plies: 0 };
         let beta = TerminalWhiteWin { plies: 0 };
 
         for max_depth in 6.. {
+            // one board per ply, bonus moves make the pool grow past that on its own
+            me.scratch_boards.reserve(&board, max_depth as usize);
+
             let board = board.clone();
             let (best_move, best_value) = me.minimax(&board, max_depth, alpha, beta);
 
-            if !me.search_state.lock().unwrap().search_active {
+            if !me.search_active.load(Ordering::Relaxed) {
                 return;
             }
 
-            if let Valuation::TerminalWhiteWin { plies: _ } = best_value {
-                {
-                    let mut search_state = me.search_state.lock().unwrap();
-                    search_state.current_best_move = best_move;
-                    search_state.search_active = false;
-                }
+            // a shorter win (or loss) than the one found could still be beyond the horizon, see distance_is_exact
+            let exact = best_value.distance_is_exact(max_depth);
+
+            if let (Valuation::TerminalWhiteWin { plies: _ }, true) = (best_value, exact) {
+                me.search_state.lock().unwrap().report(best_move, max_depth, best_value);
+                me.search_active.store(false, Ordering::Relaxed);
                 return;
             }
 
-            if let TerminalBlackWin { plies: _ } = best_value {
+            if let (TerminalBlackWin { plies: _ }, true) = (best_value, exact) {
                 // all moves are certain losses, pick the one with the most plies and exit
-                {
-                    let mut search_state = me.search_state.lock().unwrap();
-                    search_state.current_best_move = best_move;
-                    search_state.search_active = false;
-                }
+                me.search_state.lock().unwrap().report(best_move, max_depth, best_value);
+                me.search_active.store(false, Ordering::Relaxed);
                 return;
             }
 
-            me.search_state.lock().unwrap().current_best_move = best_move;
+            me.search_state.lock().unwrap().report(best_move, max_depth, best_value);
         }
 
-        me.search_state.lock().unwrap().search_active = false;
+        me.search_active.store(false, Ordering::Relaxed);
     }
 }
 
+/// value of board after a single fixed depth search, for tests comparing the searches
+#[cfg(test)]
+pub fn search_value(board: &Board, depth: u32) -> Valuation {
+    let search_state = new_shared_minimax_search_state(Move::new(127, Player::White));
+    let search_active = Arc::new(AtomicBool::new(true));
+    let mut worker = MinimaxWorker::new(search_state, search_active, Arc::new(AtomicU64::new(0)));
+
+    let alpha = Valuation::TerminalBlackWin { plies: 0 };
+    let beta = Valuation::TerminalWhiteWin { plies: 0 };
+
+    worker.minimax(board, depth, alpha, beta).1
+}
+
 /*====================================================================================================================*/
 
-pub fn minimax_search(board: &Board, search_state: SharedMinimaxSearchState) {
+pub fn minimax_search(
+    board: &Board,
+    search_state: SharedMinimaxSearchState,
+    search_active: Arc<AtomicBool>,
+    progress: Arc<AtomicU64>,
+) -> JoinHandle<()> {
     assert!(
         board.has_legal_move(),
         "Called minimax_search on board with no legal moves"
     );
 
-    let t_handle;
-
-    {
-        // let worker_board = board.clone();
-
-        t_handle = std::thread::spawn({
-            let board = board.clone();
-            move || {
-                let worker: MinimaxWorker = MinimaxWorker::new(search_state);
-                worker.start_search(board);
-            }
-        });
-    }
-
-    // detach worker thread; will get shut down automatically when search_active gets set to false
-    drop(t_handle);
+    // the worker shuts down when search_active gets cleared, the handle is only for noticing if it panicked (dropping
+    // it detaches the worker)
+    std::thread::spawn({
+        let mut board = board.clone();
+        // saves scanning the houses for has_legal_move and move generation at every node
+        board.track_features(Features::LEGAL_MASK);
+        move || {
+            let worker: MinimaxWorker = MinimaxWorker::new(search_state, search_active, progress);
+            worker.start_search(board);
+        }
+    })
 }
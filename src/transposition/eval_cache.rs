@@ -0,0 +1,96 @@
+use crate::kalah::Valuation;
+
+/*====================================================================================================================*/
+
+/// cache of static evaluations for a single search thread, keyed by the position alone (see Board::zobrist_hash)
+///
+/// lighter than the SharedTable: no depth or bound, an entry is just the evaluator's value for a position, which
+/// doesn't depend on how the search got there. Has a fixed number of slots (a power of two), a store always replaces
+/// the entry in its slot.
+pub struct EvalCache {
+    slots: Box<[Option<(u64, Valuation)>]>,
+
+    probes: u64,
+    hits: u64,
+}
+
+impl EvalCache {
+    /// cache taking up at most size_mb megabytes (but at least one slot)
+    pub fn new(size_mb: usize) -> Self {
+        let max_slots = (size_mb << 20) / std::mem::size_of::<Option<(u64, Valuation)>>();
+
+        // largest power of two that fits, so the slot index is just the low bits of the key
+        let num_slots = if max_slots == 0 { 1 } else { 1 << max_slots.ilog2() };
+
+        EvalCache {
+            slots: vec![None; num_slots].into_boxed_slice(),
+            probes: 0,
+            hits: 0,
+        }
+    }
+
+    pub fn num_slots(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        key as usize & (self.slots.len() - 1)
+    }
+
+    pub fn probe(&mut self, key: u64) -> Option<Valuation> {
+        self.probes += 1;
+
+        match self.slots[self.slot(key)] {
+            Some((entry_key, value)) if entry_key == key => {
+                self.hits += 1;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn store(&mut self, key: u64, value: Valuation) {
+        let slot = self.slot(key);
+        self.slots[slot] = Some((key, value));
+    }
+
+    /// share of the probes so far that found their position, 0 without any probes
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.probes as f64
+        }
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::EvalCache;
+    use crate::kalah::Valuation;
+
+    #[test]
+    fn test_eval_cache() {
+        let mut cache = EvalCache::new(1);
+        assert!(cache.num_slots().is_power_of_two());
+
+        let value = Valuation::NonTerminal { value: 3 };
+
+        assert_eq!(cache.probe(42), None);
+        cache.store(42, value);
+        assert_eq!(cache.probe(42), Some(value));
+
+        // same slot, different key: misses, and replaces the old entry when stored
+        let other = 42 + cache.num_slots() as u64;
+        assert_eq!(cache.probe(other), None);
+        cache.store(other, -value);
+        assert_eq!(cache.probe(other), Some(-value));
+        assert_eq!(cache.probe(42), None);
+
+        assert_eq!(cache.hit_rate(), 2.0 / 5.0);
+
+        assert_eq!(EvalCache::new(0).num_slots(), 1);
+    }
+}
@@ -0,0 +1,5 @@
+mod eval_cache;
+mod shared_table;
+
+pub use eval_cache::EvalCache;
+pub use shared_table::{Bound, Entry, SharedTable};
@@ -0,0 +1,322 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::kalah::Valuation;
+use crate::{Move, Player};
+
+/*====================================================================================================================*/
+
+/// what the value of an entry means, depending on whether the search failed high or low at that node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // the value is at least this (beta cutoff)
+    Lower,
+    // the value is at most this (no move raised alpha)
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub depth: u8,
+    pub bound: Bound,
+    pub value: Valuation,
+    pub best_move: Option<Move>,
+
+    /// search the entry was stored in, see SharedTable::new_search
+    pub generation: u8,
+}
+
+// layout of the data word:
+// bits  0..32 : valuation payload (value as i32 or plies)
+// bits 32..34 : valuation kind
+// bits 34..36 : bound
+// bits 36..44 : depth
+// bits 44..51 : house of the best move
+// bit  51     : player of the best move
+// bit  52     : whether there is a best move
+// bits 53..61 : generation
+// bit  63     : entry is in use, so the all-zero empty slot never validates
+const IN_USE: u64 = 1 << 63;
+
+impl Entry {
+    fn pack(&self) -> u64 {
+        let (kind, payload) = match self.value {
+            Valuation::NonTerminal { value } => (0, value as u32),
+            Valuation::TerminalWhiteWin { plies } => (1, plies),
+            Valuation::TerminalBlackWin { plies } => (2, plies),
+            Valuation::TerminalDraw { plies } => (3, plies),
+        };
+
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+
+        let best_move = match self.best_move {
+            Some(move_) => move_.house() as u64 | ((move_.player() == Player::Black) as u64) << 7 | 1 << 8,
+            None => 0,
+        };
+
+        payload as u64
+            | kind << 32
+            | bound << 34
+            | (self.depth as u64) << 36
+            | best_move << 44
+            | (self.generation as u64) << 53
+            | IN_USE
+    }
+
+    fn unpack(data: u64) -> Entry {
+        let payload = data as u32;
+
+        let value = match (data >> 32) & 0b11 {
+            0 => Valuation::NonTerminal { value: payload as i32 },
+            1 => Valuation::TerminalWhiteWin { plies: payload },
+            2 => Valuation::TerminalBlackWin { plies: payload },
+            _ => Valuation::TerminalDraw { plies: payload },
+        };
+
+        let bound = match (data >> 34) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+
+        let best_move = (data >> 44) as u16 & 0x1ff;
+        let best_move = (best_move & 1 << 8 != 0).then(|| {
+            let player = if best_move & 1 << 7 != 0 {
+                Player::Black
+            } else {
+                Player::White
+            };
+            Move::new((best_move & 0x7f) as u8, player)
+        });
+
+        Entry {
+            depth: (data >> 36) as u8,
+            bound,
+            value,
+            best_move,
+            generation: (data >> 53) as u8,
+        }
+    }
+}
+
+/*====================================================================================================================*/
+
+/// one table entry as two words: the data and the key XOR the data
+///
+/// both words are written and read separately, so a reader racing a writer can see the key word of one entry with
+/// the data word of another. XORing them again then yields a different key than the one the reader is probing for,
+/// so the mismatched pair gets rejected like any other miss.
+#[derive(Default)]
+struct Slot {
+    key_xor_data: AtomicU64,
+    data: AtomicU64,
+}
+
+/// transposition table that any number of search threads can probe and store into without locking
+///
+/// has a fixed number of slots (a power of two), one entry per slot. A store replaces the entry in its slot unless that
+/// one is from the current search and was searched deeper, so old searches' entries get replaced first.
+pub struct SharedTable {
+    slots: Box<[Slot]>,
+
+    generation: AtomicU8,
+}
+
+impl SharedTable {
+    /// table taking up at most size_mb megabytes (but at least one slot)
+    pub fn new(size_mb: usize) -> Self {
+        let max_slots = (size_mb << 20) / std::mem::size_of::<Slot>();
+
+        // largest power of two that fits, so the slot index is just the low bits of the key
+        let num_slots = if max_slots == 0 { 1 } else { 1 << max_slots.ilog2() };
+
+        SharedTable {
+            slots: (0..num_slots).map(|_| Slot::default()).collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    pub fn num_slots(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// start a new search: entries stored from now on take precedence over all older ones
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key_xor_data.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[key as usize & (self.slots.len() - 1)]
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let slot = self.slot(key);
+
+        let data = slot.data.load(Ordering::Relaxed);
+        let key_xor_data = slot.key_xor_data.load(Ordering::Relaxed);
+
+        (data & IN_USE != 0 && key_xor_data ^ data == key).then(|| Entry::unpack(data))
+    }
+
+    pub fn store(&self, key: u64, depth: u8, bound: Bound, value: Valuation, best_move: Option<Move>) {
+        let slot = self.slot(key);
+        let generation = self.generation.load(Ordering::Relaxed);
+
+        let old_data = slot.data.load(Ordering::Relaxed);
+
+        if old_data & IN_USE != 0 {
+            let old = Entry::unpack(old_data);
+            let old_key = slot.key_xor_data.load(Ordering::Relaxed) ^ old_data;
+
+            // keep deeper results of the current search for other positions (a torn read only affects this decision)
+            if old.generation == generation && old.depth > depth && old_key != key {
+                return;
+            }
+        }
+
+        let data = Entry {
+            depth,
+            bound,
+            value,
+            best_move,
+            generation,
+        }
+        .pack();
+
+        slot.key_xor_data.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Bound, Entry, SharedTable};
+    use crate::kalah::Valuation;
+    use crate::{Move, Player};
+
+    #[test]
+    fn test_pack() {
+        let entries = [
+            Entry {
+                depth: 0,
+                bound: Bound::Exact,
+                value: Valuation::NonTerminal { value: i32::MIN },
+                best_move: None,
+                generation: 0,
+            },
+            Entry {
+                depth: 255,
+                bound: Bound::Lower,
+                value: Valuation::TerminalBlackWin { plies: u32::MAX },
+                best_move: Some(Move::new(127, Player::Black)),
+                generation: 255,
+            },
+            Entry {
+                depth: 7,
+                bound: Bound::Upper,
+                value: Valuation::TerminalDraw { plies: 12 },
+                best_move: Some(Move::new(3, Player::White)),
+                generation: 9,
+            },
+        ];
+
+        for entry in entries {
+            assert_eq!(Entry::unpack(entry.pack()), entry);
+        }
+    }
+
+    #[test]
+    fn test_probe_store() {
+        let table = SharedTable::new(1);
+        assert!(table.num_slots().is_power_of_two());
+
+        let value = Valuation::NonTerminal { value: -5 };
+        let best_move = Some(Move::new(2, Player::White));
+
+        assert_eq!(table.probe(42), None);
+        table.store(42, 6, Bound::Exact, value, best_move);
+
+        let entry = table.probe(42).unwrap();
+        assert_eq!((entry.depth, entry.value, entry.best_move), (6, value, best_move));
+
+        // same slot, different key
+        let other = 42 + table.num_slots() as u64;
+        assert_eq!(table.probe(other), None);
+
+        // shallower entry for another position doesn't replace a deeper one of the current search
+        table.store(other, 3, Bound::Lower, value, None);
+        assert_eq!(table.probe(42).unwrap().depth, 6);
+        assert_eq!(table.probe(other), None);
+
+        // but it does after the next search started
+        table.new_search();
+        table.store(other, 3, Bound::Lower, value, None);
+        assert_eq!(table.probe(42), None);
+        assert_eq!(table.probe(other).unwrap().bound, Bound::Lower);
+
+        table.clear();
+        assert_eq!(table.probe(other), None);
+    }
+
+    #[test]
+    fn test_no_torn_reads() {
+        // few slots, so the threads keep overwriting each other's entries
+        let table = Arc::new(SharedTable::new(0));
+        assert_eq!(table.num_slots(), 1);
+
+        // everything about an entry follows from its key, so any mix of two entries can be told apart
+        let entry_for = |key: u64| Entry {
+            depth: key as u8,
+            bound: Bound::Exact,
+            value: Valuation::NonTerminal {
+                value: (key >> 8) as i32,
+            },
+            best_move: Some(Move::new((key % 100) as u8, Player::White)),
+            generation: 0,
+        };
+
+        let threads: Vec<_> = (0..8u64)
+            .map(|thread| {
+                let table = Arc::clone(&table);
+
+                std::thread::spawn(move || {
+                    let mut hits = 0;
+
+                    for i in 0..200_000u64 {
+                        // distinct keys per thread, spread over all bits
+                        let key = (thread << 56 | i).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                        let entry = entry_for(key);
+
+                        table.store(key, entry.depth, entry.bound, entry.value, entry.best_move);
+
+                        // the other threads may have overwritten it already, but never halfway
+                        if let Some(found) = table.probe(key) {
+                            assert_eq!(found, entry_for(key), "Torn read for key {key:#x}");
+                            hits += 1;
+                        }
+                    }
+
+                    hits
+                })
+            })
+            .collect();
+
+        let hits: u64 = threads.into_iter().map(|thread| thread.join().unwrap()).sum();
+        assert!(hits > 0);
+    }
+}
@@ -0,0 +1,42 @@
+/// small seedable random number generator (SplitMix64)
+///
+/// unlike thread_rng its sequence only depends on the seed and never changes with the rand version, so games played
+/// with it can be reproduced exactly
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn gen_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/*====================================================================================================================*/
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_rng() {
+        // first outputs of SplitMix64 for seed 0
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.gen_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.gen_u64(), 0x6e78_9e6a_a1b9_65f4);
+
+        let mut rng1 = Rng::new(42);
+        let mut rng2 = Rng::new(42);
+        assert!((0..100).all(|_| rng1.gen_u64() == rng2.gen_u64()));
+    }
+}